@@ -0,0 +1,6 @@
+MEMORY
+{
+  /* nRF52840 without SoftDevice */
+  FLASH : ORIGIN = 0x00000000, LENGTH = 1024K
+  RAM : ORIGIN = 0x20000000, LENGTH = 256K
+}
@@ -0,0 +1,126 @@
+//! Embassy example for the async SGP30 driver on an nRF52840.
+//!
+//! The sensor is attached to an I²C bus that is shared with other devices
+//! through `embassy-embedded-hal`. A `Ticker` from `embassy-time` drives the
+//! measurements at a steady 1 Hz cadence, independent of how long every
+//! measurement takes.
+//!
+//! During the first 15 s after initialization, the sensor returns fixed
+//! values of 400 ppm CO₂eq and 0 ppb TVOC. This example marks those
+//! measurements as warm-up values. The baseline is restored on startup (if
+//! available) and saved periodically.
+//!
+//! Wiring: SDA on P0.26, SCL on P0.27.
+
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use panic_probe as _;
+
+use embassy_executor::Spawner;
+use embassy_nrf::{bind_interrupts, peripherals, twim, twim::Twim};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use embassy_time::{Delay, Duration, Instant, Ticker};
+use sgp30::{Baseline, SharedBusSgp30Async, Sgp30Async};
+use static_cell::StaticCell;
+
+bind_interrupts!(struct Irqs {
+    TWISPI0 => twim::InterruptHandler<peripherals::TWISPI0>;
+});
+
+type I2cBus = Mutex<NoopRawMutex, Twim<'static>>;
+type Sgp30 = SharedBusSgp30Async<'static, NoopRawMutex, Twim<'static>, Delay>;
+
+/// Number of measurements during which the sensor returns fixed values.
+const WARM_UP_MEASUREMENTS: u32 = 15;
+
+/// Wait this long after a fresh start without a stored baseline before
+/// saving the baseline for the first time (datasheet, section 3.8).
+const FIRST_BASELINE_SAVE_AFTER: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Interval at which the baseline is saved afterwards.
+const BASELINE_SAVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Load a previously stored baseline.
+///
+/// Replace this with access to your non-volatile memory. Baselines older
+/// than 7 days must not be restored.
+async fn load_baseline() -> Option<Baseline> {
+    None
+}
+
+/// Store the baseline in non-volatile memory.
+///
+/// Replace this with access to your non-volatile memory.
+async fn store_baseline(baseline: &Baseline) {
+    defmt::info!(
+        "Storing baseline: CO2eq = {=u16:#06x}, TVOC = {=u16:#06x}",
+        baseline.co2eq,
+        baseline.tvoc
+    );
+}
+
+#[embassy_executor::task]
+async fn sgp30_task(mut sgp: Sgp30) {
+    sgp.init().await.unwrap();
+    let mut next_baseline_save = Instant::now()
+        + match load_baseline().await {
+            Some(baseline) => {
+                sgp.set_baseline(&baseline).await.unwrap();
+                BASELINE_SAVE_INTERVAL
+            }
+            None => FIRST_BASELINE_SAVE_AFTER,
+        };
+
+    let mut ticker = Ticker::every(Duration::from_secs(1));
+    let mut count: u32 = 0;
+    loop {
+        match sgp.measure().await {
+            Ok(measurement) if count < WARM_UP_MEASUREMENTS => defmt::info!(
+                "Warming up: CO2eq = {=u16} ppm, TVOC = {=u16} ppb",
+                measurement.co2eq_ppm,
+                measurement.tvoc_ppb
+            ),
+            Ok(measurement) => defmt::info!(
+                "CO2eq = {=u16} ppm, TVOC = {=u16} ppb",
+                measurement.co2eq_ppm,
+                measurement.tvoc_ppb
+            ),
+            Err(_) => defmt::warn!("Measurement failed"),
+        }
+        count = count.saturating_add(1);
+
+        // Saving the baseline only takes a few milliseconds, so it can be
+        // done in between two measurements without disturbing the cadence.
+        if Instant::now() >= next_baseline_save {
+            if let Ok(baseline) = sgp.get_baseline().await {
+                store_baseline(&baseline).await;
+            }
+            next_baseline_save += BASELINE_SAVE_INTERVAL;
+        }
+
+        ticker.next().await;
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_nrf::init(Default::default());
+
+    static I2C_BUS: StaticCell<I2cBus> = StaticCell::new();
+    let twim = Twim::new(
+        p.TWISPI0,
+        Irqs,
+        p.P0_26,
+        p.P0_27,
+        twim::Config::default(),
+        &mut [],
+    );
+    let i2c_bus = I2C_BUS.init(Mutex::new(twim));
+
+    // Other devices on the same bus get their own `I2cDevice`.
+    let sgp = Sgp30Async::new_shared(i2c_bus, 0x58, Delay);
+
+    spawner.spawn(sgp30_task(sgp).unwrap());
+}
@@ -0,0 +1,58 @@
+//! ESP32 example for the SGP30 driver, using `esp-idf-hal` through
+//! `esp-idf-svc`.
+//!
+//! On the ESP32, the I²C peripheral is not exposed as a `/dev/i2c-*` device
+//! like on Linux. Instead, the `I2cDriver` from `esp-idf-hal` implements the
+//! `embedded-hal` I²C traits and can be passed to the driver directly,
+//! together with the `Delay` provider of `esp-idf-hal`.
+//!
+//! This project requires the Rust ESP toolchain and `ldproxy`, see
+//! <https://docs.esp-rs.org/book/installation/index.html>. Build and flash it
+//! with `cargo run --release` from within this directory.
+//!
+//! Wiring: SDA on GPIO21, SCL on GPIO22.
+
+use esp_idf_svc::hal::{
+    delay::{Delay, FreeRtos},
+    i2c::{I2cConfig, I2cDriver},
+    peripherals::Peripherals,
+    prelude::*,
+};
+use sgp30::Sgp30;
+
+fn main() {
+    // It is necessary to call this function once. Otherwise some patches to
+    // the runtime implemented by esp-idf-sys might not link properly.
+    esp_idf_svc::sys::link_patches();
+    esp_idf_svc::log::EspLogger::initialize_default();
+
+    let peripherals = Peripherals::take().unwrap();
+    let config = I2cConfig::new().baudrate(100.kHz().into());
+    let i2c = I2cDriver::new(
+        peripherals.i2c0,
+        peripherals.pins.gpio21,
+        peripherals.pins.gpio22,
+        &config,
+    )
+    .unwrap();
+
+    // `Delay` busy-waits for short delays and yields to FreeRTOS for longer
+    // ones, which is a good fit for the command execution times.
+    let mut sgp = Sgp30::new(i2c, 0x58, Delay::new_default());
+
+    log::info!("Serial: {:?}", sgp.serial().unwrap());
+    log::info!("Feature set: {:?}", sgp.get_feature_set().unwrap());
+
+    sgp.init().unwrap();
+    loop {
+        match sgp.measure() {
+            Ok(measurement) => log::info!(
+                "CO₂eq = {} ppm, TVOC = {} ppb",
+                measurement.co2eq_ppm,
+                measurement.tvoc_ppb
+            ),
+            Err(e) => log::warn!("Measurement failed: {:?}", e),
+        }
+        FreeRtos::delay_ms(1000 - 12);
+    }
+}
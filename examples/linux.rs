@@ -1,17 +1,43 @@
+use std::{fs::OpenOptions, time::SystemTime};
+
 use embedded_hal::delay::DelayNs;
 use linux_embedded_hal::{Delay, I2cdev};
-use sgp30::Sgp30;
+use sgp30::{
+    csv::{CsvColumns, CsvLogger},
+    Sgp30,
+};
+
+/// File to which the measurements are appended.
+const CSV_FILE: &str = "sgp30.csv";
 
 fn measure_loop(sgp: &mut Sgp30<I2cdev, Delay>) -> ! {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(CSV_FILE)
+        .unwrap();
+    let is_new = file.metadata().unwrap().len() == 0;
+    let columns = CsvColumns {
+        baseline: true,
+        raw_signals: true,
+    };
+    let mut logger = CsvLogger::new(file, columns);
+    if is_new {
+        logger.write_header().unwrap();
+    }
+
     let mut i = 0;
     loop {
         if i != 0 {
             Delay.delay_ms(1000u32 - 12 - 25);
         }
-        if i % 10 == 0 {
+        let baseline = if i % 10 == 0 {
             let baseline = sgp.get_baseline().unwrap();
             println!("Baseline: {} / {}", baseline.co2eq, baseline.tvoc);
-        }
+            Some(baseline)
+        } else {
+            None
+        };
         let measurements = sgp.measure().unwrap();
         let signals = sgp.measure_raw_signals().unwrap();
         println!(
@@ -22,6 +48,14 @@ fn measure_loop(sgp: &mut Sgp30<I2cdev, Delay>) -> ! {
             signals.h2,
             signals.ethanol
         );
+        logger
+            .log(
+                SystemTime::now(),
+                &measurements,
+                baseline.as_ref(),
+                Some(&signals),
+            )
+            .unwrap();
         i += 1;
     }
 }
@@ -46,6 +80,7 @@ fn main() {
     println!();
     println!("Initializing...");
     sgp.init().unwrap();
-    println!("Starting measurement loop, press Ctrl+C to abort...\n");
+    println!("Starting measurement loop, press Ctrl+C to abort...");
+    println!("Measurements are appended to {}\n", CSV_FILE);
     measure_loop(&mut sgp);
 }
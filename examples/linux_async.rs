@@ -0,0 +1,137 @@
+//! Async example for Linux using tokio.
+//!
+//! The Linux I²C device is blocking, so it is wrapped in a small adapter
+//! implementing the `embedded-hal-async` traits on top of tokio. A sensor
+//! task streams measurements at 1 Hz through a channel, while the baseline
+//! is restored from and periodically written to a file.
+//!
+//! Run with `cargo run --example linux_async --features embedded-hal-async`.
+
+use std::time::Duration;
+
+use embedded_hal::i2c::{ErrorType, Operation, SevenBitAddress};
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+use linux_embedded_hal::I2cdev;
+use sgp30::{Baseline, Measurement, Sgp30Async};
+use tokio::{fs, sync::mpsc, time};
+
+/// File in which the baseline is stored.
+const BASELINE_FILE: &str = "sgp30-baseline.txt";
+
+/// Wait this long after a fresh start without a stored baseline before
+/// saving the baseline for the first time (datasheet, section 3.8).
+const FIRST_BASELINE_SAVE_AFTER: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Interval at which the baseline is saved afterwards.
+const BASELINE_SAVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Adapter running the blocking I²C transfers of the wrapped device on the
+/// current worker thread, without blocking the other tasks of the runtime.
+struct TokioI2c<T>(T);
+
+impl<T: ErrorType> ErrorType for TokioI2c<T> {
+    type Error = T::Error;
+}
+
+impl<T: embedded_hal::i2c::I2c> I2c for TokioI2c<T> {
+    async fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        tokio::task::block_in_place(|| self.0.transaction(address, operations))
+    }
+}
+
+/// Async delay using the tokio timer.
+struct TokioDelay;
+
+impl DelayNs for TokioDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        time::sleep(Duration::from_nanos(ns.into())).await;
+    }
+}
+
+type Sgp30 = Sgp30Async<TokioI2c<I2cdev>, TokioDelay>;
+
+/// Load a previously stored baseline.
+///
+/// Baselines older than 7 days must not be restored, so the modification
+/// time of the file is checked as well.
+async fn load_baseline() -> Option<Baseline> {
+    let modified = fs::metadata(BASELINE_FILE).await.ok()?.modified().ok()?;
+    if modified.elapsed().ok()? > Duration::from_secs(7 * 24 * 60 * 60) {
+        return None;
+    }
+    let contents = fs::read_to_string(BASELINE_FILE).await.ok()?;
+    contents.trim().parse().ok()
+}
+
+/// Store the baseline in the baseline file.
+async fn store_baseline(baseline: &Baseline) -> std::io::Result<()> {
+    let contents = format!("{}\n", baseline.to_hex());
+    fs::write(BASELINE_FILE, contents).await
+}
+
+/// Measure at 1 Hz and send the measurements to `tx`, saving the baseline
+/// in between.
+async fn sensor_task(mut sgp: Sgp30, tx: mpsc::Sender<Measurement>) {
+    sgp.init().await.unwrap();
+    let mut next_baseline_save = time::Instant::now()
+        + match load_baseline().await {
+            Some(baseline) => {
+                println!("Restoring baseline: {}", baseline.to_hex());
+                sgp.set_baseline(&baseline).await.unwrap();
+                BASELINE_SAVE_INTERVAL
+            }
+            None => FIRST_BASELINE_SAVE_AFTER,
+        };
+
+    // The interval keeps a steady cadence, independent of how long every
+    // measurement takes.
+    let mut interval = time::interval(Duration::from_secs(1));
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        match sgp.measure().await {
+            Ok(measurement) => {
+                if tx.send(measurement).await.is_err() {
+                    // The receiver is gone
+                    return;
+                }
+            }
+            Err(e) => eprintln!("Measurement failed: {:?}", e),
+        }
+
+        if time::Instant::now() >= next_baseline_save {
+            match sgp.get_baseline().await {
+                Ok(baseline) => {
+                    if let Err(e) = store_baseline(&baseline).await {
+                        eprintln!("Storing baseline failed: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Reading baseline failed: {:?}", e),
+            }
+            next_baseline_save += BASELINE_SAVE_INTERVAL;
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let dev = I2cdev::new("/dev/i2c-1").unwrap();
+    let sgp = Sgp30Async::new(TokioI2c(dev), 0x58, TokioDelay);
+
+    let (tx, mut rx) = mpsc::channel(16);
+    tokio::spawn(sensor_task(sgp, tx));
+
+    println!("Starting measurement loop, press Ctrl+C to abort...\n");
+    let mut i = 0;
+    while let Some(measurement) = rx.recv().await {
+        i += 1;
+        println!(
+            "{}: CO₂eq = {} ppm, TVOC = {} ppb",
+            i, measurement.co2eq_ppm, measurement.tvoc_ppb
+        );
+    }
+}
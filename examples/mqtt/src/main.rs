@@ -0,0 +1,238 @@
+//! MQTT example publishing the measurements to Home Assistant.
+//!
+//! The sensor is sampled at 1 Hz. The fixed values of the 15 s
+//! initialization phase are skipped, and the remaining measurements are
+//! averaged over one minute before they are published together with the
+//! air quality index. TVOC spikes (e.g. from cooking) are detected on the
+//! unfiltered measurements and published immediately as a binary sensor.
+//!
+//! On startup, the sensors are announced through the Home Assistant MQTT
+//! discovery topics, so they show up without any configuration. The broker
+//! defaults to `localhost:1883` and can be changed with the `MQTT_HOST` and
+//! `MQTT_PORT` environment variables.
+//!
+//! Run with `cargo run` from within this directory.
+
+use std::{env, thread, time::Duration};
+
+use linux_embedded_hal::{Delay, I2cdev};
+use rumqttc::{Client, LastWill, MqttOptions, QoS};
+use serde_json::json;
+use sgp30::{
+    aqi::Aqi,
+    pipeline::{Downsample, Processor},
+    sampler::Sampler,
+    spike::{SpikeConfig, SpikeDetector, SpikeEvent},
+    timings::INIT_PHASE_S,
+    Measurement, Sgp30,
+};
+
+/// Prefix of the Home Assistant discovery topics.
+const DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// Number of measurements averaged into one published state.
+const AVERAGE_SAMPLES: u16 = 60;
+
+/// A sensor entity announced to Home Assistant.
+struct Entity {
+    component: &'static str,
+    key: &'static str,
+    name: &'static str,
+    config: serde_json::Value,
+}
+
+/// Return the entities of the sensor.
+fn entities() -> [Entity; 4] {
+    [
+        Entity {
+            component: "sensor",
+            key: "co2eq",
+            name: "CO₂eq",
+            config: json!({
+                "device_class": "carbon_dioxide",
+                "state_class": "measurement",
+                "unit_of_measurement": "ppm",
+                "value_template": "{{ value_json.co2eq }}",
+            }),
+        },
+        Entity {
+            component: "sensor",
+            key: "tvoc",
+            name: "TVOC",
+            config: json!({
+                "device_class": "volatile_organic_compounds_parts",
+                "state_class": "measurement",
+                "unit_of_measurement": "ppb",
+                "value_template": "{{ value_json.tvoc }}",
+            }),
+        },
+        Entity {
+            component: "sensor",
+            key: "aqi",
+            name: "Air quality index",
+            config: json!({
+                "device_class": "aqi",
+                "state_class": "measurement",
+                "value_template": "{{ value_json.aqi }}",
+            }),
+        },
+        Entity {
+            component: "binary_sensor",
+            key: "voc_event",
+            name: "VOC event",
+            config: json!({
+                "payload_on": "ON",
+                "payload_off": "OFF",
+            }),
+        },
+    ]
+}
+
+/// Topics of a sensor, derived from its serial number.
+struct Topics {
+    node_id: String,
+}
+
+impl Topics {
+    fn new(serial: [u8; 6]) -> Self {
+        let hex: String = serial.iter().map(|b| format!("{b:02x}")).collect();
+        Self {
+            node_id: format!("sgp30_{hex}"),
+        }
+    }
+
+    fn availability(&self) -> String {
+        format!("{}/availability", self.node_id)
+    }
+
+    fn state(&self) -> String {
+        format!("{}/state", self.node_id)
+    }
+
+    fn voc_event(&self) -> String {
+        format!("{}/voc_event", self.node_id)
+    }
+
+    fn config(&self, entity: &Entity) -> String {
+        format!(
+            "{DISCOVERY_PREFIX}/{}/{}/{}/config",
+            entity.component, self.node_id, entity.key
+        )
+    }
+}
+
+/// Publish the discovery configuration of all entities.
+fn announce(client: &mut Client, topics: &Topics) {
+    let device = json!({
+        "identifiers": [topics.node_id],
+        "name": "SGP30",
+        "manufacturer": "Sensirion",
+        "model": "SGP30",
+    });
+    for entity in entities() {
+        let state_topic = if entity.key == "voc_event" {
+            topics.voc_event()
+        } else {
+            topics.state()
+        };
+        let topic = topics.config(&entity);
+        let mut config = entity.config;
+        config["name"] = entity.name.into();
+        config["unique_id"] = format!("{}_{}", topics.node_id, entity.key).into();
+        config["state_topic"] = state_topic.into();
+        config["availability_topic"] = topics.availability().into();
+        config["device"] = device.clone();
+        client
+            .publish(topic, QoS::AtLeastOnce, true, config.to_string())
+            .unwrap();
+    }
+}
+
+fn main() {
+    let host = env::var("MQTT_HOST").unwrap_or_else(|_| "localhost".into());
+    let port = env::var("MQTT_PORT")
+        .ok()
+        .map(|port| port.parse().expect("invalid MQTT_PORT"))
+        .unwrap_or(1883);
+
+    let dev = I2cdev::new("/dev/i2c-1").unwrap();
+    let mut sgp = Sgp30::new(dev, 0x58, Delay);
+    let topics = Topics::new(sgp.serial().unwrap());
+    println!("Publishing as {} to {host}:{port}", topics.node_id);
+
+    let mut options = MqttOptions::new(&topics.node_id, host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        topics.availability(),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    let (mut client, mut connection) = Client::new(options, 16);
+    // The connection must be polled for the client to make progress. It
+    // reconnects automatically after errors.
+    thread::spawn(move || {
+        for notification in connection.iter() {
+            if let Err(e) = notification {
+                eprintln!("MQTT error: {e}");
+                thread::sleep(Duration::from_secs(5));
+            }
+        }
+    });
+
+    announce(&mut client, &topics);
+    client
+        .publish(topics.availability(), QoS::AtLeastOnce, true, "online")
+        .unwrap();
+
+    let mut warm_up = INIT_PHASE_S;
+    let mut pipeline = (move |m: Measurement| {
+        if warm_up > 0 {
+            warm_up -= 1;
+            return None;
+        }
+        Some(m)
+    })
+    .chain(Downsample::new(AVERAGE_SAMPLES));
+    let mut spikes = SpikeDetector::new(SpikeConfig::DEFAULT);
+
+    for measurement in Sampler::new(sgp) {
+        let measurement = measurement.unwrap();
+
+        // The fixed values of the initialization phase would distort the
+        // background level
+        let event = if measurement == Measurement::WARM_UP {
+            None
+        } else {
+            spikes.update(&measurement)
+        };
+        match event {
+            Some(SpikeEvent::EventStarted { tvoc_ppb, .. }) => {
+                println!("VOC event started at {tvoc_ppb} ppb");
+                client
+                    .publish(topics.voc_event(), QoS::AtLeastOnce, true, "ON")
+                    .unwrap();
+            }
+            Some(SpikeEvent::EventEnded { peak_ppb, .. }) => {
+                println!("VOC event ended, peak {peak_ppb} ppb");
+                client
+                    .publish(topics.voc_event(), QoS::AtLeastOnce, true, "OFF")
+                    .unwrap();
+            }
+            None => {}
+        }
+
+        if let Some(average) = pipeline.process(measurement) {
+            let aqi = Aqi::from_measurement(&average);
+            let state = json!({
+                "co2eq": average.co2eq_ppm,
+                "tvoc": average.tvoc_ppb,
+                "aqi": aqi.value,
+            });
+            println!("{state}");
+            client
+                .publish(topics.state(), QoS::AtLeastOnce, false, state.to_string())
+                .unwrap();
+        }
+    }
+}
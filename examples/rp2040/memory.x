@@ -0,0 +1,18 @@
+MEMORY
+{
+  /* Raspberry Pi Pico (2 MiB flash). The last 4 KiB sector of the flash is
+   * not part of FLASH, it is reserved for the stored baseline. */
+  BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
+  FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 4K
+  RAM : ORIGIN = 0x20000000, LENGTH = 256K
+}
+
+EXTERN(BOOT2_FIRMWARE)
+
+SECTIONS {
+  /* ### Boot loader */
+  .boot2 ORIGIN(BOOT2) :
+  {
+    KEEP(*(.boot2));
+  } > BOOT2
+} INSERT BEFORE .text;
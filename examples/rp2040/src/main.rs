@@ -0,0 +1,241 @@
+//! Bare-metal example for the blocking SGP30 driver on an RP2040 (Raspberry
+//! Pi Pico).
+//!
+//! The 1 Hz measurement cadence is driven by the 64-bit hardware timer of the
+//! RP2040: Every measurement is scheduled at an absolute timer instant, so
+//! the time spent measuring and storing the baseline doesn't make the period
+//! drift.
+//!
+//! The baseline is stored in the last 4 KiB sector of the on-board flash
+//! (which is excluded from the program memory in `memory.x`). Access to the
+//! flash goes through the `embedded-storage` `NorFlash` traits, so the
+//! baseline persistence functions work unchanged with any other flash or
+//! EEPROM implementing them.
+//!
+//! Note that baselines older than 7 days must not be restored. Since the
+//! RP2040 has no battery-backed clock, this example cannot check the age of
+//! a stored baseline. Add a timestamp to the stored record if your board has
+//! an RTC.
+//!
+//! Wiring: SDA on GP4, SCL on GP5.
+
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use panic_probe as _;
+
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash,
+};
+use rp2040_hal::{
+    clocks::init_clocks_and_plls,
+    entry,
+    fugit::{ExtU64, RateExtU32},
+    gpio::{FunctionI2C, Pin, Pins, PullUp},
+    pac,
+    Clock, Sio, Timer, Watchdog, I2C,
+};
+use sgp30::{Baseline, Sgp30};
+
+/// The second stage boot loader for the W25Q080 flash of the Pico.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// Frequency of the external crystal oscillator of the Pico.
+const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+/// Number of measurements during which the sensor returns fixed values.
+const WARM_UP_MEASUREMENTS: u32 = 15;
+
+/// Wait this long after a fresh start without a stored baseline before
+/// saving the baseline for the first time (datasheet, section 3.8).
+const FIRST_BASELINE_SAVE_AFTER_S: u32 = 12 * 60 * 60;
+
+/// Interval at which the baseline is saved afterwards.
+const BASELINE_SAVE_INTERVAL_S: u32 = 60 * 60;
+
+/// Size of the flash on the Pico.
+const FLASH_SIZE: u32 = 2 * 1024 * 1024;
+
+/// Start of the flash in the XIP address space.
+const FLASH_XIP_BASE: u32 = 0x1000_0000;
+
+/// Marker preceding a stored baseline, to distinguish it from erased flash.
+const BASELINE_MAGIC: [u8; 4] = *b"SGP3";
+
+/// The last sector of the on-board flash, accessed through the
+/// `embedded-storage` traits.
+///
+/// Offsets are relative to the start of the sector.
+struct BaselineSector;
+
+impl BaselineSector {
+    /// Offset of the sector from the start of the flash.
+    const OFFSET: u32 = FLASH_SIZE - Self::ERASE_SIZE as u32;
+}
+
+impl ErrorType for BaselineSector {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for BaselineSector {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        let addr = (FLASH_XIP_BASE + Self::OFFSET + offset) as *const u8;
+        // SAFETY: The range was checked to be within the sector, which is
+        // memory mapped through XIP and not used by the program.
+        let flash = unsafe { core::slice::from_raw_parts(addr, bytes.len()) };
+        bytes.copy_from_slice(flash);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        Self::ERASE_SIZE
+    }
+}
+
+impl NorFlash for BaselineSector {
+    const WRITE_SIZE: usize = 256;
+    const ERASE_SIZE: usize = 4096;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        // SAFETY: This example runs on a single core without DMA, so nothing
+        // else accesses the flash while interrupts are disabled.
+        cortex_m::interrupt::free(|_| unsafe {
+            rp2040_flash::flash::flash_range_erase(Self::OFFSET + from, to - from, true);
+        });
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        // SAFETY: See `erase()`.
+        cortex_m::interrupt::free(|_| unsafe {
+            rp2040_flash::flash::flash_range_program(Self::OFFSET + offset, bytes, true);
+        });
+        Ok(())
+    }
+}
+
+/// Load a previously stored baseline from `flash`.
+fn load_baseline<F: ReadNorFlash>(flash: &mut F) -> Option<Baseline> {
+    let mut record = [0; 8];
+    flash.read(0, &mut record).ok()?;
+    if record[..4] != BASELINE_MAGIC {
+        return None;
+    }
+    // The stored bytes use the same order as `Baseline::as_bytes()`.
+    Some(Baseline {
+        tvoc: u16::from_be_bytes([record[4], record[5]]),
+        co2eq: u16::from_be_bytes([record[6], record[7]]),
+    })
+}
+
+/// Store the baseline in `flash`, replacing the previously stored one.
+fn store_baseline<F: NorFlash>(flash: &mut F, baseline: &Baseline) -> Result<(), F::Error> {
+    // Writes must cover whole pages (256 bytes on most NOR flashes), the
+    // remainder of the page stays erased.
+    let mut page = [0xFF; 256];
+    page[..4].copy_from_slice(&BASELINE_MAGIC);
+    page[4..8].copy_from_slice(&baseline.as_bytes());
+    flash.erase(0, F::ERASE_SIZE as u32)?;
+    flash.write(0, &page)
+}
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let sda: Pin<_, FunctionI2C, PullUp> = pins.gpio4.reconfigure();
+    let scl: Pin<_, FunctionI2C, PullUp> = pins.gpio5.reconfigure();
+    let i2c = I2C::i2c0(
+        pac.I2C0,
+        sda,
+        scl,
+        100.kHz(),
+        &mut pac.RESETS,
+        clocks.system_clock.freq(),
+    );
+
+    // The timer is used both by the driver to wait for the command execution
+    // times and to schedule the measurements.
+    let timer = Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+    let mut sgp = Sgp30::new(i2c, 0x58, timer);
+    let mut flash = BaselineSector;
+
+    sgp.init().unwrap();
+    let mut next_baseline_save = match load_baseline(&mut flash) {
+        Some(baseline) => {
+            defmt::info!(
+                "Restoring baseline: CO2eq = {=u16:#06x}, TVOC = {=u16:#06x}",
+                baseline.co2eq,
+                baseline.tvoc
+            );
+            sgp.set_baseline(&baseline).unwrap();
+            BASELINE_SAVE_INTERVAL_S
+        }
+        None => FIRST_BASELINE_SAVE_AFTER_S,
+    };
+
+    let mut seconds: u32 = 0;
+    let mut next = timer.get_counter();
+    loop {
+        match sgp.measure() {
+            Ok(measurement) if seconds < WARM_UP_MEASUREMENTS => defmt::info!(
+                "Warming up ({=u32} s): CO2eq = {=u16} ppm, TVOC = {=u16} ppb",
+                seconds,
+                measurement.co2eq_ppm,
+                measurement.tvoc_ppb
+            ),
+            Ok(measurement) => defmt::info!(
+                "CO2eq = {=u16} ppm, TVOC = {=u16} ppb",
+                measurement.co2eq_ppm,
+                measurement.tvoc_ppb
+            ),
+            Err(_) => defmt::warn!("Measurement failed"),
+        }
+
+        // Erasing and programming the sector takes a few tens of ms, which
+        // fits easily into the remainder of the 1 s period.
+        if seconds >= next_baseline_save {
+            if let Ok(baseline) = sgp.get_baseline() {
+                if store_baseline(&mut flash, &baseline).is_err() {
+                    defmt::warn!("Storing baseline failed");
+                }
+            }
+            next_baseline_save += BASELINE_SAVE_INTERVAL_S;
+        }
+
+        // Schedule the next measurement exactly one second after the
+        // previous one, independent of how long this iteration took.
+        seconds += 1;
+        next += 1.secs();
+        while timer.get_counter() < next {
+            core::hint::spin_loop();
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! RTIC 2 example for the SGP30 driver on an STM32F411 ("Black Pill").
+//!
+//! The air quality measurement must be performed at a steady cadence of 1 Hz.
+//! Instead of sleeping for a fixed time after every measurement (which makes
+//! the period drift by the duration of the measurement and any other work
+//! done in the loop), this example schedules every measurement at an
+//! absolute instant of the RTIC monotonic using `delay_until`.
+//!
+//! The example also shows where to hook in baseline persistence: A stored
+//! baseline is restored right after initializing the sensor, and the current
+//! baseline is saved periodically.
+//!
+//! Wiring: SCL on PB8, SDA on PB9.
+
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use panic_probe as _;
+
+use rtic_monotonics::systick::prelude::*;
+use sgp30::Baseline;
+
+systick_monotonic!(Mono, 1000);
+
+/// Wait this long after a fresh start without a stored baseline before
+/// saving the baseline for the first time (datasheet, section 3.8).
+const FIRST_BASELINE_SAVE_AFTER_S: u32 = 12 * 60 * 60;
+
+/// Interval at which the baseline is saved afterwards.
+const BASELINE_SAVE_INTERVAL_S: u32 = 60 * 60;
+
+/// Load a previously stored baseline.
+///
+/// Replace this with access to your non-volatile memory. Baselines older
+/// than 7 days must not be restored.
+fn load_baseline() -> Option<Baseline> {
+    None
+}
+
+/// Store the baseline in non-volatile memory.
+///
+/// Replace this with access to your non-volatile memory.
+fn store_baseline(baseline: &Baseline) {
+    defmt::info!(
+        "Storing baseline: CO2eq = {=u16:#06x}, TVOC = {=u16:#06x}",
+        baseline.co2eq,
+        baseline.tvoc
+    );
+}
+
+#[rtic::app(device = stm32f4xx_hal::pac, dispatchers = [USART1])]
+mod app {
+    use stm32f4xx_hal::{
+        i2c::{I2c, Mode},
+        pac::{I2C1, TIM5},
+        prelude::*,
+        timer::Delay,
+    };
+
+    use super::*;
+
+    type Sgp30 = sgp30::Sgp30<I2c<I2C1>, Delay<TIM5, 1_000_000>>;
+
+    #[shared]
+    struct Shared {}
+
+    #[local]
+    struct Local {
+        sgp: Sgp30,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local) {
+        let rcc = ctx.device.RCC.constrain();
+        let clocks = rcc.cfgr.use_hse(25.MHz()).sysclk(48.MHz()).freeze();
+
+        Mono::start(ctx.core.SYST, 48_000_000);
+
+        let gpiob = ctx.device.GPIOB.split();
+        let i2c = I2c::new(
+            ctx.device.I2C1,
+            (gpiob.pb8, gpiob.pb9),
+            Mode::standard(100.kHz()),
+            &clocks,
+        );
+
+        // The driver uses this delay to wait for the command execution times
+        // (at most a few ms), the 1 Hz cadence is driven by the monotonic.
+        let delay = ctx.device.TIM5.delay_us(&clocks);
+
+        let sgp = sgp30::Sgp30::new(i2c, 0x58, delay);
+
+        sample::spawn().ok();
+
+        (Shared {}, Local { sgp })
+    }
+
+    #[task(local = [sgp])]
+    async fn sample(ctx: sample::Context) {
+        let sgp = ctx.local.sgp;
+
+        sgp.init().unwrap();
+        let mut next_baseline_save = match load_baseline() {
+            Some(baseline) => {
+                sgp.set_baseline(&baseline).unwrap();
+                BASELINE_SAVE_INTERVAL_S
+            }
+            None => FIRST_BASELINE_SAVE_AFTER_S,
+        };
+
+        let mut seconds: u32 = 0;
+        let mut next = Mono::now();
+        loop {
+            match sgp.measure() {
+                Ok(measurement) if seconds < 15 => defmt::info!(
+                    "Warming up ({=u32} s): CO2eq = {=u16} ppm, TVOC = {=u16} ppb",
+                    seconds,
+                    measurement.co2eq_ppm,
+                    measurement.tvoc_ppb
+                ),
+                Ok(measurement) => defmt::info!(
+                    "CO2eq = {=u16} ppm, TVOC = {=u16} ppb",
+                    measurement.co2eq_ppm,
+                    measurement.tvoc_ppb
+                ),
+                Err(_) => defmt::warn!("Measurement failed"),
+            }
+
+            if seconds >= next_baseline_save {
+                if let Ok(baseline) = sgp.get_baseline() {
+                    store_baseline(&baseline);
+                }
+                next_baseline_save += BASELINE_SAVE_INTERVAL_S;
+            }
+
+            // Schedule the next measurement exactly one second after the
+            // previous one, independent of how long this iteration took.
+            seconds += 1;
+            next += 1.secs();
+            Mono::delay_until(next).await;
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Browser example running the processing modules as WebAssembly.
+//!
+//! The [`Dashboard`] applies the same warm-up filter, averaging, air quality
+//! index and TVOC spike detection as the MQTT example to measurements
+//! pushed from JavaScript, so dashboards can be prototyped against the
+//! exact crate code that runs on the hardware. The crate does not provide a
+//! sensor simulator, so `index.html` feeds it with synthetic measurements
+//! generated in JavaScript at 1 Hz.
+//!
+//! Build with:
+//!
+//! ```text
+//! cargo build --release
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/sgp30_example_wasm.wasm
+//! ```
+//!
+//! and serve this directory with any static web server.
+
+use sgp30::{
+    aqi::{Aqi, AqiCategory},
+    pipeline::{Chain, Downsample, Processor},
+    spike::{SpikeConfig, SpikeDetector},
+    timings::INIT_PHASE_S,
+    Measurement,
+};
+use wasm_bindgen::prelude::*;
+
+/// A processor dropping the measurements of the initialization phase.
+struct SkipWarmUp(u32);
+
+impl Processor for SkipWarmUp {
+    fn process(&mut self, measurement: Measurement) -> Option<Measurement> {
+        if self.0 > 0 {
+            self.0 -= 1;
+            return None;
+        }
+        Some(measurement)
+    }
+}
+
+/// The processed state shown by the dashboard.
+#[wasm_bindgen]
+pub struct Dashboard {
+    pipeline: Chain<SkipWarmUp, Downsample>,
+    spikes: SpikeDetector,
+    average: Option<Measurement>,
+}
+
+#[wasm_bindgen]
+impl Dashboard {
+    /// Create a dashboard averaging every `average_samples` measurements.
+    #[wasm_bindgen(constructor)]
+    pub fn new(average_samples: u16) -> Self {
+        Self {
+            pipeline: SkipWarmUp(INIT_PHASE_S).chain(Downsample::new(average_samples)),
+            spikes: SpikeDetector::new(SpikeConfig::DEFAULT),
+            average: None,
+        }
+    }
+
+    /// Process a measurement. Return whether a new average is available.
+    pub fn push(&mut self, co2eq_ppm: u16, tvoc_ppb: u16) -> bool {
+        let measurement = Measurement::new(co2eq_ppm, tvoc_ppb);
+        // The fixed values of the initialization phase would distort the
+        // background level. The events are reflected by `voc_event()`.
+        if measurement != Measurement::WARM_UP {
+            self.spikes.update(&measurement);
+        }
+        match self.pipeline.process(measurement) {
+            Some(average) => {
+                self.average = Some(average);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return the most recent averaged CO₂eq value in ppm.
+    pub fn co2eq_ppm(&self) -> Option<u16> {
+        self.average.map(|m| m.co2eq_ppm)
+    }
+
+    /// Return the most recent averaged TVOC value in ppb.
+    pub fn tvoc_ppb(&self) -> Option<u16> {
+        self.average.map(|m| m.tvoc_ppb)
+    }
+
+    /// Return the air quality index of the most recent average.
+    pub fn aqi(&self) -> Option<u16> {
+        self.average.map(|m| Aqi::from_measurement(&m).value)
+    }
+
+    /// Return the category of the air quality index, e.g. `"Moderate"`.
+    pub fn category(&self) -> Option<String> {
+        let category = Aqi::from_measurement(&self.average?).category();
+        Some(
+            match category {
+                AqiCategory::Good => "Good",
+                AqiCategory::Moderate => "Moderate",
+                AqiCategory::UnhealthyForSensitiveGroups => "Unhealthy for sensitive groups",
+                AqiCategory::Unhealthy => "Unhealthy",
+                AqiCategory::VeryUnhealthy => "Very unhealthy",
+                AqiCategory::Hazardous => "Hazardous",
+            }
+            .into(),
+        )
+    }
+
+    /// Return whether a TVOC spike is ongoing.
+    pub fn voc_event(&self) -> bool {
+        self.spikes.in_event()
+    }
+
+    /// Return the TVOC background level in ppb.
+    pub fn background_ppb(&self) -> Option<f32> {
+        self.spikes.background_ppb()
+    }
+}
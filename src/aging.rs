@@ -0,0 +1,231 @@
+//! Estimation of long-term sensor drift from the baseline history.
+//!
+//! The baseline of the SGP30 tracks the sensor's response to clean air. A
+//! baseline that keeps drifting in one direction over weeks and months
+//! indicates an aging sensor. Given the baselines that were saved over the
+//! lifetime of a sensor (see the "Restoring Baseline Values" section in the
+//! crate documentation), [`estimate()`] fits a line through each of the two
+//! baseline values and reports their drift per day:
+//!
+//! ```
+//! use sgp30::{
+//!     aging::{self, AgingLimits, BaselineRecord},
+//!     Baseline,
+//! };
+//!
+//! let history = [
+//!     BaselineRecord::new(0, Baseline { co2eq: 0x8f00, tvoc: 0x9000 }),
+//!     BaselineRecord::new(24 * 30, Baseline { co2eq: 0x8e80, tvoc: 0x8f00 }),
+//!     BaselineRecord::new(24 * 60, Baseline { co2eq: 0x8e00, tvoc: 0x8e00 }),
+//! ];
+//! let estimate = aging::estimate(history).unwrap();
+//! assert_eq!(estimate.tvoc_drift_per_day, -256.0 / 30.0);
+//!
+//! let limits = AgingLimits {
+//!     max_relative_drift_per_day: 0.0001,
+//!     min_span_hours: 24 * 30,
+//! };
+//! assert!(estimate.needs_replacement(&limits));
+//! ```
+//!
+//! The datasheet does not specify limits for the baseline drift, so suitable
+//! [`AgingLimits`] have to be derived from the history of known good sensors.
+
+#[allow(unused_imports)] // Required for no_std
+use num_traits::Float;
+
+use crate::Baseline;
+
+/// A baseline together with the age of the sensor at which it was read.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BaselineRecord {
+    /// The operating time of the sensor in hours when the baseline was read.
+    pub age_hours: u32,
+    /// The baseline.
+    pub baseline: Baseline,
+}
+
+impl BaselineRecord {
+    /// Create a new baseline record.
+    pub const fn new(age_hours: u32, baseline: Baseline) -> Self {
+        Self {
+            age_hours,
+            baseline,
+        }
+    }
+}
+
+/// The estimated drift of the baseline values.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AgingEstimate {
+    /// The number of records the estimate is based on.
+    pub records: usize,
+    /// The time between the oldest and the newest record in hours.
+    pub span_hours: u32,
+    /// Drift of the CO₂eq baseline in ticks per day.
+    pub co2eq_drift_per_day: f32,
+    /// Drift of the TVOC baseline in ticks per day.
+    pub tvoc_drift_per_day: f32,
+    /// Mean of the CO₂eq baseline.
+    pub co2eq_mean: f32,
+    /// Mean of the TVOC baseline.
+    pub tvoc_mean: f32,
+}
+
+impl AgingEstimate {
+    /// Drift of the CO₂eq baseline per day, relative to its mean.
+    pub fn co2eq_relative_drift_per_day(&self) -> f32 {
+        self.co2eq_drift_per_day / self.co2eq_mean
+    }
+
+    /// Drift of the TVOC baseline per day, relative to its mean.
+    pub fn tvoc_relative_drift_per_day(&self) -> f32 {
+        self.tvoc_drift_per_day / self.tvoc_mean
+    }
+
+    /// Return whether the drift of either baseline value exceeds the
+    /// `limits`, i.e. whether the sensor should be replaced.
+    ///
+    /// Estimates spanning less than
+    /// [`min_span_hours`](AgingLimits::min_span_hours) are never flagged.
+    pub fn needs_replacement(&self, limits: &AgingLimits) -> bool {
+        self.span_hours >= limits.min_span_hours
+            && (self.co2eq_relative_drift_per_day().abs() > limits.max_relative_drift_per_day
+                || self.tvoc_relative_drift_per_day().abs() > limits.max_relative_drift_per_day)
+    }
+}
+
+/// Limits for flagging a sensor for replacement.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AgingLimits {
+    /// The maximum acceptable drift per day relative to the mean baseline,
+    /// in either direction (e.g. `0.001` for 0.1 % per day).
+    pub max_relative_drift_per_day: f32,
+    /// The minimum time covered by the history before a sensor is flagged.
+    ///
+    /// Short histories are dominated by environmental changes rather than
+    /// by aging.
+    pub min_span_hours: u32,
+}
+
+/// Running sums for a least-squares line fit.
+#[derive(Default)]
+struct LineFit {
+    n: f64,
+    sum_x: f64,
+    sum_x_sq: f64,
+    sum_y: f64,
+    sum_xy: f64,
+}
+
+impl LineFit {
+    fn add(&mut self, x: f64, y: f64) {
+        self.n += 1.0;
+        self.sum_x += x;
+        self.sum_x_sq += x * x;
+        self.sum_y += y;
+        self.sum_xy += x * y;
+    }
+
+    fn mean(&self) -> f64 {
+        self.sum_y / self.n
+    }
+
+    fn slope(&self) -> f64 {
+        (self.n * self.sum_xy - self.sum_x * self.sum_y)
+            / (self.n * self.sum_x_sq - self.sum_x * self.sum_x)
+    }
+}
+
+/// Estimate the drift of the baseline values from their `history`.
+///
+/// The records may be passed in any order. Returns `None` if the records
+/// don't span a period of time (e.g. if fewer than two records are passed).
+pub fn estimate<I>(history: I) -> Option<AgingEstimate>
+where
+    I: IntoIterator<Item = BaselineRecord>,
+{
+    let mut co2eq = LineFit::default();
+    let mut tvoc = LineFit::default();
+    let mut records = 0;
+    let mut oldest = u32::MAX;
+    let mut newest = u32::MIN;
+    for record in history {
+        let days = f64::from(record.age_hours) / 24.0;
+        co2eq.add(days, f64::from(record.baseline.co2eq));
+        tvoc.add(days, f64::from(record.baseline.tvoc));
+        records += 1;
+        oldest = oldest.min(record.age_hours);
+        newest = newest.max(record.age_hours);
+    }
+    if records < 2 || oldest == newest {
+        return None;
+    }
+    Some(AgingEstimate {
+        records,
+        span_hours: newest - oldest,
+        co2eq_drift_per_day: co2eq.slope() as f32,
+        tvoc_drift_per_day: tvoc.slope() as f32,
+        co2eq_mean: co2eq.mean() as f32,
+        tvoc_mean: tvoc.mean() as f32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(age_hours: u32, co2eq: u16, tvoc: u16) -> BaselineRecord {
+        BaselineRecord::new(age_hours, Baseline { co2eq, tvoc })
+    }
+
+    const LIMITS: AgingLimits = AgingLimits {
+        max_relative_drift_per_day: 0.001,
+        min_span_hours: 24 * 7,
+    };
+
+    #[test]
+    fn too_few_records() {
+        assert_eq!(estimate([]), None);
+        assert_eq!(estimate([record(10, 1000, 1000)]), None);
+        assert_eq!(
+            estimate([record(10, 1000, 1000), record(10, 1100, 1100)]),
+            None
+        );
+    }
+
+    #[test]
+    fn stable() {
+        let history = (0..10).map(|day| record(day * 24, 30_000, 40_000));
+        let estimate = estimate(history).unwrap();
+        assert_eq!(estimate.records, 10);
+        assert_eq!(estimate.span_hours, 9 * 24);
+        assert_eq!(estimate.co2eq_drift_per_day, 0.0);
+        assert_eq!(estimate.tvoc_drift_per_day, 0.0);
+        assert_eq!(estimate.co2eq_mean, 30_000.0);
+        assert!(!estimate.needs_replacement(&LIMITS));
+    }
+
+    #[test]
+    fn drifting() {
+        // Unordered, TVOC baseline decreasing by 100 ticks per day
+        let history = [
+            record(48, 30_000, 39_800),
+            record(0, 30_000, 40_000),
+            record(24 * 10, 30_000, 39_000),
+        ];
+        let estimate = estimate(history).unwrap();
+        assert_eq!(estimate.span_hours, 240);
+        assert_eq!(estimate.co2eq_drift_per_day, 0.0);
+        assert_eq!(estimate.tvoc_drift_per_day, -100.0);
+        assert!(estimate.needs_replacement(&LIMITS));
+    }
+
+    #[test]
+    fn short_history_not_flagged() {
+        let history = [record(0, 30_000, 40_000), record(24, 30_000, 39_000)];
+        let estimate = estimate(history).unwrap();
+        assert!(estimate.tvoc_relative_drift_per_day() < -0.02);
+        assert!(!estimate.needs_replacement(&LIMITS));
+    }
+}
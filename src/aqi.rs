@@ -0,0 +1,241 @@
+//! A composite air quality index.
+//!
+//! Displays and status LEDs usually need a single number instead of the two
+//! values of a [`Measurement`]. The [`Aqi`] maps both values to a sub-index
+//! on a 0–500 scale using piecewise linear curves, and reports the larger
+//! one as the composite index, similar to the US EPA AQI:
+//!
+//! ```
+//! use sgp30::{
+//!     aqi::{Aqi, AqiCategory},
+//!     Measurement,
+//! };
+//!
+//! let aqi = Aqi::from_measurement(&Measurement::new(800, 100));
+//! assert_eq!(aqi.value, 75);
+//! assert_eq!(aqi.category(), AqiCategory::Moderate);
+//! ```
+//!
+//! The default curves ([`AqiCurves::DEFAULT`]) are loosely based on common
+//! indoor air quality guidelines for CO₂ and TVOC. They are not an official
+//! index, and custom curves can be passed using [`Aqi::with_curves()`].
+
+use crate::Measurement;
+
+/// The highest value of the index.
+pub const AQI_MAX: u16 = 500;
+
+/// A point of a piecewise linear curve, mapping a concentration to an index
+/// value.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Breakpoint {
+    /// The concentration (in ppm for CO₂eq, in ppb for TVOC)
+    pub concentration: u16,
+    /// The index value at this concentration
+    pub index: u16,
+}
+
+impl Breakpoint {
+    /// Create a new breakpoint.
+    pub const fn new(concentration: u16, index: u16) -> Self {
+        Self {
+            concentration,
+            index,
+        }
+    }
+}
+
+/// The curves mapping the two measurement values to a sub-index.
+///
+/// The breakpoints of each curve must be sorted by ascending concentration.
+/// Concentrations below the first breakpoint map to the index of the first
+/// breakpoint, concentrations above the last breakpoint to the index of the
+/// last breakpoint. The index is clamped to [`AQI_MAX`].
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct AqiCurves<'a> {
+    /// Curve for the CO₂eq value in ppm
+    pub co2eq: &'a [Breakpoint],
+    /// Curve for the TVOC value in ppb
+    pub tvoc: &'a [Breakpoint],
+}
+
+impl AqiCurves<'static> {
+    /// The default curves.
+    ///
+    /// | Index | CO₂eq (ppm) | TVOC (ppb) |
+    /// |-------|-------------|------------|
+    /// | 0     | 400         | 0          |
+    /// | 50    | 600         | 65         |
+    /// | 100   | 1000        | 220        |
+    /// | 150   | 1500        | 660        |
+    /// | 200   | 2000        | 2200       |
+    /// | 300   | 5000        | 5500       |
+    /// | 500   | 60000       | 60000      |
+    pub const DEFAULT: Self = Self {
+        co2eq: &[
+            Breakpoint::new(400, 0),
+            Breakpoint::new(600, 50),
+            Breakpoint::new(1000, 100),
+            Breakpoint::new(1500, 150),
+            Breakpoint::new(2000, 200),
+            Breakpoint::new(5000, 300),
+            Breakpoint::new(60000, 500),
+        ],
+        tvoc: &[
+            Breakpoint::new(0, 0),
+            Breakpoint::new(65, 50),
+            Breakpoint::new(220, 100),
+            Breakpoint::new(660, 150),
+            Breakpoint::new(2200, 200),
+            Breakpoint::new(5500, 300),
+            Breakpoint::new(60000, 500),
+        ],
+    };
+}
+
+impl Default for AqiCurves<'static> {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Evaluate the piecewise linear `curve` at `concentration`.
+fn evaluate(curve: &[Breakpoint], concentration: u16) -> u16 {
+    let (first, last) = match (curve.first(), curve.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return 0,
+    };
+    let index = if concentration <= first.concentration {
+        first.index
+    } else if concentration >= last.concentration {
+        last.index
+    } else {
+        curve
+            .windows(2)
+            .find(|pair| concentration <= pair[1].concentration)
+            .map_or(last.index, |pair| {
+                let (low, high) = (pair[0], pair[1]);
+                let span = u32::from(high.concentration - low.concentration);
+                let offset = u32::from(concentration - low.concentration);
+                let (from, to) = (i64::from(low.index), i64::from(high.index));
+                // The result lies between the two indices, so it fits into a u16
+                (from + (to - from) * i64::from(offset) / i64::from(span)) as u16
+            })
+    };
+    index.min(AQI_MAX)
+}
+
+/// The category of an index value.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum AqiCategory {
+    /// 0–50
+    Good,
+    /// 51–100
+    Moderate,
+    /// 101–150
+    UnhealthyForSensitiveGroups,
+    /// 151–200
+    Unhealthy,
+    /// 201–300
+    VeryUnhealthy,
+    /// 301–500
+    Hazardous,
+}
+
+impl AqiCategory {
+    /// Return the category of the index `value`.
+    pub const fn from_value(value: u16) -> Self {
+        match value {
+            0..=50 => AqiCategory::Good,
+            51..=100 => AqiCategory::Moderate,
+            101..=150 => AqiCategory::UnhealthyForSensitiveGroups,
+            151..=200 => AqiCategory::Unhealthy,
+            201..=300 => AqiCategory::VeryUnhealthy,
+            _ => AqiCategory::Hazardous,
+        }
+    }
+}
+
+/// A composite air quality index.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Aqi {
+    /// The composite index, i.e. the larger of the two sub-indices
+    pub value: u16,
+    /// The sub-index of the CO₂eq value
+    pub co2eq_index: u16,
+    /// The sub-index of the TVOC value
+    pub tvoc_index: u16,
+}
+
+impl Aqi {
+    /// Compute the index of a measurement using the
+    /// [default curves](AqiCurves::DEFAULT).
+    pub fn from_measurement(measurement: &Measurement) -> Self {
+        Self::with_curves(measurement, &AqiCurves::DEFAULT)
+    }
+
+    /// Compute the index of a measurement using custom `curves`.
+    pub fn with_curves(measurement: &Measurement, curves: &AqiCurves<'_>) -> Self {
+        let co2eq_index = evaluate(curves.co2eq, measurement.co2eq_ppm);
+        let tvoc_index = evaluate(curves.tvoc, measurement.tvoc_ppb);
+        Self {
+            value: co2eq_index.max(tvoc_index),
+            co2eq_index,
+            tvoc_index,
+        }
+    }
+
+    /// Return the category of the composite index.
+    pub const fn category(&self) -> AqiCategory {
+        AqiCategory::from_value(self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_curves() {
+        let aqi = Aqi::from_measurement(&Measurement::new(400, 0));
+        assert_eq!(aqi.value, 0);
+        assert_eq!(aqi.category(), AqiCategory::Good);
+
+        let aqi = Aqi::from_measurement(&Measurement::new(1250, 65));
+        assert_eq!(aqi.co2eq_index, 125);
+        assert_eq!(aqi.tvoc_index, 50);
+        assert_eq!(aqi.value, 125);
+        assert_eq!(aqi.category(), AqiCategory::UnhealthyForSensitiveGroups);
+
+        let aqi = Aqi::from_measurement(&Measurement::new(u16::MAX, u16::MAX));
+        assert_eq!(aqi.value, AQI_MAX);
+        assert_eq!(aqi.category(), AqiCategory::Hazardous);
+    }
+
+    #[test]
+    fn custom_curves() {
+        // A decreasing curve, and a curve exceeding the maximum
+        let curves = AqiCurves {
+            co2eq: &[Breakpoint::new(0, 100), Breakpoint::new(100, 0)],
+            tvoc: &[Breakpoint::new(1000, 1000)],
+        };
+        let aqi = Aqi::with_curves(&Measurement::new(25, 0), &curves);
+        assert_eq!(aqi.co2eq_index, 75);
+        assert_eq!(aqi.tvoc_index, AQI_MAX);
+
+        let empty = AqiCurves {
+            co2eq: &[],
+            tvoc: &[],
+        };
+        assert_eq!(Aqi::with_curves(&Measurement::new(400, 0), &empty).value, 0);
+    }
+
+    #[test]
+    fn categories() {
+        assert_eq!(AqiCategory::from_value(50), AqiCategory::Good);
+        assert_eq!(AqiCategory::from_value(51), AqiCategory::Moderate);
+        assert_eq!(AqiCategory::from_value(200), AqiCategory::Unhealthy);
+        assert_eq!(AqiCategory::from_value(201), AqiCategory::VeryUnhealthy);
+        assert!(AqiCategory::Good < AqiCategory::Hazardous);
+    }
+}
@@ -0,0 +1,283 @@
+//! Multi-resolution archive of measurements.
+//!
+//! The [`Archive`] keeps the recent history of the measurements at three
+//! resolutions, similar to a round-robin database: The measurements are
+//! averaged into points of the fine series, which are in turn averaged into
+//! the points of the medium series, and those into the coarse series. Each
+//! series is a ring buffer holding a fixed number of points, with the
+//! oldest point being overwritten when it is full.
+//!
+//! With the [default configuration](ArchiveConfig::DEFAULT) and measurements
+//! taken at 1 Hz, [`DefaultArchive`] keeps the last hour at 1 minute, the
+//! last day at 15 minutes and the last week at 1 hour resolution in less
+//! than 1.5 kB of RAM, e.g. for drawing graphs in a local user interface:
+//!
+//! ```
+//! use sgp30::{
+//!     archive::{ArchiveConfig, DefaultArchive},
+//!     Measurement,
+//! };
+//!
+//! let mut archive = DefaultArchive::new(ArchiveConfig::DEFAULT);
+//! for i in 0..2 * 60 * 60 {
+//!     archive.update(&Measurement::new(400 + i / 60, 0));
+//! }
+//! // The fine series holds the minutes of the last hour
+//! assert_eq!(archive.fine().len(), 60);
+//! assert_eq!(archive.fine().latest(), Some(Measurement::new(519, 0)));
+//! // The medium series holds 8 quarter hours
+//! assert_eq!(archive.medium().len(), 8);
+//! assert_eq!(archive.coarse().len(), 2);
+//! assert_eq!(
+//!     archive.coarse().iter().collect::<Vec<_>>(),
+//!     [Measurement::new(429, 0), Measurement::new(489, 0)]
+//! );
+//! ```
+//!
+//! The archive only keeps points which were completely averaged, so the
+//! first point of the fine series appears after 60 measurements.
+
+use crate::{
+    pipeline::{Downsample, Processor},
+    Measurement,
+};
+
+/// A fixed-size ring buffer of averaged measurements.
+#[derive(Debug, Clone)]
+pub struct Series<const N: usize> {
+    points: [Measurement; N],
+    /// Index of the slot for the next point.
+    next: usize,
+    len: usize,
+}
+
+impl<const N: usize> Series<N> {
+    /// Create a new empty series.
+    pub const fn new() -> Self {
+        Self {
+            points: [Measurement::new(0, 0); N],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    /// Append a point, overwriting the oldest point if the series is full.
+    pub fn push(&mut self, point: Measurement) {
+        if N == 0 {
+            return;
+        }
+        self.points[self.next] = point;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Return the number of points in the series.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return whether the series is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the maximum number of points in the series.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Return the newest point, if any.
+    pub fn latest(&self) -> Option<Measurement> {
+        self.iter().next_back()
+    }
+
+    /// Iterate over the points, from the oldest to the newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Measurement> + '_ {
+        // Before the series is full, the points start at index 0 and `next`
+        // equals `len`. Afterwards, the oldest point is at `next`.
+        let start = if self.len < N { 0 } else { self.next };
+        let (newer, older) = self.points[..self.len].split_at(start);
+        older.iter().chain(newer).copied()
+    }
+
+    /// Remove all points.
+    pub fn clear(&mut self) {
+        self.next = 0;
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for Series<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The averaging factors of an [`Archive`].
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct ArchiveConfig {
+    /// The number of measurements averaged into a point of the fine series.
+    pub fine_factor: u16,
+    /// The number of fine points averaged into a point of the medium series.
+    pub medium_factor: u16,
+    /// The number of medium points averaged into a point of the coarse
+    /// series.
+    pub coarse_factor: u16,
+}
+
+impl ArchiveConfig {
+    /// Default configuration for measurements taken at 1 Hz: 1 minute,
+    /// 15 minutes and 1 hour per point.
+    pub const DEFAULT: Self = Self {
+        fine_factor: 60,
+        medium_factor: 15,
+        coarse_factor: 4,
+    };
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// An archive with `F` fine, `M` medium and `C` coarse points, see the
+/// [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Archive<const F: usize, const M: usize, const C: usize> {
+    config: ArchiveConfig,
+    fine_downsample: Downsample,
+    medium_downsample: Downsample,
+    coarse_downsample: Downsample,
+    fine: Series<F>,
+    medium: Series<M>,
+    coarse: Series<C>,
+}
+
+/// An archive for the [default configuration](ArchiveConfig::DEFAULT),
+/// holding the last hour, day and week.
+pub type DefaultArchive = Archive<60, 96, 168>;
+
+impl<const F: usize, const M: usize, const C: usize> Archive<F, M, C> {
+    /// Create a new empty archive.
+    pub const fn new(config: ArchiveConfig) -> Self {
+        Self {
+            config,
+            fine_downsample: Downsample::new(config.fine_factor),
+            medium_downsample: Downsample::new(config.medium_factor),
+            coarse_downsample: Downsample::new(config.coarse_factor),
+            fine: Series::new(),
+            medium: Series::new(),
+            coarse: Series::new(),
+        }
+    }
+
+    /// Add a measurement.
+    pub fn update(&mut self, measurement: &Measurement) {
+        let Some(fine) = self.fine_downsample.process(*measurement) else {
+            return;
+        };
+        self.fine.push(fine);
+        let Some(medium) = self.medium_downsample.process(fine) else {
+            return;
+        };
+        self.medium.push(medium);
+        if let Some(coarse) = self.coarse_downsample.process(medium) {
+            self.coarse.push(coarse);
+        }
+    }
+
+    /// Return the series with the highest resolution.
+    pub const fn fine(&self) -> &Series<F> {
+        &self.fine
+    }
+
+    /// Return the series with the medium resolution.
+    pub const fn medium(&self) -> &Series<M> {
+        &self.medium
+    }
+
+    /// Return the series with the lowest resolution.
+    pub const fn coarse(&self) -> &Series<C> {
+        &self.coarse
+    }
+
+    /// Remove all points and discard the partially averaged measurements.
+    pub fn clear(&mut self) {
+        *self = Self::new(self.config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn co2eq(ppm: u16) -> Measurement {
+        Measurement::new(ppm, 0)
+    }
+
+    #[test]
+    fn series_wrap_around() {
+        let mut series = Series::<3>::new();
+        assert!(series.is_empty());
+        assert_eq!(series.latest(), None);
+        assert_eq!(series.iter().count(), 0);
+        for ppm in 1..=2 {
+            series.push(co2eq(ppm));
+        }
+        assert_eq!(series.iter().collect::<Vec<_>>(), [co2eq(1), co2eq(2)]);
+        for ppm in 3..=7 {
+            series.push(co2eq(ppm));
+        }
+        assert_eq!(series.len(), 3);
+        assert_eq!(
+            series.iter().collect::<Vec<_>>(),
+            [co2eq(5), co2eq(6), co2eq(7)]
+        );
+        assert_eq!(series.latest(), Some(co2eq(7)));
+        series.clear();
+        assert!(series.is_empty());
+
+        // A series without capacity stays empty
+        let mut series = Series::<0>::new();
+        series.push(co2eq(1));
+        assert!(series.is_empty());
+    }
+
+    #[test]
+    fn cascade() {
+        let config = ArchiveConfig {
+            fine_factor: 2,
+            medium_factor: 2,
+            coarse_factor: 3,
+        };
+        let mut archive = Archive::<4, 2, 2>::new(config);
+        for ppm in 0..24 {
+            archive.update(&Measurement::new(ppm * 10, ppm));
+        }
+        // Averages of 2, 4 and 12 measurements
+        assert_eq!(
+            archive.fine().iter().collect::<Vec<_>>(),
+            [
+                Measurement::new(165, 16),
+                Measurement::new(185, 18),
+                Measurement::new(205, 20),
+                Measurement::new(225, 22)
+            ]
+        );
+        assert_eq!(
+            archive.medium().iter().collect::<Vec<_>>(),
+            [Measurement::new(175, 17), Measurement::new(215, 21)]
+        );
+        assert_eq!(
+            archive.coarse().iter().collect::<Vec<_>>(),
+            [Measurement::new(55, 5), Measurement::new(175, 17)]
+        );
+
+        archive.clear();
+        archive.update(&co2eq(1000));
+        assert!(archive.fine().is_empty());
+        archive.update(&co2eq(1000));
+        assert_eq!(archive.fine().latest(), Some(co2eq(1000)));
+    }
+}
@@ -1,7 +1,19 @@
-use super::{types::*, Command, Error, SELFTEST_SUCCESS};
-use byteorder::{BigEndian, ByteOrder};
+#[cfg(feature = "error-history")]
+use super::ErrorHistory;
+use super::{
+    commands::{self, CommandData, CommandId},
+    sampler::{RunConfig, RunEvent},
+    timings::SAMPLE_INTERVAL_US,
+    transport::is_address_nack,
+    types::*,
+    Correction, Error, MeasureBuf, SerialBuf, SELFTEST_SUCCESS,
+};
+#[cfg(feature = "embassy")]
+use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+#[cfg(feature = "embassy")]
+use embassy_sync::{blocking_mutex::raw::RawMutex, mutex::Mutex};
 use embedded_hal_async::{delay::DelayNs, i2c::I2c};
-use sensirion_i2c::i2c_async;
+use sensirion_i2c::crc8;
 
 /// Async driver for the SGP30.
 ///
@@ -17,6 +29,29 @@ pub struct Sgp30Async<I2C, D> {
     delay: D,
     /// Whether the air quality measurement was initialized.
     initialized: bool,
+    /// Whether to use combined write/read transactions where possible.
+    combined_reads: bool,
+    /// Whether the raw signals can be measured without initialization.
+    raw_signals_only: bool,
+    /// Whether the CRC checksums of responses are not validated.
+    skip_crc: bool,
+    /// The additional delay before reading a response, in microseconds.
+    extra_read_delay_us: u32,
+    /// The interval for polling responses until they are ready, if enabled.
+    poll_interval_us: Option<u32>,
+    /// The correction applied to every air quality measurement.
+    correction: Option<Correction>,
+    /// Whether a humidity value for compensation was sent to the sensor.
+    humidity_compensation: bool,
+    /// The most recent successful air quality measurement.
+    last_measurement: Option<Measurement>,
+    /// The most recent successful raw signals measurement.
+    last_raw_signals: Option<RawSignals>,
+    /// The feature set read from the sensor.
+    feature_set: Option<FeatureSet>,
+    /// The most recent bus and CRC errors.
+    #[cfg(feature = "error-history")]
+    error_history: ErrorHistory,
 }
 
 impl<I2C, D> Sgp30Async<I2C, D>
@@ -25,72 +60,393 @@ where
     D: DelayNs,
 {
     /// Create a new instance of the SGP30 driver.
-    pub fn new(i2c: I2C, address: u8, delay: D) -> Self {
+    pub const fn new(i2c: I2C, address: u8, delay: D) -> Self {
         Self {
             i2c,
             address,
             delay,
             initialized: false,
+            combined_reads: false,
+            raw_signals_only: false,
+            skip_crc: false,
+            extra_read_delay_us: 0,
+            poll_interval_us: None,
+            correction: None,
+            humidity_compensation: false,
+            last_measurement: None,
+            last_raw_signals: None,
+            feature_set: None,
+            #[cfg(feature = "error-history")]
+            error_history: ErrorHistory::new(),
         }
     }
 
+    /// Create a new instance of the SGP30 driver for a sensor which is
+    /// already measuring.
+    ///
+    /// Use this if the microcontroller was reset while the sensor kept its
+    /// power supply, and the air quality measurement was initialized before
+    /// the reset. Sending the init command again would restart the 15 s
+    /// initialization phase and reset the baseline, so this constructor marks
+    /// the driver as initialized instead, allowing to resume measuring
+    /// immediately.
+    ///
+    /// Note that the 1 s measurement interval should be kept up across the
+    /// reset as well as possible.
+    pub const fn assume_initialized(i2c: I2C, address: u8, delay: D) -> Self {
+        let mut sgp = Self::new(i2c, address, delay);
+        sgp.initialized = true;
+        sgp
+    }
+
     /// Destroy driver instance, return I²C bus instance.
     pub fn destroy(self) -> I2C {
         self.i2c
     }
 
+    /// Replace the I²C bus instance, keeping the state of the driver.
+    ///
+    /// Some HALs can only recover from fatal bus errors by re-creating the
+    /// I²C peripheral. This constructs a driver around `i2c`, carrying over
+    /// the address, the initialization state and all other settings, so the
+    /// 1 s measurements can be continued without sending the init command
+    /// again. The baseline is kept by the sensor itself and is not affected.
+    ///
+    /// The old I²C bus instance is dropped.
+    pub fn rebuild(self, i2c: I2C) -> Self {
+        Self { i2c, ..self }
+    }
+
+    /// Return whether the air quality measurement was initialized.
+    ///
+    /// This is set by [`init()`](Self::init) and
+    /// [`force_init()`](Self::force_init).
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// Return whether humidity compensation with a humidity value sent by
+    /// this driver is active.
+    ///
+    /// This is set by [`set_humidity()`](Self::set_humidity) and cleared by
+    /// [`disable_humidity_compensation()`](Self::disable_humidity_compensation).
+    /// Note that a soft reset or power cycle of the sensor also resets the
+    /// humidity to its default value, which is not tracked by the driver.
+    pub fn humidity_compensation_active(&self) -> bool {
+        self.humidity_compensation
+    }
+
+    /// Return the most recent successful air quality measurement, without
+    /// communicating with the sensor.
+    ///
+    /// This allows answering requests (e.g. Modbus or BLE reads) immediately
+    /// without adding bus traffic to the 1 s measurement cadence. The
+    /// [correction](Self::set_correction) is already applied.
+    pub fn last_measurement(&self) -> Option<Measurement> {
+        self.last_measurement
+    }
+
+    /// Return the most recent successful raw signals measurement, without
+    /// communicating with the sensor.
+    pub fn last_raw_signals(&self) -> Option<RawSignals> {
+        self.last_raw_signals
+    }
+
+    /// Return the history of the most recent bus and CRC errors.
+    ///
+    /// This can be used to report what went wrong on devices without any
+    /// logging infrastructure.
+    #[cfg(feature = "error-history")]
+    pub fn recent_errors(&self) -> &ErrorHistory {
+        &self.error_history
+    }
+
+    /// Remove all entries from the error history.
+    #[cfg(feature = "error-history")]
+    pub fn clear_recent_errors(&mut self) {
+        self.error_history.clear();
+    }
+
+    /// Enable or disable combined write/read transactions.
+    ///
+    /// By default, every command is sent in a separate write, followed by
+    /// the maximum command duration specified in the datasheet and a separate
+    /// read. When enabled, commands with a very short execution time (the
+    /// serial number and feature set commands) are instead issued as a single
+    /// `write_read` transaction, which reduces bus arbitration overhead and
+    /// latency on busy shared buses.
+    ///
+    /// Since no delay is inserted between the command and the read in this
+    /// mode, the sensor might NACK the read if the response isn't ready yet.
+    /// Only enable this option after verifying that it works with your I²C
+    /// master and bus speed.
+    pub fn set_combined_reads(&mut self, enabled: bool) {
+        self.combined_reads = enabled;
+    }
+
+    /// Enable or disable the raw signals only mode.
+    ///
+    /// By default, the [raw signals](Self::measure_raw_signals) can only be
+    /// measured after the air quality measurement was initialized. The
+    /// sensor doesn't require this for the raw signals, so when enabled,
+    /// they can be measured without calling [`init()`](Self::init) and
+    /// without keeping up the 1 Hz cadence of the air quality measurement,
+    /// e.g. on part verification rigs or in applications which only use the
+    /// H2 and Ethanol signals.
+    pub fn set_raw_signals_only(&mut self, enabled: bool) {
+        self.raw_signals_only = enabled;
+    }
+
+    /// Return whether the raw signals only mode is enabled, see
+    /// [`set_raw_signals_only()`](Self::set_raw_signals_only).
+    pub fn raw_signals_only(&self) -> bool {
+        self.raw_signals_only
+    }
+
+    /// Enable or disable the CRC check of responses.
+    ///
+    /// By default, the CRC checksum of every word read from the sensor is
+    /// validated, and a single flipped bit fails the whole command with an
+    /// [`Error::Crc`]. On trusted buses (e.g. a short point-to-point
+    /// connection on the same board), the check can be skipped to save the
+    /// computation and to avoid these failures. This is a trade-off: A
+    /// corrupted word is then returned as a wrong value without any
+    /// indication, e.g. as a spike in the measurements or as a wrong
+    /// baseline.
+    ///
+    /// The CRC checksums of the data written to the sensor are always
+    /// computed, since the sensor requires them.
+    pub fn set_skip_crc(&mut self, enabled: bool) {
+        self.skip_crc = enabled;
+    }
+
+    /// Return whether the CRC check of responses is skipped, see
+    /// [`set_skip_crc()`](Self::set_skip_crc).
+    pub fn skip_crc(&self) -> bool {
+        self.skip_crc
+    }
+
+    /// Set an additional delay in microseconds, which is inserted between
+    /// sending a command and reading its response, on top of the command
+    /// durations specified in the datasheet.
+    ///
+    /// Some I²C masters (e.g. bit-banged ones) need more margin before the
+    /// response can be read. The delay does not apply to
+    /// [combined reads](Self::set_combined_reads). Defaults to 0.
+    pub fn set_extra_read_delay_us(&mut self, delay_us: u32) {
+        self.extra_read_delay_us = delay_us;
+    }
+
+    /// Return the additional delay before reading a response, see
+    /// [`set_extra_read_delay_us()`](Self::set_extra_read_delay_us).
+    pub fn extra_read_delay_us(&self) -> u32 {
+        self.extra_read_delay_us
+    }
+
+    /// Enable or disable polling for responses.
+    ///
+    /// By default, the driver waits for the maximum command duration
+    /// specified in the datasheet before reading a response, e.g. 220 ms
+    /// for the self-test and 25 ms for the raw signals. The sensor NACKs
+    /// reads while a command is still being processed, so with a polling
+    /// interval set, the driver instead only waits for the typical command
+    /// duration and then retries NACKed reads every `interval_us`, until the
+    /// maximum command duration has passed. This reduces the latency and
+    /// the time spent waiting.
+    ///
+    /// Commands without a response still wait for their maximum duration.
+    /// A read counts as NACKed if the
+    /// [`ErrorKind`](embedded_hal::i2c::ErrorKind) of the bus error is
+    /// `ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)`.
+    pub fn set_poll_interval_us(&mut self, interval_us: Option<u32>) {
+        self.poll_interval_us = interval_us;
+    }
+
+    /// Return the interval for polling responses, see
+    /// [`set_poll_interval_us()`](Self::set_poll_interval_us).
+    pub fn poll_interval_us(&self) -> Option<u32> {
+        self.poll_interval_us
+    }
+
+    /// Set a correction that is applied to every air quality measurement
+    /// before it is returned, or remove it by passing `None`.
+    ///
+    /// This can be used to apply per-installation calibrations, e.g. to
+    /// compensate a known cross-sensitivity or a co-located heat source, in
+    /// a single place. The correction is not applied to the raw signals.
+    pub fn set_correction(&mut self, correction: Option<Correction>) {
+        self.correction = correction;
+    }
+
     /// Write an I²C command to the sensor.
-    async fn send_command(&mut self, command: Command) -> Result<(), Error<I2C::Error>> {
-        self.i2c
+    async fn send_command(&mut self, command: CommandId) -> Result<(), Error<I2C::Error>> {
+        let result = self
+            .i2c
             .write(self.address, &command.as_bytes())
             .await
-            .map_err(Error::I2cWrite)
+            .map_err(|source| Error::from_write(command, source, is_address_nack));
+        self.record(result)
     }
 
     /// Write an I²C command and data to the sensor.
     ///
-    /// The data slice must have a length of 2 or 4.
-    ///
-    /// CRC checksums will automatically be added to the data.
+    /// The data is a single word ([`CommandData::Data2`]) or two words
+    /// ([`CommandData::Data4`]), a CRC checksum is automatically added after
+    /// every word.
     async fn send_command_and_data(
         &mut self,
-        command: Command,
-        data: &[u8],
+        command: CommandId,
+        data: CommandData,
     ) -> Result<(), Error<I2C::Error>> {
         let mut buf = [0; 2 /* command */ + 6 /* max length of data + crc */];
         let payload = command.as_bytes_with_data(&mut buf, data);
-        self.i2c
+        let result = self
+            .i2c
             .write(self.address, payload)
             .await
-            .map_err(Error::I2cWrite)
+            .map_err(|source| Error::from_write(command, source, is_address_nack));
+        self.record(result)
+    }
+
+    /// Write an I²C command and read the response words in a single combined
+    /// transaction, validating the CRC checksums.
+    ///
+    /// Bus errors are reported as [`Error::I2cRead`].
+    async fn write_read_words_with_crc(
+        &mut self,
+        command: CommandId,
+        buf: &mut [u8],
+    ) -> Result<(), Error<I2C::Error>> {
+        let result = self
+            .i2c
+            .write_read(self.address, &command.as_bytes(), buf)
+            .await
+            .map_err(|source| Error::from_read(command, source, is_address_nack))
+            .and_then(|()| self.check_crc(command, buf));
+        self.record(result)
+    }
+
+    /// Wait until the response to `command` may be read: for the maximum
+    /// command duration, or for the typical duration when polling.
+    async fn wait_for_completion(&mut self, command: CommandId) {
+        let info = command.info();
+        let duration_us = match self.poll_interval_us {
+            Some(_) => info.typical_duration_us,
+            None => info.max_duration_us,
+        };
+        self.delay.delay_us(duration_us).await;
+    }
+
+    /// Return the time in microseconds for which NACKed reads of the
+    /// response to `command` are retried.
+    fn poll_budget_us(&self, command: CommandId) -> u32 {
+        let info = command.info();
+        match self.poll_interval_us {
+            Some(_) => info
+                .max_duration_us
+                .saturating_sub(info.typical_duration_us),
+            None => 0,
+        }
+    }
+
+    /// Wait for the additional delay before reading a response, if any.
+    async fn extra_read_delay(&mut self) {
+        if self.extra_read_delay_us > 0 {
+            self.delay.delay_us(self.extra_read_delay_us).await;
+        }
+    }
+
+    /// Read the response words to `command` from the sensor, validating the
+    /// CRC checksums.
+    async fn read_words_with_crc(
+        &mut self,
+        command: CommandId,
+        buf: &mut [u8],
+    ) -> Result<(), Error<I2C::Error>> {
+        self.extra_read_delay().await;
+        let mut poll_budget_us = self.poll_budget_us(command);
+        let result = loop {
+            match self.i2c.read(self.address, buf).await {
+                // The command is still being processed
+                Err(ref e) if poll_budget_us > 0 && is_address_nack(e) => {
+                    let interval_us = self.poll_interval_us.unwrap_or(0).clamp(1, poll_budget_us);
+                    self.delay.delay_us(interval_us).await;
+                    poll_budget_us -= interval_us;
+                }
+                result => break result,
+            }
+        };
+        let result = result
+            .map_err(|source| Error::from_read(command, source, is_address_nack))
+            .and_then(|()| self.check_crc(command, buf));
+        self.record(result)
+    }
+
+    /// Validate the CRC checksums of the response `buf` to `command`, unless
+    /// the check is [skipped](Self::set_skip_crc).
+    fn check_crc(&self, command: CommandId, buf: &[u8]) -> Result<(), Error<I2C::Error>> {
+        if self.skip_crc {
+            return Ok(());
+        }
+        crc8::validate(buf).map_err(|_| Error::Crc { command })
+    }
+
+    /// Record a failed bus operation in the error history.
+    fn record<T>(&mut self, result: Result<T, Error<I2C::Error>>) -> Result<T, Error<I2C::Error>> {
+        #[cfg(feature = "error-history")]
+        if let Err(ref e) = result {
+            self.error_history.push(e.kind());
+        }
+        result
+    }
+
+    /// Apply the correction (if any) to a measurement.
+    fn correct(&self, measurement: Measurement) -> Measurement {
+        match self.correction {
+            Some(correction) => correction(measurement),
+            None => measurement,
+        }
     }
 
     /// Return the 48 bit serial number of the SGP30.
     pub async fn serial(&mut self) -> Result<[u8; 6], Error<I2C::Error>> {
-        // Request serial number
-        self.send_command(Command::GetSerial).await?;
+        self.serial_into(&mut SerialBuf::new()).await
+    }
+
+    /// Like [`serial()`](Self::serial), but reading the raw response into a
+    /// caller-provided buffer. See [`ReadBuf`](crate::ReadBuf) for details.
+    pub async fn serial_into(&mut self, buf: &mut SerialBuf) -> Result<[u8; 6], Error<I2C::Error>> {
+        if self.combined_reads {
+            self.write_read_words_with_crc(CommandId::GetSerial, buf.as_mut_bytes())
+                .await?;
+        } else {
+            // Request serial number
+            self.send_command(CommandId::GetSerial).await?;
 
-        // Recommended wait time according to datasheet (6.5)
-        self.delay.delay_us(500).await;
+            // Recommended wait time according to datasheet (6.5)
+            self.wait_for_completion(CommandId::GetSerial).await;
 
-        // Read serial number
-        let mut buf = [0; 9];
-        i2c_async::read_words_with_crc(&mut self.i2c, self.address, &mut buf).await?;
+            // Read serial number
+            self.read_words_with_crc(CommandId::GetSerial, buf.as_mut_bytes())
+                .await?;
+        }
 
+        let buf = buf.as_bytes();
         Ok([buf[0], buf[1], buf[3], buf[4], buf[6], buf[7]])
     }
 
     /// Run an on-chip self-test. Return a boolean indicating whether the test succeeded.
     pub async fn selftest(&mut self) -> Result<bool, Error<I2C::Error>> {
         // Start self test
-        self.send_command(Command::SelfTest).await?;
+        self.send_command(CommandId::SelfTest).await?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(220).await;
+        self.wait_for_completion(CommandId::SelfTest).await;
 
         // Read result
         let mut buf = [0; 3];
-        i2c_async::read_words_with_crc(&mut self.i2c, self.address, &mut buf).await?;
+        self.read_words_with_crc(CommandId::SelfTest, &mut buf)
+            .await?;
 
         // Compare with self-test success pattern
         Ok(&buf[0..2] == SELFTEST_SUCCESS)
@@ -129,10 +485,12 @@ where
     /// This might be necessary after a sensor soft or hard reset.
     pub async fn force_init(&mut self) -> Result<(), Error<I2C::Error>> {
         // Send command to sensor
-        self.send_command(Command::InitAirQuality).await?;
+        self.send_command(CommandId::InitAirQuality).await?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10).await;
+        self.delay
+            .delay_us(commands::INIT_AIR_QUALITY.max_duration_us)
+            .await;
 
         self.initialized = true;
         Ok(())
@@ -156,21 +514,32 @@ where
     /// values of 400 ppm CO₂eq and 0 ppb TVOC. After 15 s (15 measurements)
     /// the values should start to change.
     pub async fn measure(&mut self) -> Result<Measurement, Error<I2C::Error>> {
+        self.measure_into(&mut MeasureBuf::new()).await
+    }
+
+    /// Like [`measure()`](Self::measure), but reading the raw response into a
+    /// caller-provided buffer. See [`ReadBuf`](crate::ReadBuf) for details.
+    pub async fn measure_into(
+        &mut self,
+        buf: &mut MeasureBuf,
+    ) -> Result<Measurement, Error<I2C::Error>> {
         if !self.initialized {
             // Measurements weren't initialized
             return Err(Error::NotInitialized);
         }
 
         // Send command to sensor
-        self.send_command(Command::MeasureAirQuality).await?;
+        self.send_command(CommandId::MeasureAirQuality).await?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(12).await;
+        self.wait_for_completion(CommandId::MeasureAirQuality).await;
 
         // Read result
-        let mut buf = [0; 6];
-        i2c_async::read_words_with_crc(&mut self.i2c, self.address, &mut buf).await?;
-        Ok(Measurement::from_bytes(&buf))
+        self.read_words_with_crc(CommandId::MeasureAirQuality, buf.as_mut_bytes())
+            .await?;
+        let measurement = self.correct(Measurement::from_bytes(buf.as_bytes()));
+        self.last_measurement = Some(measurement);
+        Ok(measurement)
     }
 
     /// Return sensor raw signals.
@@ -180,24 +549,165 @@ where
     /// calibration and baseline compensation algorithm. The command performs a
     /// measurement to which the sensor responds with the two signals for H2
     /// and Ethanol.
+    ///
+    /// The air quality measurement must have been initialized using the
+    /// [`init()`](Self::init) method, unless the
+    /// [raw signals only mode](Self::set_raw_signals_only) is enabled.
+    /// Otherwise an [`Error::NotInitialized`] will be returned.
     pub async fn measure_raw_signals(&mut self) -> Result<RawSignals, Error<I2C::Error>> {
-        if !self.initialized {
+        self.measure_raw_signals_into(&mut MeasureBuf::new()).await
+    }
+
+    /// Like [`measure_raw_signals()`](Self::measure_raw_signals), but reading
+    /// the raw response into a caller-provided buffer. See [`ReadBuf`](crate::ReadBuf) for
+    /// details.
+    pub async fn measure_raw_signals_into(
+        &mut self,
+        buf: &mut MeasureBuf,
+    ) -> Result<RawSignals, Error<I2C::Error>> {
+        if !self.initialized && !self.raw_signals_only {
             // Measurements weren't initialized
             return Err(Error::NotInitialized);
         }
 
         // Send command to sensor
-        self.send_command(Command::MeasureRawSignals).await?;
+        self.send_command(CommandId::MeasureRawSignals).await?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(25).await;
+        self.wait_for_completion(CommandId::MeasureRawSignals).await;
 
         // Read result
-        let mut buf = [0; 6];
-        i2c_async::read_words_with_crc(&mut self.i2c, self.address, &mut buf).await?;
+        self.read_words_with_crc(CommandId::MeasureRawSignals, buf.as_mut_bytes())
+            .await?;
+        let signals = RawSignals::from_bytes(buf.as_bytes());
+        self.last_raw_signals = Some(signals);
+        Ok(signals)
+    }
+
+    /// Measure at 1 s intervals until the sensor returns live data, return
+    /// the first live measurement.
+    ///
+    /// During the initialization phase, the sensor returns the fixed values
+    /// of [`Measurement::WARM_UP`]. This keeps measuring until the values
+    /// differ from these (before applying the [correction](Self::set_correction)),
+    /// e.g. to validate units on a production line. If no live data was
+    /// returned within `timeout_s` measurements, an [`Error::Timeout`] is
+    /// returned.
+    ///
+    /// Note that a sensor in clean air may keep returning 400 ppm CO₂eq and
+    /// 0 ppb TVOC after the initialization phase as well, so the timeout
+    /// should be chosen accordingly. The initialization phase takes 15 s.
+    ///
+    /// The air quality measurement must have been initialized using the
+    /// [`init()`](Self::init) method, otherwise an [`Error::NotInitialized`]
+    /// is returned.
+    pub async fn wait_for_live_data(
+        &mut self,
+        timeout_s: u32,
+    ) -> Result<Measurement, Error<I2C::Error>> {
+        let mut buf = MeasureBuf::new();
+        for i in 0..timeout_s {
+            if i > 0 {
+                self.delay
+                    .delay_us(SAMPLE_INTERVAL_US - commands::MEASURE_AIR_QUALITY.max_duration_us)
+                    .await;
+            }
+            let measurement = self.measure_into(&mut buf).await?;
+            if Measurement::from_bytes(buf.as_bytes()) != Measurement::WARM_UP {
+                return Ok(measurement);
+            }
+        }
+        Err(Error::Timeout {
+            command: CommandId::MeasureAirQuality,
+        })
+    }
+
+    /// Measure `N` times at 1 s intervals, return the measurements.
+    ///
+    /// This is convenient for duty-cycled applications which sample for a
+    /// while and then decide what to do, e.g. collect 30 s worth of
+    /// measurements and average them. If a measurement fails, the error is
+    /// returned and the measurements collected so far are discarded.
+    ///
+    /// Note that the measurements of the initialization phase (the first
+    /// 15 s after [`init()`](Self::init)) are the fixed values of
+    /// [`Measurement::WARM_UP`].
+    ///
+    /// The air quality measurement must have been initialized using the
+    /// [`init()`](Self::init) method, otherwise an [`Error::NotInitialized`]
+    /// is returned.
+    #[cfg(feature = "heapless")]
+    pub async fn collect_n<const N: usize>(
+        &mut self,
+    ) -> Result<heapless::Vec<Measurement, N>, Error<I2C::Error>> {
+        let mut measurements = heapless::Vec::new();
+        while !measurements.is_full() {
+            if !measurements.is_empty() {
+                self.delay
+                    .delay_us(SAMPLE_INTERVAL_US - commands::MEASURE_AIR_QUALITY.max_duration_us)
+                    .await;
+            }
+            // Cannot fail, the vector is not full
+            let _ = measurements.push(self.measure().await?);
+        }
+        Ok(measurements)
+    }
+
+    /// Get an air quality measurement without any checks and bookkeeping.
+    ///
+    /// Unlike [`measure()`](Self::measure), this does not check whether the
+    /// air quality measurement was initialized, and skips the correction,
+    /// the [last measurement](Self::last_measurement) cache and the error
+    /// history. The CRC is still validated, unless the check is
+    /// [skipped](Self::set_skip_crc). Use this only if the
+    /// initialization is guaranteed by the structure of the application.
+    ///
+    /// Measuring without initialization doesn't return an error, but
+    /// undefined values.
+    pub async fn measure_unchecked(&mut self) -> Result<Measurement, Error<I2C::Error>> {
+        let buf = self
+            .transfer_unchecked(
+                CommandId::MeasureAirQuality,
+                commands::MEASURE_AIR_QUALITY.max_duration_us,
+            )
+            .await?;
+        Ok(Measurement::from_bytes(&buf))
+    }
+
+    /// Return sensor raw signals without any checks and bookkeeping.
+    ///
+    /// See [`measure_unchecked()`](Self::measure_unchecked).
+    pub async fn measure_raw_signals_unchecked(&mut self) -> Result<RawSignals, Error<I2C::Error>> {
+        let buf = self
+            .transfer_unchecked(
+                CommandId::MeasureRawSignals,
+                commands::MEASURE_RAW_SIGNALS.max_duration_us,
+            )
+            .await?;
         Ok(RawSignals::from_bytes(&buf))
     }
 
+    /// Send `command`, wait for its completion and read a two word response.
+    async fn transfer_unchecked(
+        &mut self,
+        command: CommandId,
+        max_duration_us: u32,
+    ) -> Result<[u8; 6], Error<I2C::Error>> {
+        self.i2c
+            .write(self.address, &command.as_bytes())
+            .await
+            .map_err(|source| Error::from_write(command, source, is_address_nack))?;
+        self.delay.delay_us(max_duration_us).await;
+        self.extra_read_delay().await;
+        let mut buf = [0; 6];
+        self.i2c
+            .read(self.address, &mut buf)
+            .await
+            .map_err(|source| Error::from_read(command, source, is_address_nack))?;
+        self.check_crc(command, &buf)?;
+        Ok(buf)
+    }
+
     /// Return the baseline values of the baseline correction algorithm.
     ///
     /// The SGP30 provides the possibility to read and write the baseline
@@ -211,16 +721,25 @@ where
     /// algorithm can be restored by calling [`init()`](Self::init) followed by
     /// [`set_baseline()`](Self::set_baseline).
     pub async fn get_baseline(&mut self) -> Result<Baseline, Error<I2C::Error>> {
+        self.get_baseline_into(&mut MeasureBuf::new()).await
+    }
+
+    /// Like [`get_baseline()`](Self::get_baseline), but reading the raw response into a
+    /// caller-provided buffer. See [`ReadBuf`](crate::ReadBuf) for details.
+    pub async fn get_baseline_into(
+        &mut self,
+        buf: &mut MeasureBuf,
+    ) -> Result<Baseline, Error<I2C::Error>> {
         // Send command to sensor
-        self.send_command(Command::GetBaseline).await?;
+        self.send_command(CommandId::GetBaseline).await?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10).await;
+        self.wait_for_completion(CommandId::GetBaseline).await;
 
         // Read result
-        let mut buf = [0; 6];
-        i2c_async::read_words_with_crc(&mut self.i2c, self.address, &mut buf).await?;
-        Ok(Baseline::from_bytes(&buf))
+        self.read_words_with_crc(CommandId::GetBaseline, buf.as_mut_bytes())
+            .await?;
+        Ok(Baseline::from_bytes(buf.as_bytes()))
     }
 
     /// Set the baseline values for the baseline correction algorithm.
@@ -245,15 +764,37 @@ where
         // Send command and data to sensor
         // Note that the order of the two parameters is inverted when writing
         // compared to when reading.
-        let mut buf = [0; 4];
-        BigEndian::write_u16(&mut buf[0..2], baseline.tvoc);
-        BigEndian::write_u16(&mut buf[2..4], baseline.co2eq);
-        self.send_command_and_data(Command::SetBaseline, &buf)
+        let data = CommandData::Data4(baseline.as_bytes());
+        self.send_command_and_data(CommandId::SetBaseline, data)
             .await?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10).await;
+        self.delay
+            .delay_us(commands::SET_BASELINE.max_duration_us)
+            .await;
+
+        Ok(())
+    }
 
+    /// Set the baseline values and read them back to verify that they were
+    /// written correctly.
+    ///
+    /// This catches data corrupted on the bus, which would otherwise only
+    /// show as drifting measurements. If the values read back differ, an
+    /// [`Error::BaselineMismatch`] is returned, and the baseline should be
+    /// written again.
+    pub async fn set_baseline_verified(
+        &mut self,
+        baseline: &Baseline,
+    ) -> Result<(), Error<I2C::Error>> {
+        self.set_baseline(baseline).await?;
+        let read = self.get_baseline().await?;
+        if read != *baseline {
+            return Err(Error::BaselineMismatch {
+                written: *baseline,
+                read,
+            });
+        }
         Ok(())
     }
 
@@ -269,7 +810,9 @@ where
     /// set. Restarting the sensor (power-on or soft reset) or calling the
     /// function with a `None` value sets the humidity value used for
     /// compensation to its default value (11.57 g/m³) until a new humidity
-    /// value is sent.
+    /// value is sent. Prefer
+    /// [`disable_humidity_compensation()`](Self::disable_humidity_compensation)
+    /// over passing `None` to make the intent explicit.
     ///
     /// Before calling this method, the air quality measurements must have been
     /// initialized using the [`init()`](Self::init) method.
@@ -284,36 +827,261 @@ where
         }
 
         // Send command and data to sensor
-        let buf = match humidity {
+        let data = CommandData::Data2(match humidity {
             Some(humi) => humi.as_bytes(),
             None => [0, 0],
-        };
-        self.send_command_and_data(Command::SetHumidity, &buf)
+        });
+        self.send_command_and_data(CommandId::SetHumidity, data)
             .await?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10).await;
+        self.delay
+            .delay_us(commands::SET_HUMIDITY.max_duration_us)
+            .await;
+
+        self.humidity_compensation = humidity.is_some();
 
         Ok(())
     }
 
+    /// Disable the humidity compensation with a humidity value sent by this
+    /// driver.
+    ///
+    /// This sends a humidity value of zero, which makes the sensor fall back
+    /// to its default humidity value ([`Humidity::DEFAULT`], 11.57 g/m³).
+    ///
+    /// Before calling this method, the air quality measurements must have been
+    /// initialized using the [`init()`](Self::init) method.
+    /// Otherwise an [`Error::NotInitialized`] will be returned.
+    pub async fn disable_humidity_compensation(&mut self) -> Result<(), Error<I2C::Error>> {
+        self.set_humidity(None).await
+    }
+
+    /// Shut the air quality measurement down, return the final baseline.
+    ///
+    /// This is the teardown path before putting the microcontroller to sleep
+    /// or updating its firmware: It reads the baseline, which should be
+    /// stored and restored with [`set_baseline()`](Self::set_baseline) after
+    /// the next [`init()`](Self::init). If `clear_humidity` is true and a
+    /// humidity value sent by this driver is active, the humidity
+    /// compensation is disabled. Finally, the driver is marked as
+    /// uninitialized, so the next [`init()`](Self::init) sends the init
+    /// command again.
+    ///
+    /// The driver does not control the power supply of the sensor. To save
+    /// power, switch the sensor off after this returned.
+    ///
+    /// Before calling this method, the air quality measurements must have been
+    /// initialized using the [`init()`](Self::init) method.
+    /// Otherwise an [`Error::NotInitialized`] will be returned. If a command
+    /// fails, the driver stays initialized.
+    pub async fn shutdown(&mut self, clear_humidity: bool) -> Result<Baseline, Error<I2C::Error>> {
+        if !self.initialized {
+            // Measurements weren't initialized
+            return Err(Error::NotInitialized);
+        }
+
+        let baseline = self.get_baseline().await?;
+        if clear_humidity && self.humidity_compensation {
+            self.disable_humidity_compensation().await?;
+        }
+        self.initialized = false;
+
+        Ok(baseline)
+    }
+
     /// Get the feature set.
     ///
     /// The SGP30 features a versioning system for the available set of
     /// measurement commands and on-chip algorithms. This so called feature set
     /// version number can be read out with this method.
     pub async fn get_feature_set(&mut self) -> Result<FeatureSet, Error<I2C::Error>> {
-        // Send command to sensor
-        self.send_command(Command::GetFeatureSet).await?;
+        let mut buf = [0; 3];
+        if self.combined_reads {
+            self.write_read_words_with_crc(CommandId::GetFeatureSet, &mut buf)
+                .await?;
+        } else {
+            // Send command to sensor
+            self.send_command(CommandId::GetFeatureSet).await?;
 
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(2).await;
+            // Max duration according to datasheet (Table 10)
+            self.wait_for_completion(CommandId::GetFeatureSet).await;
 
-        // Read result
-        let mut buf = [0; 3];
-        i2c_async::read_words_with_crc(&mut self.i2c, self.address, &mut buf).await?;
+            // Read result
+            self.read_words_with_crc(CommandId::GetFeatureSet, &mut buf)
+                .await?;
+        }
+
+        let feature_set = FeatureSet::parse(buf[0], buf[1]);
+        self.feature_set = Some(feature_set);
+        Ok(feature_set)
+    }
+
+    /// Return the feature set, reading it from the sensor only on the first
+    /// call.
+    ///
+    /// The feature set doesn't change while the sensor is running, so
+    /// subsequent calls return the cached value without any I²C transaction.
+    /// Every call to [`get_feature_set()`](Self::get_feature_set) updates
+    /// the cached value.
+    pub async fn feature_set(&mut self) -> Result<FeatureSet, Error<I2C::Error>> {
+        match self.feature_set {
+            Some(feature_set) => Ok(feature_set),
+            None => self.get_feature_set().await,
+        }
+    }
+
+    /// Read the feature set from the sensor again and update the cached
+    /// value returned by [`feature_set()`](Self::feature_set).
+    ///
+    /// Use this if the sensor might have been replaced while the driver
+    /// kept running, e.g. on a hot-pluggable connector.
+    pub async fn refresh_feature_set(&mut self) -> Result<FeatureSet, Error<I2C::Error>> {
+        self.feature_set = None;
+        self.feature_set().await
+    }
+
+    /// Run the sampling loop, passing every measurement to `sink`.
+    ///
+    /// This initializes the air quality measurement (unless it already was
+    /// initialized), discards the measurements of the 15 s initialization
+    /// phase and takes a measurement every second, using the delay of the
+    /// driver. Failed measurements are retried in the following seconds and
+    /// then skipped. The function never returns, but can be cancelled by
+    /// dropping the future, e.g. by `select`ing it with a shutdown signal.
+    ///
+    /// To restore a baseline, call [`init()`](Self::init) and
+    /// [`set_baseline()`](Self::set_baseline) before running the loop. Use
+    /// [`run_with()`](Self::run_with) to read the baseline periodically and
+    /// to be notified about errors.
+    pub async fn run(&mut self, mut sink: impl FnMut(Measurement)) -> ! {
+        self.run_with(RunConfig::DEFAULT, |event| {
+            if let RunEvent::Measurement(measurement) = event {
+                sink(measurement);
+            }
+        })
+        .await
+    }
+
+    /// Run the sampling loop with the given `config`, passing measurements,
+    /// baselines and errors to `handler`.
+    ///
+    /// See [`run()`](Self::run). If the initialization fails, it is retried
+    /// after 1 s. Note that the baselines read during the first 12 h
+    /// without a restored baseline should not be stored (datasheet,
+    /// section 3.8).
+    ///
+    /// The loop does not know when the driver was initialized, so the first
+    /// [`warm_up_samples`](RunConfig::warm_up_samples) measurements (and
+    /// those after every initialization by the loop) are discarded as long
+    /// as they have the fixed values of [`Measurement::WARM_UP`]. A failed
+    /// measurement is retried in the next sample interval rather than
+    /// immediately, to keep the 1 s cadence required by the baseline
+    /// compensation of the sensor.
+    pub async fn run_with(
+        &mut self,
+        config: RunConfig,
+        mut handler: impl FnMut(RunEvent<I2C::Error>),
+    ) -> ! {
+        let mut warm_up = config.warm_up_samples;
+        let mut failures = 0;
+        let mut seconds: u32 = 0;
+        loop {
+            // Time spent on commands in this interval
+            let mut busy_us = 0;
+
+            if !self.initialized {
+                busy_us += commands::INIT_AIR_QUALITY.max_duration_us;
+                match self.init().await {
+                    Ok(()) => warm_up = config.warm_up_samples,
+                    Err(e) => {
+                        handler(RunEvent::Error(e));
+                        self.delay.delay_us(SAMPLE_INTERVAL_US - busy_us).await;
+                        continue;
+                    }
+                }
+            }
+
+            let result = self.measure().await;
+            busy_us += commands::MEASURE_AIR_QUALITY.max_duration_us;
+            match result {
+                Ok(measurement) if warm_up > 0 && measurement == Measurement::WARM_UP => {
+                    warm_up -= 1;
+                    failures = 0;
+                }
+                Ok(measurement) => {
+                    warm_up = 0;
+                    failures = 0;
+                    handler(RunEvent::Measurement(measurement));
+                }
+                // Retried in the next interval
+                Err(_) if failures < config.max_retries => failures += 1,
+                Err(e) => {
+                    failures = 0;
+                    handler(RunEvent::Error(e));
+                }
+            }
+
+            seconds = seconds.wrapping_add(1);
+            if config.baseline_interval_s > 0 && seconds % config.baseline_interval_s == 0 {
+                busy_us += commands::GET_BASELINE.max_duration_us;
+                match self.get_baseline().await {
+                    Ok(baseline) => handler(RunEvent::Baseline(baseline)),
+                    Err(e) => handler(RunEvent::Error(e)),
+                }
+            }
+
+            self.delay
+                .delay_us(SAMPLE_INTERVAL_US.saturating_sub(busy_us))
+                .await;
+        }
+    }
+}
+
+/// An [`Sgp30Async`] driver on an I²C bus shared through
+/// `embassy-embedded-hal`, see [`Sgp30Async::new_shared()`].
+///
+/// This is useful for naming the driver type in the signature of a task.
+#[cfg(feature = "embassy")]
+pub type SharedBusSgp30Async<'a, M, BUS, D> = Sgp30Async<I2cDevice<'a, M, BUS>, D>;
 
-        Ok(FeatureSet::parse(buf[0], buf[1]))
+#[cfg(feature = "embassy")]
+impl<'a, M, BUS, D> Sgp30Async<I2cDevice<'a, M, BUS>, D>
+where
+    M: RawMutex,
+    BUS: I2c,
+    D: DelayNs,
+{
+    /// Create a new instance of the SGP30 driver on an I²C bus shared with
+    /// other devices.
+    ///
+    /// The bus is locked for the duration of every transfer, but not while
+    /// waiting for a command to complete, so other devices can use the bus
+    /// in the meantime.
+    ///
+    /// ```
+    /// use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+    /// use embedded_hal_mock::eh1::{
+    ///     delay::NoopDelay,
+    ///     i2c::{Mock as I2cMock, Transaction},
+    /// };
+    /// use sgp30::{SharedBusSgp30Async, Sgp30Async};
+    ///
+    /// type Sgp30<'a> = SharedBusSgp30Async<'a, NoopRawMutex, I2cMock, NoopDelay>;
+    ///
+    /// # let mock = I2cMock::new(&[
+    /// #     Transaction::write(0x58, vec![0x36, 0x82]),
+    /// #     Transaction::read(0x58, vec![0, 0, 0x81, 0, 1, 0xB0, 0, 2, 0xE3]),
+    /// # ]);
+    /// let bus = Mutex::<NoopRawMutex, _>::new(mock);
+    /// // Other devices on the same bus get their own `I2cDevice`
+    /// let mut sgp: Sgp30 = Sgp30Async::new_shared(&bus, 0x58, NoopDelay);
+    /// let serial = futures_executor::block_on(sgp.serial()).unwrap();
+    /// assert_eq!(serial, [0, 0, 0, 1, 0, 2]);
+    /// # bus.into_inner().done();
+    /// ```
+    pub fn new_shared(bus: &'a Mutex<M, BUS>, address: u8, delay: D) -> Self {
+        Self::new(I2cDevice::new(bus), address, delay)
     }
 }
 
@@ -333,7 +1101,7 @@ mod tests {
     fn serial() {
         block_on(async {
             let expectations = [
-                Transaction::write(0x58, Command::GetSerial.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::GetSerial.as_bytes()[..].into()),
                 Transaction::read(0x58, vec![0, 0, 129, 0, 100, 254, 204, 130, 135]),
             ];
             let mock = I2cMock::new(&expectations);
@@ -349,7 +1117,7 @@ mod tests {
     fn selftest_ok() {
         block_on(async {
             let expectations = [
-                Transaction::write(0x58, Command::SelfTest.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::SelfTest.as_bytes()[..].into()),
                 Transaction::read(0x58, vec![0xD4, 0x00, 0xC6]),
             ];
             let mock = I2cMock::new(&expectations);
@@ -364,7 +1132,7 @@ mod tests {
     fn selftest_fail() {
         block_on(async {
             let expectations = [
-                Transaction::write(0x58, Command::SelfTest.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::SelfTest.as_bytes()[..].into()),
                 Transaction::read(0x58, vec![0x12, 0x34, 0x37]),
             ];
             let mock = I2cMock::new(&expectations);
@@ -394,16 +1162,80 @@ mod tests {
     fn measure_success() {
         block_on(async {
             let expectations = [
-                Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
-                Transaction::write(0x58, Command::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            assert!(!sgp.is_initialized());
+            sgp.init().await.unwrap();
+            assert!(sgp.is_initialized());
+            let measurements = sgp.measure().await.unwrap();
+            assert_eq!(measurements.co2eq_ppm, 4_660);
+            assert_eq!(measurements.tvoc_ppb, 54_274);
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `set_correction` function: Correction is applied to measurements
+    #[test]
+    fn measure_corrected() {
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
                 Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
             ];
             let mock = I2cMock::new(&expectations);
             let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
             sgp.init().await.unwrap();
+            sgp.set_correction(Some(|m| Measurement {
+                co2eq_ppm: m.co2eq_ppm - 60,
+                tvoc_ppb: m.tvoc_ppb / 2,
+            }));
+            let measurements = sgp.measure().await.unwrap();
+            assert_eq!(measurements.co2eq_ppm, 4_600);
+            assert_eq!(measurements.tvoc_ppb, 27_137);
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `assume_initialized` function: No init command is sent
+    #[test]
+    fn assume_initialized() {
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::assume_initialized(mock, 0x58, NoopDelay);
+            assert!(sgp.is_initialized());
+            sgp.init().await.unwrap();
             let measurements = sgp.measure().await.unwrap();
             assert_eq!(measurements.co2eq_ppm, 4_660);
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `measure_into` function: Response is read into the caller-provided buffer
+    #[test]
+    fn measure_into() {
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            sgp.init().await.unwrap();
+            let mut buf = MeasureBuf::new();
+            let measurements = sgp.measure_into(&mut buf).await.unwrap();
+            assert_eq!(measurements.co2eq_ppm, 4_660);
             assert_eq!(measurements.tvoc_ppb, 54_274);
+            assert_eq!(buf.as_bytes(), &[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
             sgp.destroy().done();
         })
     }
@@ -413,8 +1245,8 @@ mod tests {
     fn get_baseline() {
         block_on(async {
             let expectations = [
-                Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
-                Transaction::write(0x58, Command::GetBaseline.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
                 Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
             ];
             let mock = I2cMock::new(&expectations);
@@ -433,7 +1265,7 @@ mod tests {
         block_on(async {
             #[rustfmt::skip]
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
             Transaction::write(0x58, vec![
                 /* command: */ 0x20, 0x1E,
                 /* data + crc8: */ 0x56, 0x78, 0x7D, 0x12, 0x34, 0x37,
@@ -451,13 +1283,50 @@ mod tests {
         })
     }
 
+    /// Test the `set_baseline_verified` function
+    #[test]
+    fn set_baseline_verified() {
+        block_on(async {
+            #[rustfmt::skip]
+            let expectations = [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, vec![
+                    /* command: */ 0x20, 0x1E,
+                    /* data + crc8: */ 0x56, 0x78, 0x7D, 0x12, 0x34, 0x37,
+                ]),
+                Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+                Transaction::write(0x58, vec![
+                    /* command: */ 0x20, 0x1E,
+                    /* data + crc8: */ 0x56, 0x78, 0x7D, 0x12, 0x34, 0x37,
+                ]),
+                Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x00, 0x00, 0x81]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            sgp.init().await.unwrap();
+            let baseline = Baseline::new(0x1234, 0x5678);
+            sgp.set_baseline_verified(&baseline).await.unwrap();
+            match sgp.set_baseline_verified(&baseline).await {
+                Err(Error::BaselineMismatch { written, read }) => {
+                    assert_eq!(written, baseline);
+                    assert_eq!(read, Baseline::new(0x1234, 0));
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+            sgp.destroy().done();
+        })
+    }
+
     /// Test the `set_humidity` function
+    #[cfg(feature = "humidity-float")]
     #[test]
     fn set_humidity() {
         block_on(async {
             #[rustfmt::skip]
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
             Transaction::write(0x58, vec![
                 /* command: */ 0x20, 0x61,
                 /* data + crc8: */ 0x0F, 0x80, 0x62,
@@ -467,7 +1336,9 @@ mod tests {
             let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
             sgp.init().await.unwrap();
             let humidity = Humidity::from_f32(15.5).unwrap();
+            assert!(!sgp.humidity_compensation_active());
             sgp.set_humidity(Some(&humidity)).await.unwrap();
+            assert!(sgp.humidity_compensation_active());
             sgp.destroy().done();
         })
     }
@@ -478,7 +1349,7 @@ mod tests {
         block_on(async {
             #[rustfmt::skip]
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
             Transaction::write(0x58, vec![
                 /* command: */ 0x20, 0x61,
                 /* data + crc8: */ 0x00, 0x00, 0x81,
@@ -492,13 +1363,83 @@ mod tests {
         })
     }
 
+    /// Test the `disable_humidity_compensation` function
+    #[cfg(feature = "humidity-float")]
+    #[test]
+    fn disable_humidity_compensation() {
+        block_on(async {
+            #[rustfmt::skip]
+            let expectations = [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, vec![
+                    /* command: */ 0x20, 0x61,
+                    /* data + crc8: */ 0x0F, 0x80, 0x62,
+                ]),
+                Transaction::write(0x58, vec![
+                    /* command: */ 0x20, 0x61,
+                    /* data + crc8: */ 0x00, 0x00, 0x81,
+                ]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            sgp.init().await.unwrap();
+            let humidity = Humidity::from_f32(15.5).unwrap();
+            sgp.set_humidity(Some(&humidity)).await.unwrap();
+            sgp.disable_humidity_compensation().await.unwrap();
+            assert!(!sgp.humidity_compensation_active());
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `shutdown` function
+    #[test]
+    fn shutdown() {
+        block_on(async {
+            #[rustfmt::skip]
+            let expectations = [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, vec![
+                    /* command: */ 0x20, 0x61,
+                    /* data + crc8: */ 0x0F, 0x80, 0x62,
+                ]),
+                Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+                Transaction::write(0x58, vec![
+                    /* command: */ 0x20, 0x61,
+                    /* data + crc8: */ 0x00, 0x00, 0x81,
+                ]),
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            assert!(matches!(
+                sgp.shutdown(true).await,
+                Err(Error::NotInitialized)
+            ));
+            sgp.init().await.unwrap();
+            let humidity = Humidity::new(0x0F, 0x80).unwrap();
+            sgp.set_humidity(Some(&humidity)).await.unwrap();
+            let baseline = sgp.shutdown(true).await.unwrap();
+            assert_eq!(baseline, Baseline::new(4_660, 54_274));
+            assert!(!sgp.is_initialized());
+            assert!(!sgp.humidity_compensation_active());
+
+            // Without humidity compensation, no humidity is sent
+            sgp.init().await.unwrap();
+            sgp.shutdown(true).await.unwrap();
+            sgp.destroy().done();
+        })
+    }
+
     /// Test the `get_feature_set` function.
     #[test]
     fn get_feature_set() {
         block_on(async {
             let expectations = [
-                Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
-                Transaction::write(0x58, Command::GetFeatureSet.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
                 Transaction::read(0x58, vec![0x00, 0x42, 0xDE]),
             ];
             let mock = I2cMock::new(&expectations);
@@ -507,6 +1448,82 @@ mod tests {
             let feature_set = sgp.get_feature_set().await.unwrap();
             assert_eq!(feature_set.product_type, ProductType::Sgp30);
             assert_eq!(feature_set.product_version, 0x42);
+            assert_eq!(feature_set.raw(), 0x0042);
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `feature_set` and `refresh_feature_set` functions.
+    #[test]
+    fn feature_set_cached() {
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x00, 0x42, 0xDE]),
+                Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x00, 0x22, 0x65]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            // Only the first call reads from the sensor
+            assert_eq!(sgp.feature_set().await.unwrap().product_version, 0x42);
+            assert_eq!(sgp.feature_set().await.unwrap().product_version, 0x42);
+            assert_eq!(
+                sgp.refresh_feature_set().await.unwrap().product_version,
+                0x22
+            );
+            assert_eq!(sgp.feature_set().await.unwrap().product_version, 0x22);
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test that NACKs of the address are reported as
+    /// `Error::DeviceNotResponding`
+    #[test]
+    fn device_not_responding() {
+        use embedded_hal::i2c::{ErrorKind as BusErrorKind, NoAcknowledgeSource};
+
+        use crate::ErrorKind;
+
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into())
+                    .with_error(BusErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+                Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x00, 0x42, 0xDE])
+                    .with_error(BusErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+                Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x00, 0x42, 0xDE]).with_error(BusErrorKind::Bus),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            let err = sgp.get_feature_set().await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::DeviceNotResponding);
+            let err = sgp.get_feature_set().await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::DeviceNotResponding);
+            assert_eq!(err.command(), Some(CommandId::GetFeatureSet));
+            let err = sgp.get_feature_set().await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::I2cRead);
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `get_feature_set` function with combined reads enabled.
+    #[test]
+    fn get_feature_set_combined() {
+        block_on(async {
+            let expectations = [Transaction::write_read(
+                0x58,
+                CommandId::GetFeatureSet.as_bytes()[..].into(),
+                vec![0x00, 0x42, 0xDE],
+            )];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            sgp.set_combined_reads(true);
+            let feature_set = sgp.get_feature_set().await.unwrap();
+            assert_eq!(feature_set.product_type, ProductType::Sgp30);
+            assert_eq!(feature_set.product_version, 0x42);
+            assert_eq!(feature_set.raw(), 0x0042);
             sgp.destroy().done();
         })
     }
@@ -516,8 +1533,8 @@ mod tests {
     fn measure_raw_signals() {
         block_on(async {
             let expectations = [
-                Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
-                Transaction::write(0x58, Command::MeasureRawSignals.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
                 Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
             ];
             let mock = I2cMock::new(&expectations);
@@ -529,4 +1546,464 @@ mod tests {
             sgp.destroy().done();
         })
     }
+
+    /// Test the `measure_raw_signals` function in the raw signals only mode
+    #[test]
+    fn measure_raw_signals_only() {
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            assert!(matches!(
+                sgp.measure_raw_signals().await,
+                Err(Error::NotInitialized)
+            ));
+            assert!(!sgp.raw_signals_only());
+            sgp.set_raw_signals_only(true);
+            assert!(sgp.raw_signals_only());
+            let signals = sgp.measure_raw_signals().await.unwrap();
+            assert_eq!(signals.h2, 0x1234);
+            assert_eq!(signals.ethanol, 0x5678);
+            // The air quality measurement still requires initialization
+            assert!(matches!(sgp.measure().await, Err(Error::NotInitialized)));
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `wait_for_live_data` function
+    #[test]
+    fn wait_for_live_data() {
+        block_on(async {
+            let warm_up = [0x01, 0x90, 0x4C, 0x00, 0x00, 0x81];
+            let expectations = [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, warm_up.into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x16]),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, warm_up.into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, warm_up.into()),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            // The correction doesn't affect the detection of live data
+            sgp.set_correction(Some(|m| Measurement::new(m.co2eq_ppm + 10, m.tvoc_ppb)));
+            assert!(matches!(
+                sgp.wait_for_live_data(1).await,
+                Err(Error::NotInitialized)
+            ));
+            sgp.init().await.unwrap();
+            let measurement = sgp.wait_for_live_data(5).await.unwrap();
+            assert_eq!(measurement, Measurement::new(422, 7));
+            assert!(matches!(
+                sgp.wait_for_live_data(2).await,
+                Err(Error::Timeout {
+                    command: CommandId::MeasureAirQuality
+                })
+            ));
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `collect_n` function
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn collect_n() {
+        block_on(async {
+            let measure =
+                || Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into());
+            let expectations = [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                measure(),
+                Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+                measure(),
+                Transaction::read(0x58, vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x16]),
+                measure(),
+                Transaction::read(0x58, vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x16]),
+                measure(),
+                Transaction::read(0x58, vec![0x01, 0x9C, 0x00, 0x00, 0x07, 0x16]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            assert!(matches!(
+                sgp.collect_n::<2>().await,
+                Err(Error::NotInitialized)
+            ));
+            sgp.init().await.unwrap();
+            let measurements = sgp.collect_n::<2>().await.unwrap();
+            assert_eq!(
+                measurements,
+                [Measurement::new(400, 0), Measurement::new(412, 7)]
+            );
+            assert!(sgp.collect_n::<0>().await.unwrap().is_empty());
+            // An error discards the collected measurements
+            assert!(matches!(sgp.collect_n::<2>().await, Err(Error::Crc { .. })));
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `set_skip_crc` function
+    #[test]
+    fn skip_crc() {
+        block_on(async {
+            let measure =
+                || Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into());
+            // Corrupted CRC of the TVOC word
+            let response = vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x00];
+            #[rustfmt::skip]
+            let expectations = [
+                measure(),
+                Transaction::read(0x58, response.clone()),
+                measure(),
+                Transaction::read(0x58, response),
+                // The CRC is still sent to the sensor
+                Transaction::write(0x58, vec![
+                    /* command: */ 0x20, 0x61,
+                    /* data + crc8: */ 0x0F, 0x80, 0x62,
+                ]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::assume_initialized(mock, 0x58, NoopDelay);
+            assert!(!sgp.skip_crc());
+            assert!(matches!(sgp.measure().await, Err(Error::Crc { .. })));
+            sgp.set_skip_crc(true);
+            assert!(sgp.skip_crc());
+            assert_eq!(sgp.measure().await.unwrap(), Measurement::new(412, 7));
+            let humidity = Humidity::new(0x0F, 0x80).unwrap();
+            sgp.set_humidity(Some(&humidity)).await.unwrap();
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `rebuild` function
+    #[test]
+    fn rebuild() {
+        block_on(async {
+            let expectations = [Transaction::write(
+                0x42,
+                CommandId::InitAirQuality.as_bytes()[..].into(),
+            )];
+            let mock = I2cMock::new(&expectations);
+            let mut old_mock = mock.clone();
+            let mut sgp = Sgp30Async::new(mock, 0x42, NoopDelay);
+            sgp.init().await.unwrap();
+            sgp.set_combined_reads(true);
+
+            let expectations = [
+                Transaction::write_read(
+                    0x42,
+                    CommandId::GetFeatureSet.as_bytes()[..].into(),
+                    vec![0x00, 0x42, 0xDE],
+                ),
+                Transaction::write(0x42, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x42, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+            ];
+            let mut sgp = sgp.rebuild(I2cMock::new(&expectations));
+            old_mock.done();
+            assert!(sgp.is_initialized());
+            sgp.get_feature_set().await.unwrap();
+            sgp.measure().await.unwrap();
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `set_extra_read_delay_us` function
+    #[test]
+    fn extra_read_delay() {
+        use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+                Transaction::write_read(
+                    0x58,
+                    CommandId::GetFeatureSet.as_bytes()[..].into(),
+                    vec![0x00, 0x42, 0xDE],
+                ),
+            ];
+            let delays = [
+                DelayTransaction::delay_us(commands::MEASURE_AIR_QUALITY.max_duration_us),
+                DelayTransaction::delay_us(500),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut delay = CheckedDelay::new(&delays);
+            let mut sgp = Sgp30Async::assume_initialized(mock, 0x58, delay.clone());
+            assert_eq!(sgp.extra_read_delay_us(), 0);
+            sgp.set_extra_read_delay_us(500);
+            assert_eq!(sgp.extra_read_delay_us(), 500);
+            sgp.measure().await.unwrap();
+            // Combined reads are not delayed
+            sgp.set_combined_reads(true);
+            sgp.get_feature_set().await.unwrap();
+            sgp.destroy().done();
+            delay.done();
+        })
+    }
+
+    /// Test the `set_poll_interval_us` function
+    #[test]
+    fn poll_until_ready() {
+        use embedded_hal::i2c::{ErrorKind as BusErrorKind, NoAcknowledgeSource};
+        use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+
+        use crate::ErrorKind;
+
+        block_on(async {
+            let nack = BusErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+            let response = vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4];
+            let expectations = [
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, response.clone()).with_error(nack),
+                Transaction::read(0x58, response.clone()),
+                // Not ready within the maximum duration
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, response.clone()).with_error(nack),
+                Transaction::read(0x58, response.clone()).with_error(nack),
+                Transaction::read(0x58, response).with_error(nack),
+            ];
+            let delays = [
+                DelayTransaction::delay_us(10_000),
+                DelayTransaction::delay_us(1_500),
+                DelayTransaction::delay_us(10_000),
+                DelayTransaction::delay_us(1_500),
+                DelayTransaction::delay_us(500),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut delay = CheckedDelay::new(&delays);
+            let mut sgp = Sgp30Async::assume_initialized(mock, 0x58, delay.clone());
+            sgp.set_poll_interval_us(Some(1_500));
+            assert_eq!(sgp.poll_interval_us(), Some(1_500));
+            assert_eq!(
+                sgp.measure().await.unwrap(),
+                Measurement::new(4_660, 54_274)
+            );
+            assert_eq!(
+                sgp.measure().await.unwrap_err().kind(),
+                ErrorKind::DeviceNotResponding
+            );
+            sgp.destroy().done();
+            delay.done();
+        })
+    }
+
+    /// Test the `measure_unchecked` and `measure_raw_signals_unchecked`
+    /// functions
+    #[test]
+    fn measure_unchecked() {
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+                Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                // Invalid CRC
+                Transaction::read(0x58, vec![0x01, 0x90, 0x00, 0x00, 0x00, 0x81]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            sgp.set_correction(Some(|m| Measurement::new(0, m.tvoc_ppb)));
+            // Neither initialized nor corrected
+            let measurement = sgp.measure_unchecked().await.unwrap();
+            assert_eq!(measurement, Measurement::new(4_660, 54_274));
+            assert_eq!(sgp.last_measurement(), None);
+            let signals = sgp.measure_raw_signals_unchecked().await.unwrap();
+            assert_eq!(signals, RawSignals::new(0x1234, 0x5678));
+            assert!(matches!(
+                sgp.measure_unchecked().await,
+                Err(Error::Crc {
+                    command: CommandId::MeasureAirQuality
+                })
+            ));
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test the `last_measurement` and `last_raw_signals` functions
+    #[test]
+    fn last_measurement() {
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                // Invalid CRC
+                Transaction::read(0x58, vec![0x01, 0x90, 0x00, 0x00, 0x00, 0x81]),
+                Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+            ];
+            let mock = I2cMock::new(&expectations);
+            let mut sgp = Sgp30Async::new(mock, 0x58, NoopDelay);
+            assert_eq!(sgp.last_measurement(), None);
+            assert_eq!(sgp.last_raw_signals(), None);
+            sgp.init().await.unwrap();
+            let measurement = sgp.measure().await.unwrap();
+            assert_eq!(sgp.last_measurement(), Some(measurement));
+            // A failed measurement keeps the last successful one
+            assert!(sgp.measure().await.is_err());
+            assert_eq!(sgp.last_measurement(), Some(measurement));
+            assert_eq!(sgp.last_raw_signals(), None);
+            let signals = sgp.measure_raw_signals().await.unwrap();
+            assert_eq!(sgp.last_raw_signals(), Some(signals));
+            sgp.destroy().done();
+        })
+    }
+
+    /// Test two drivers created with `new_shared` on the same bus.
+    #[cfg(feature = "embassy")]
+    #[test]
+    fn new_shared() {
+        use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+
+        block_on(async {
+            let expectations = [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x59, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+            ];
+            let bus = Mutex::<NoopRawMutex, _>::new(I2cMock::new(&expectations));
+            let mut first = Sgp30Async::new_shared(&bus, 0x58, NoopDelay);
+            let mut second = Sgp30Async::new_shared(&bus, 0x59, NoopDelay);
+            first.init().await.unwrap();
+            second.init().await.unwrap();
+            let signals = first.measure_raw_signals().await.unwrap();
+            assert_eq!(signals.h2, 0x1234);
+            bus.into_inner().done();
+        })
+    }
+
+    /// A delay which only yields to the executor once.
+    struct YieldDelay;
+
+    impl DelayNs for YieldDelay {
+        async fn delay_ns(&mut self, _ns: u32) {
+            let mut yielded = false;
+            core::future::poll_fn(|cx| {
+                if yielded {
+                    return core::task::Poll::Ready(());
+                }
+                yielded = true;
+                cx.waker().wake_by_ref();
+                core::task::Poll::Pending
+            })
+            .await
+        }
+    }
+
+    /// Test the `run_with` function, cancelling it after three events
+    #[test]
+    fn run_with() {
+        use core::{cell::RefCell, future::Future, pin::pin, task::Poll};
+
+        use embedded_hal::i2c::ErrorKind;
+
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            // Warm-up
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+            // Measurement retried in the next interval
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x00; 6]).with_error(ErrorKind::Other),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+            // Failed measurement
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x00; 6]).with_error(ErrorKind::Other),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x00; 6]).with_error(ErrorKind::Other),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30Async::new(mock, 0x58, YieldDelay);
+        let config = RunConfig {
+            warm_up_samples: 1,
+            max_retries: 1,
+            baseline_interval_s: 3,
+        };
+        let events = RefCell::new(Vec::new());
+        block_on(async {
+            let mut run = pin!(sgp.run_with(config, |event| events.borrow_mut().push(event)));
+            core::future::poll_fn(|cx| {
+                let _ = run.as_mut().poll(cx);
+                if events.borrow().len() == 3 {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await
+        });
+        let events = events.into_inner();
+        assert!(matches!(
+            events[0],
+            RunEvent::Measurement(Measurement {
+                co2eq_ppm: 4_660,
+                tvoc_ppb: 54_274
+            })
+        ));
+        assert!(matches!(
+            events[1],
+            RunEvent::Baseline(Baseline {
+                co2eq: 0x1234,
+                tvoc: 0x5678
+            })
+        ));
+        assert!(matches!(events[2], RunEvent::Error(Error::I2cRead { .. })));
+        assert_eq!(events.len(), 3);
+        sgp.destroy().done();
+    }
+
+    /// Test that `run_with` discards the warm-up values when the driver was
+    /// initialized before, e.g. to restore a baseline
+    #[test]
+    fn run_with_initialized() {
+        use core::{cell::RefCell, future::Future, pin::pin, task::Poll};
+
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30Async::new(mock, 0x58, YieldDelay);
+        block_on(sgp.init()).unwrap();
+        let config = RunConfig {
+            baseline_interval_s: 0,
+            ..RunConfig::DEFAULT
+        };
+        let events = RefCell::new(Vec::new());
+        block_on(async {
+            let mut run = pin!(sgp.run_with(config, |event| events.borrow_mut().push(event)));
+            core::future::poll_fn(|cx| {
+                let _ = run.as_mut().poll(cx);
+                if events.borrow().is_empty() {
+                    Poll::Pending
+                } else {
+                    Poll::Ready(())
+                }
+            })
+            .await
+        });
+        let events = events.into_inner();
+        assert!(matches!(
+            events[..],
+            [RunEvent::Measurement(Measurement {
+                co2eq_ppm: 4_660,
+                tvoc_ppb: 54_274
+            })]
+        ));
+        sgp.destroy().done();
+    }
 }
@@ -0,0 +1,226 @@
+//! Long-duration soak test against a real SGP30 on a Linux I²C bus.
+//!
+//! Samples the sensor at 1 Hz for the given number of hours and checks the
+//! cadence of the measurements, the evolution of the baseline, the error
+//! rates and the memory use of the process. At the end, a JSON report is
+//! written to stdout (or the file given with `--output`), progress is
+//! logged to stderr:
+//!
+//! ```text
+//! cargo run --release --features soak-test --bin soak-test -- --hours 24
+//! ```
+//!
+//! The I²C device defaults to `/dev/i2c-1` and can be changed with
+//! `--device`.
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    process::ExitCode,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use linux_embedded_hal::{Delay, I2cdev};
+use serde_json::json;
+use sgp30::{sampler::Sampler, Sgp30};
+
+/// The I²C address of the sensor.
+const ADDRESS: u8 = 0x58;
+
+/// Intervals deviating from 1 s by more than this are counted as late or
+/// early.
+const CADENCE_TOLERANCE: Duration = Duration::from_millis(100);
+
+/// Interval at which the baseline is read and the progress is logged.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The command line options.
+struct Options {
+    hours: f64,
+    device: String,
+    output: Option<String>,
+}
+
+impl Options {
+    fn parse() -> Result<Self, String> {
+        let mut options = Options {
+            hours: 24.0,
+            device: "/dev/i2c-1".into(),
+            output: None,
+        };
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "--hours" => {
+                    let value = value()?;
+                    let hours: f64 = value.parse().map_err(|e| format!("invalid hours: {e}"))?;
+                    let valid = hours.is_finite()
+                        && hours > 0.0
+                        && Duration::try_from_secs_f64(hours * 60.0 * 60.0).is_ok();
+                    if !valid {
+                        return Err(format!("invalid hours: {value}"));
+                    }
+                    options.hours = hours;
+                }
+                "--device" => options.device = value()?,
+                "--output" => options.output = Some(value()?),
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Return the resident set size of the process in bytes.
+fn resident_set_size() -> Option<u64> {
+    // The second field of statm is the resident set size in pages, assume
+    // the common page size of 4 KiB
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
+
+/// Statistics of the intervals between two measurements.
+#[derive(Default)]
+struct Cadence {
+    count: u64,
+    total: Duration,
+    min: Option<Duration>,
+    max: Duration,
+    late: u64,
+    early: u64,
+}
+
+impl Cadence {
+    fn add(&mut self, interval: Duration) {
+        let nominal = Duration::from_secs(1);
+        self.count += 1;
+        self.total += interval;
+        self.min = Some(self.min.map_or(interval, |min| min.min(interval)));
+        self.max = self.max.max(interval);
+        if interval > nominal + CADENCE_TOLERANCE {
+            self.late += 1;
+        } else if interval < nominal - CADENCE_TOLERANCE {
+            self.early += 1;
+        }
+    }
+
+    fn report(&self) -> serde_json::Value {
+        let mean_ms = self
+            .total
+            .checked_div(self.count.try_into().unwrap_or(u32::MAX))
+            .map(|mean| mean.as_secs_f64() * 1000.0);
+        json!({
+            "intervals": self.count,
+            "mean_ms": mean_ms,
+            "min_ms": self.min.map(|min| min.as_secs_f64() * 1000.0),
+            "max_ms": self.max.as_secs_f64() * 1000.0,
+            "late": self.late,
+            "early": self.early,
+        })
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("usage: soak-test [--hours HOURS] [--device DEVICE] [--output FILE]");
+            return ExitCode::FAILURE;
+        }
+    };
+    let dev = match I2cdev::new(&options.device) {
+        Ok(dev) => dev,
+        Err(e) => {
+            eprintln!("cannot open {}: {e}", options.device);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut sgp = Sgp30::new(dev, ADDRESS, Delay);
+    let serial = sgp.serial().ok().map(|serial| {
+        serial
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>()
+    });
+    let mut sampler = Sampler::new(sgp);
+
+    let duration = Duration::from_secs_f64(options.hours * 60.0 * 60.0);
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.as_secs());
+    let start = Instant::now();
+    let rss_start = resident_set_size();
+    let mut rss_max = rss_start;
+
+    let mut cadence = Cadence::default();
+    let mut errors = BTreeMap::<String, u64>::new();
+    let mut measurements = 0u64;
+    let mut baselines = Vec::new();
+    let mut last_sample: Option<Instant> = None;
+    let mut next_checkpoint = CHECKPOINT_INTERVAL;
+
+    eprintln!("Soak test on {} for {} h", options.device, options.hours);
+    while start.elapsed() < duration {
+        match sampler.sample() {
+            Ok(_) => measurements += 1,
+            Err(e) => *errors.entry(format!("{:?}", e.kind())).or_default() += 1,
+        }
+        let now = Instant::now();
+        if let Some(last) = last_sample {
+            cadence.add(now - last);
+        }
+        last_sample = Some(now);
+
+        if start.elapsed() >= next_checkpoint {
+            next_checkpoint += CHECKPOINT_INTERVAL;
+            let hours = start.elapsed().as_secs_f64() / 3600.0;
+            match sampler.driver_mut().get_baseline() {
+                Ok(baseline) => baselines.push(json!({
+                    "hours": hours,
+                    "co2eq": baseline.co2eq,
+                    "tvoc": baseline.tvoc,
+                })),
+                Err(e) => *errors.entry(format!("{:?}", e.kind())).or_default() += 1,
+            }
+            rss_max = rss_max.max(resident_set_size());
+            let total_errors: u64 = errors.values().sum();
+            eprintln!("{hours:.1} h: {measurements} measurements, {total_errors} errors");
+        }
+    }
+
+    let total_errors: u64 = errors.values().sum();
+    let attempts = measurements + total_errors;
+    let report = json!({
+        "device": options.device,
+        "serial": serial,
+        "started_at": started_at,
+        "hours": start.elapsed().as_secs_f64() / 3600.0,
+        "measurements": measurements,
+        "crc_errors": errors.get("Crc").copied().unwrap_or(0),
+        "errors": errors,
+        "error_rate": if attempts == 0 { 0.0 } else { total_errors as f64 / attempts as f64 },
+        "cadence": cadence.report(),
+        "baselines": baselines,
+        "memory": {
+            "sampler_bytes": std::mem::size_of_val(&sampler),
+            "rss_start_bytes": rss_start,
+            "rss_end_bytes": resident_set_size(),
+            "rss_max_bytes": rss_max.max(resident_set_size()),
+        },
+    });
+    let report = serde_json::to_string_pretty(&report).unwrap_or_default();
+    match options.output {
+        Some(path) => {
+            if let Err(e) = fs::write(&path, report + "\n") {
+                eprintln!("cannot write {path}: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+        None => println!("{report}"),
+    }
+    ExitCode::SUCCESS
+}
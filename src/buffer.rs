@@ -0,0 +1,56 @@
+/// A word-aligned buffer for raw sensor responses.
+///
+/// Some HALs can only do DMA I²C transfers from/into suitably aligned memory.
+/// The `*_into` methods of the driver read the sensor response (including the
+/// CRC bytes) directly into such a caller-provided buffer instead of copying
+/// it through a small buffer on the stack.
+///
+/// After a successful read, the raw response can be inspected through
+/// [`as_bytes()`](ReadBuf::as_bytes).
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[repr(C, align(4))]
+pub struct ReadBuf<const N: usize> {
+    bytes: [u8; N],
+}
+
+/// Buffer for the response of the measure, raw signals and baseline commands
+/// (two words with CRC).
+pub type MeasureBuf = ReadBuf<6>;
+
+/// Buffer for the response of the serial number command (three words with
+/// CRC).
+pub type SerialBuf = ReadBuf<9>;
+
+impl<const N: usize> ReadBuf<N> {
+    /// Create a new zeroed buffer.
+    pub const fn new() -> Self {
+        Self { bytes: [0; N] }
+    }
+
+    /// Return the contents of the buffer.
+    pub const fn as_bytes(&self) -> &[u8; N] {
+        &self.bytes
+    }
+
+    /// Return the contents of the buffer mutably.
+    pub fn as_mut_bytes(&mut self) -> &mut [u8; N] {
+        &mut self.bytes
+    }
+}
+
+impl<const N: usize> Default for ReadBuf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment() {
+        assert_eq!(core::mem::align_of::<MeasureBuf>(), 4);
+        assert_eq!(core::mem::align_of::<SerialBuf>(), 4);
+    }
+}
@@ -0,0 +1,469 @@
+//! Summary statistics of the sensor raw signals.
+//!
+//! The raw signals command is intended for part verification and testing
+//! purposes. This module collects the H2 and Ethanol raw signals over a
+//! period of time and summarizes them, e.g. for incoming inspection:
+//!
+//! ```no_run
+//! use linux_embedded_hal::{Delay, I2cdev};
+//! use sgp30::{calibration, Sgp30};
+//!
+//! let dev = I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sgp = Sgp30::new(dev, 0x58, Delay);
+//! sgp.init().unwrap();
+//! // Collect the raw signals for 5 minutes
+//! let report = calibration::collect(&mut sgp, 300).unwrap();
+//! println!("H2: {:?}", report.h2);
+//! println!("Ethanol: {:?}", report.ethanol);
+//! ```
+//!
+//! When measuring in some other way (e.g. with the async driver), the raw
+//! signals can be fed into a [`Calibration`] directly.
+//!
+//! The reference values of the signals in clean air, captured with
+//! [`calibrate_reference()`], are stored in a [`RawCalibration`], which
+//! converts the raw signals into H2 and Ethanol concentrations.
+
+use embedded_hal::delay::DelayNs;
+#[allow(unused_imports)] // Required for no_std
+use num_traits::Float;
+
+use crate::{commands, sampler::Cadence, transport::Sgp30Transport, Error, RawSignals, Sgp30};
+
+/// Summary statistics of a single raw signal.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SignalStats {
+    /// The arithmetic mean of the signal.
+    pub mean: f32,
+    /// The smallest value of the signal.
+    pub min: u16,
+    /// The largest value of the signal.
+    pub max: u16,
+    /// The (population) variance of the signal. The standard deviation is
+    /// the square root of this value.
+    pub variance: f32,
+    /// The drift of the signal, i.e. the slope of a least-squares line fit
+    /// through all samples, in ticks per sample. When sampling at the
+    /// recommended cadence of 1 Hz, this is the drift in ticks per second.
+    pub drift: f32,
+}
+
+impl SignalStats {
+    /// Return the difference between the largest and the smallest value.
+    pub fn range(&self) -> u16 {
+        self.max - self.min
+    }
+}
+
+/// The summary of all raw signals collected by a [`Calibration`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct CalibrationReport {
+    /// The number of samples the statistics are based on.
+    pub samples: u32,
+    /// Statistics of the H2 signal.
+    pub h2: SignalStats,
+    /// Statistics of the Ethanol signal.
+    pub ethanol: SignalStats,
+}
+
+/// Running sums of a single signal.
+#[derive(Debug, Clone)]
+struct Accumulator {
+    sum: u64,
+    // The sums of products overflow a u64 after months of samples at 1 Hz
+    sum_sq: u128,
+    /// Sum of the values weighted by their sample index.
+    sum_xy: u128,
+    min: u16,
+    max: u16,
+}
+
+impl Accumulator {
+    const fn new() -> Self {
+        Self {
+            sum: 0,
+            sum_sq: 0,
+            sum_xy: 0,
+            min: u16::MAX,
+            max: u16::MIN,
+        }
+    }
+
+    fn add(&mut self, index: u32, value: u16) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += u64::from(value);
+        let value = u128::from(value);
+        self.sum_sq += value * value;
+        self.sum_xy += u128::from(index) * value;
+    }
+
+    fn stats(&self, n: u32) -> SignalStats {
+        let n = i128::from(n);
+        let sum = i128::from(self.sum);
+
+        // n² · variance = n · Σy² - (Σy)²
+        let variance = (n * self.sum_sq as i128 - sum * sum) as f64 / (n * n) as f64;
+
+        // With the sample indices x = 0..n as the abscissa, the slope of the
+        // least-squares fit is (n · Σxy - Σx · Σy) / (n · Σx² - (Σx)²).
+        let sum_x = n * (n - 1) / 2;
+        let sum_x_sq = (n - 1) * n * (2 * n - 1) / 6;
+        let denominator = n * sum_x_sq - sum_x * sum_x;
+        let drift = if denominator == 0 {
+            0.0
+        } else {
+            (n * self.sum_xy as i128 - sum_x * sum) as f64 / denominator as f64
+        };
+
+        SignalStats {
+            mean: (sum as f64 / n as f64) as f32,
+            min: self.min,
+            max: self.max,
+            variance: variance as f32,
+            drift: drift as f32,
+        }
+    }
+}
+
+/// Collects raw signals and computes their summary statistics.
+///
+/// No samples are stored, so the memory use is constant regardless of the
+/// number of samples.
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    samples: u32,
+    h2: Accumulator,
+    ethanol: Accumulator,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Calibration {
+    /// Create a new, empty calibration.
+    pub const fn new() -> Self {
+        Self {
+            samples: 0,
+            h2: Accumulator::new(),
+            ethanol: Accumulator::new(),
+        }
+    }
+
+    /// Add a raw signals measurement.
+    ///
+    /// The measurements are expected to be taken at regular intervals, since
+    /// the drift is computed per sample.
+    pub fn add(&mut self, signals: &RawSignals) {
+        self.h2.add(self.samples, signals.h2);
+        self.ethanol.add(self.samples, signals.ethanol);
+        self.samples += 1;
+    }
+
+    /// Return the number of samples collected so far.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Return the summary of the collected samples, or `None` if no samples
+    /// were collected.
+    pub fn report(&self) -> Option<CalibrationReport> {
+        if self.samples == 0 {
+            return None;
+        }
+        Some(self.report_unchecked())
+    }
+
+    /// Return the summary of the collected samples, which must not be empty.
+    fn report_unchecked(&self) -> CalibrationReport {
+        CalibrationReport {
+            samples: self.samples,
+            h2: self.h2.stats(self.samples),
+            ethanol: self.ethanol.stats(self.samples),
+        }
+    }
+}
+
+/// Measure the raw signals `samples` times at 1 s intervals and return the
+/// summary of the measurements.
+///
+/// At least one sample is taken. The air quality measurement must have been
+/// initialized before. Note that the raw signals measurement does not
+/// replace the regular air quality measurements, so the baseline
+/// compensation algorithm is not fed during the collection.
+pub fn collect<I2C, D>(
+    sgp: &mut Sgp30<I2C, D>,
+    samples: u32,
+) -> Result<CalibrationReport, Error<I2C::Error>>
+where
+    I2C: Sgp30Transport,
+    D: DelayNs,
+{
+    let mut cadence = Cadence::new(commands::MEASURE_RAW_SIGNALS.max_duration_us);
+    let mut calibration = Calibration::new();
+    for _ in 0..samples.max(1) {
+        cadence.wait(&mut sgp.delay);
+        calibration.add(&sgp.measure_raw_signals()?);
+    }
+    // At least one sample was added
+    Ok(calibration.report_unchecked())
+}
+
+/// The reference concentration of H2 in clean air in ppm (datasheet).
+const H2_REFERENCE_PPM: f32 = 0.5;
+
+/// The reference concentration of Ethanol in clean air in ppm (datasheet).
+const ETHANOL_REFERENCE_PPM: f32 = 0.4;
+
+/// The raw signal ticks per factor of e in concentration.
+const TICKS_PER_E: f32 = 512.0;
+
+/// The per-device reference values (sref) of the raw signals, for
+/// converting the raw signals into gas concentrations.
+///
+/// The raw signals depend on the individual sensor, so the reference values
+/// are the signals of the device in clean air, captured using
+/// [`calibrate_reference()`] (or from a [`CalibrationReport`]). They can be
+/// stored as 4 bytes (two big-endian words, H2 first) and restored later:
+///
+/// ```
+/// use sgp30::{calibration::RawCalibration, RawSignals};
+///
+/// let calibration = RawCalibration::new(13_119, 18_472);
+/// let stored: [u8; 4] = calibration.into();
+///
+/// let restored = RawCalibration::from(stored);
+/// let signals = RawSignals::new(13_119 - 512, 18_472);
+/// assert!((restored.h2_ppm(&signals) - 0.5 * 1f32.exp()).abs() < 1e-6);
+/// assert_eq!(restored.ethanol_ppm(&signals), 0.4);
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct RawCalibration {
+    /// Reference value of the H2 signal.
+    pub h2: u16,
+    /// Reference value of the Ethanol signal.
+    pub ethanol: u16,
+}
+
+impl RawCalibration {
+    /// Create a calibration from the reference values of the signals.
+    pub const fn new(h2: u16, ethanol: u16) -> Self {
+        Self { h2, ethanol }
+    }
+
+    /// Create a calibration from the mean signals of a report collected in
+    /// clean air.
+    pub fn from_report(report: &CalibrationReport) -> Self {
+        // The means are within the range of the signals
+        Self {
+            h2: report.h2.mean.round() as u16,
+            ethanol: report.ethanol.mean.round() as u16,
+        }
+    }
+
+    /// Return the H2 concentration in ppm.
+    ///
+    /// Computed according to the datasheet as
+    /// `0.5 ppm · exp((sref - sout) / 512)`.
+    pub fn h2_ppm(&self, signals: &RawSignals) -> f32 {
+        concentration(H2_REFERENCE_PPM, self.h2, signals.h2)
+    }
+
+    /// Return the Ethanol concentration in ppm.
+    ///
+    /// Computed according to the datasheet as
+    /// `0.4 ppm · exp((sref - sout) / 512)`.
+    pub fn ethanol_ppm(&self, signals: &RawSignals) -> f32 {
+        concentration(ETHANOL_REFERENCE_PPM, self.ethanol, signals.ethanol)
+    }
+}
+
+impl From<RawCalibration> for [u8; 4] {
+    /// Convert a `RawCalibration` to two big-endian words, H2 first.
+    fn from(val: RawCalibration) -> Self {
+        let h2 = val.h2.to_be_bytes();
+        let ethanol = val.ethanol.to_be_bytes();
+        [h2[0], h2[1], ethanol[0], ethanol[1]]
+    }
+}
+
+impl From<[u8; 4]> for RawCalibration {
+    /// Create a `RawCalibration` from two big-endian words, H2 first.
+    fn from([h2_msb, h2_lsb, ethanol_msb, ethanol_lsb]: [u8; 4]) -> Self {
+        Self {
+            h2: u16::from_be_bytes([h2_msb, h2_lsb]),
+            ethanol: u16::from_be_bytes([ethanol_msb, ethanol_lsb]),
+        }
+    }
+}
+
+/// Return the concentration of a gas with the clean air concentration
+/// `reference_ppm`, given the reference and the current signal.
+fn concentration(reference_ppm: f32, reference: u16, signal: u16) -> f32 {
+    let ticks = f32::from(reference) - f32::from(signal);
+    reference_ppm * (ticks / TICKS_PER_E).exp()
+}
+
+/// Measure the raw signals `samples` times at 1 s intervals and return their
+/// means as the reference values.
+///
+/// This must be run in clean air (e.g. outdoors or in a well ventilated
+/// room), with the sensor warmed up. See [`collect()`] for the requirements.
+pub fn calibrate_reference<I2C, D>(
+    sgp: &mut Sgp30<I2C, D>,
+    samples: u32,
+) -> Result<RawCalibration, Error<I2C::Error>>
+where
+    I2C: Sgp30Transport,
+    D: DelayNs,
+{
+    collect(sgp, samples).map(|report| RawCalibration::from_report(&report))
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::{
+        delay::NoopDelay,
+        i2c::{Mock as I2cMock, Transaction},
+    };
+    use super::*;
+    use crate::commands::CommandId;
+
+    fn signals(h2: u16, ethanol: u16) -> RawSignals {
+        RawSignals { h2, ethanol }
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(Calibration::new().report(), None);
+    }
+
+    #[test]
+    fn constant() {
+        let mut calibration = Calibration::new();
+        calibration.add(&signals(13_000, 18_000));
+        calibration.add(&signals(13_000, 18_000));
+        calibration.add(&signals(13_000, 18_000));
+        let report = calibration.report().unwrap();
+        assert_eq!(report.samples, 3);
+        assert_eq!(
+            report.h2,
+            SignalStats {
+                mean: 13_000.0,
+                min: 13_000,
+                max: 13_000,
+                variance: 0.0,
+                drift: 0.0,
+            }
+        );
+        assert_eq!(report.ethanol.mean, 18_000.0);
+    }
+
+    #[test]
+    fn single_sample() {
+        let mut calibration = Calibration::new();
+        calibration.add(&signals(u16::MAX, 0));
+        let report = calibration.report().unwrap();
+        assert_eq!(report.h2.mean, 65_535.0);
+        assert_eq!(report.h2.variance, 0.0);
+        assert_eq!(report.h2.drift, 0.0);
+        assert_eq!(report.ethanol.range(), 0);
+    }
+
+    #[test]
+    fn drift() {
+        let mut calibration = Calibration::new();
+        for i in 0..10 {
+            calibration.add(&signals(1000 + 2 * i, 1000 - i));
+        }
+        let report = calibration.report().unwrap();
+        assert_eq!(report.h2.drift, 2.0);
+        assert_eq!(report.ethanol.drift, -1.0);
+        assert_eq!(report.h2.mean, 1009.0);
+        assert_eq!(report.h2.variance, 33.0);
+        assert_eq!(report.h2.range(), 18);
+    }
+
+    /// Test a window of about 10 months at 1 Hz
+    #[test]
+    fn long_window() {
+        let mut calibration = Calibration::new();
+        for _ in 0..26_000_000 {
+            calibration.add(&signals(u16::MAX, 0));
+        }
+        let report = calibration.report().unwrap();
+        assert_eq!(report.h2.mean, f32::from(u16::MAX));
+        assert_eq!(report.h2.variance, 0.0);
+        assert_eq!(report.h2.drift, 0.0);
+    }
+
+    /// Test the `collect` function
+    #[test]
+    fn collect_samples() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+            Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x36, 0x55, 0x56, 0x76, 0x62]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        sgp.init().unwrap();
+        let report = collect(&mut sgp, 2).unwrap();
+        assert_eq!(report.samples, 2);
+        assert_eq!(report.h2.min, 0x1234);
+        assert_eq!(report.h2.max, 0x1236);
+        assert_eq!(report.h2.drift, 2.0);
+        assert_eq!(report.ethanol.drift, -2.0);
+        sgp.destroy().done();
+    }
+
+    #[test]
+    fn raw_calibration() {
+        let calibration = RawCalibration::new(13_000, 18_000);
+        assert_eq!(calibration.h2_ppm(&signals(13_000, 0)), 0.5);
+        assert_eq!(calibration.ethanol_ppm(&signals(0, 18_000)), 0.4);
+        // Higher concentrations decrease the signal
+        let ppm = calibration.ethanol_ppm(&signals(0, 18_000 - 1024));
+        assert!((ppm - 0.4 * 2f32.exp()).abs() < 1e-5, "{}", ppm);
+        let ppm = calibration.h2_ppm(&signals(13_512, 0));
+        assert!((ppm - 0.5 / 1f32.exp()).abs() < 1e-6, "{}", ppm);
+
+        let bytes: [u8; 4] = calibration.into();
+        assert_eq!(bytes, [0x32, 0xC8, 0x46, 0x50]);
+        assert_eq!(RawCalibration::from(bytes), calibration);
+    }
+
+    /// Test the `calibrate_reference` function
+    #[test]
+    fn calibrate_reference_signals() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+            Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x36, 0x55, 0x56, 0x76, 0x62]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        sgp.init().unwrap();
+        let calibration = calibrate_reference(&mut sgp, 2).unwrap();
+        assert_eq!(calibration, RawCalibration::new(0x1235, 0x5677));
+        sgp.destroy().done();
+    }
+
+    /// Test the `collect` function without initialization
+    #[test]
+    fn collect_not_initialized() {
+        let mock = I2cMock::new(&[]);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        assert!(matches!(collect(&mut sgp, 2), Err(Error::NotInitialized)));
+        sgp.destroy().done();
+    }
+}
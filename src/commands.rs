@@ -0,0 +1,265 @@
+//! The I²C command table of the SGP30.
+//!
+//! This module exposes the canonical command codes, parameter and response
+//! lengths as well as the typical and maximum execution durations used by
+//! this driver, so that other tooling (bus analyzers, test fixtures,
+//! bindings) doesn't need to duplicate the tables from the datasheet.
+//!
+//! All values are taken from the datasheet (Table 10), except for the
+//! durations of the `get_serial_id` command, which are the recommended wait
+//! time from section 6.5.
+
+use sensirion_i2c::crc8;
+
+/// Static information about a single SGP30 command.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct CommandInfo {
+    /// The 16 bit command code.
+    pub code: u16,
+    /// Number of parameter bytes sent after the command code, including
+    /// CRC bytes.
+    pub parameter_len: usize,
+    /// Number of response bytes returned by the sensor, including CRC bytes.
+    pub response_len: usize,
+    /// Typical duration of the command in microseconds.
+    pub typical_duration_us: u32,
+    /// Maximum duration of the command in microseconds. The response may
+    /// only be read after this time has elapsed.
+    pub max_duration_us: u32,
+}
+
+impl CommandInfo {
+    /// Return the command code as the two bytes sent on the wire.
+    pub const fn code_bytes(&self) -> [u8; 2] {
+        self.code.to_be_bytes()
+    }
+}
+
+/// `Init_air_quality`: Initialize air quality measurements.
+pub const INIT_AIR_QUALITY: CommandInfo = CommandInfo {
+    code: 0x2003,
+    parameter_len: 0,
+    response_len: 0,
+    typical_duration_us: 2_000,
+    max_duration_us: 10_000,
+};
+
+/// `Measure_air_quality`: Get a current air quality measurement.
+pub const MEASURE_AIR_QUALITY: CommandInfo = CommandInfo {
+    code: 0x2008,
+    parameter_len: 0,
+    response_len: 6,
+    typical_duration_us: 10_000,
+    max_duration_us: 12_000,
+};
+
+/// `Get_baseline`: Return the baseline values.
+pub const GET_BASELINE: CommandInfo = CommandInfo {
+    code: 0x2015,
+    parameter_len: 0,
+    response_len: 6,
+    typical_duration_us: 10_000,
+    max_duration_us: 10_000,
+};
+
+/// `Set_baseline`: Set the baseline values.
+pub const SET_BASELINE: CommandInfo = CommandInfo {
+    code: 0x201E,
+    parameter_len: 6,
+    response_len: 0,
+    typical_duration_us: 10_000,
+    max_duration_us: 10_000,
+};
+
+/// `Set_humidity`: Set the current absolute humidity.
+pub const SET_HUMIDITY: CommandInfo = CommandInfo {
+    code: 0x2061,
+    parameter_len: 3,
+    response_len: 0,
+    typical_duration_us: 1_000,
+    max_duration_us: 10_000,
+};
+
+/// `Measure_test`: Run an on-chip self-test.
+pub const MEASURE_TEST: CommandInfo = CommandInfo {
+    code: 0x2032,
+    parameter_len: 0,
+    response_len: 3,
+    typical_duration_us: 200_000,
+    max_duration_us: 220_000,
+};
+
+/// `Get_feature_set_version`: Return the feature set.
+pub const GET_FEATURE_SET: CommandInfo = CommandInfo {
+    code: 0x202F,
+    parameter_len: 0,
+    response_len: 3,
+    typical_duration_us: 1_000,
+    max_duration_us: 2_000,
+};
+
+/// `Measure_raw_signals`: Measure raw signals.
+pub const MEASURE_RAW_SIGNALS: CommandInfo = CommandInfo {
+    code: 0x2050,
+    parameter_len: 0,
+    response_len: 6,
+    typical_duration_us: 20_000,
+    max_duration_us: 25_000,
+};
+
+/// `Get_serial_id`: Return the serial number.
+pub const GET_SERIAL_ID: CommandInfo = CommandInfo {
+    code: 0x3682,
+    parameter_len: 0,
+    response_len: 9,
+    typical_duration_us: 500,
+    max_duration_us: 500,
+};
+
+/// Identifies an I²C command sent to the sensor, e.g. in errors.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum CommandId {
+    /// Return the serial number.
+    GetSerial,
+    /// Run an on-chip self-test.
+    SelfTest,
+    /// Initialize air quality measurements.
+    InitAirQuality,
+    /// Get a current air quality measurement.
+    MeasureAirQuality,
+    /// Measure raw signals.
+    MeasureRawSignals,
+    /// Return the baseline value.
+    GetBaseline,
+    /// Set the baseline value.
+    SetBaseline,
+    /// Set the current absolute humidity.
+    SetHumidity,
+    /// Return the feature set.
+    GetFeatureSet,
+}
+
+impl CommandId {
+    /// All commands, in the order of their declaration.
+    pub const ALL: [CommandId; 9] = [
+        CommandId::GetSerial,
+        CommandId::SelfTest,
+        CommandId::InitAirQuality,
+        CommandId::MeasureAirQuality,
+        CommandId::MeasureRawSignals,
+        CommandId::GetBaseline,
+        CommandId::SetBaseline,
+        CommandId::SetHumidity,
+        CommandId::GetFeatureSet,
+    ];
+
+    /// Return the command with the 16 bit command `code`, if any.
+    pub fn from_code(code: u16) -> Option<CommandId> {
+        CommandId::ALL
+            .into_iter()
+            .find(|command| command.info().code == code)
+    }
+
+    /// Return the static information about this command.
+    pub fn info(self) -> &'static CommandInfo {
+        match self {
+            CommandId::GetSerial => &GET_SERIAL_ID,
+            CommandId::SelfTest => &MEASURE_TEST,
+            CommandId::InitAirQuality => &INIT_AIR_QUALITY,
+            CommandId::MeasureAirQuality => &MEASURE_AIR_QUALITY,
+            CommandId::MeasureRawSignals => &MEASURE_RAW_SIGNALS,
+            CommandId::GetBaseline => &GET_BASELINE,
+            CommandId::SetBaseline => &SET_BASELINE,
+            CommandId::SetHumidity => &SET_HUMIDITY,
+            CommandId::GetFeatureSet => &GET_FEATURE_SET,
+        }
+    }
+
+    pub(crate) fn as_bytes(self) -> [u8; 2] {
+        self.info().code_bytes()
+    }
+
+    /// Writes this command and the provided `data` words with their CRC
+    /// checksums to `buf`, returning a slice of the written portion of
+    /// `buf`.
+    pub(crate) fn as_bytes_with_data(self, buf: &mut [u8; 8], data: CommandData) -> &[u8] {
+        let [c0, c1] = self.as_bytes();
+        match data {
+            CommandData::Data2([d0, d1]) => {
+                *buf = [c0, c1, d0, d1, crc8::calculate(&[d0, d1]), 0, 0, 0];
+                &buf[..5]
+            }
+            CommandData::Data4([d0, d1, d2, d3]) => {
+                *buf = [
+                    c0,
+                    c1,
+                    d0,
+                    d1,
+                    crc8::calculate(&[d0, d1]),
+                    d2,
+                    d3,
+                    crc8::calculate(&[d2, d3]),
+                ];
+                buf
+            }
+        }
+    }
+}
+
+/// The data sent along with a command, without CRC checksums.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub(crate) enum CommandData {
+    /// A single word.
+    Data2([u8; 2]),
+    /// Two words.
+    Data4([u8; 4]),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_bytes() {
+        assert_eq!(GET_SERIAL_ID.code_bytes(), [0x36, 0x82]);
+        assert_eq!(MEASURE_TEST.code_bytes(), [0x20, 0x32]);
+        assert_eq!(INIT_AIR_QUALITY.code_bytes(), [0x20, 0x03]);
+        assert_eq!(MEASURE_AIR_QUALITY.code_bytes(), [0x20, 0x08]);
+        assert_eq!(MEASURE_RAW_SIGNALS.code_bytes(), [0x20, 0x50]);
+        assert_eq!(GET_BASELINE.code_bytes(), [0x20, 0x15]);
+        assert_eq!(SET_BASELINE.code_bytes(), [0x20, 0x1E]);
+        assert_eq!(SET_HUMIDITY.code_bytes(), [0x20, 0x61]);
+        assert_eq!(GET_FEATURE_SET.code_bytes(), [0x20, 0x2F]);
+    }
+
+    #[test]
+    fn from_code() {
+        for command in CommandId::ALL {
+            assert_eq!(CommandId::from_code(command.info().code), Some(command));
+        }
+        assert_eq!(CommandId::from_code(0x2000), None);
+    }
+
+    #[test]
+    fn as_bytes_with_data_parameter_len() {
+        let mut buf = [0; 8];
+        let payload =
+            CommandId::SetHumidity.as_bytes_with_data(&mut buf, CommandData::Data2([0x0F, 0x80]));
+        assert_eq!(payload.len(), 2 + SET_HUMIDITY.parameter_len);
+        let payload =
+            CommandId::SetBaseline.as_bytes_with_data(&mut buf, CommandData::Data4([1, 2, 3, 4]));
+        assert_eq!(payload.len(), 2 + SET_BASELINE.parameter_len);
+    }
+
+    #[test]
+    fn as_bytes_with_data_crc() {
+        let mut buf = [0xFF; 8];
+        let payload = CommandId::SetBaseline
+            .as_bytes_with_data(&mut buf, CommandData::Data4([0xBE, 0xEF, 0, 0]));
+        assert_eq!(payload, [0x20, 0x1E, 0xBE, 0xEF, 0x92, 0x00, 0x00, 0x81]);
+        // The unused part of the buffer doesn't matter
+        let payload =
+            CommandId::SetHumidity.as_bytes_with_data(&mut buf, CommandData::Data2([0xBE, 0xEF]));
+        assert_eq!(payload, [0x20, 0x61, 0xBE, 0xEF, 0x92]);
+    }
+}
@@ -0,0 +1,272 @@
+//! Confidence assessment of air quality measurements.
+//!
+//! Not all measurements of the SGP30 are equally trustworthy: During the
+//! first 15 s after initialization the sensor returns fixed values, the
+//! baseline compensation needs 12 h to settle unless a baseline is restored,
+//! and the measurements are less accurate without up-to-date humidity
+//! compensation. The [`ConfidenceTracker`] keeps track of these conditions
+//! and of recent bus and CRC errors, and attaches a [`Confidence`] to every
+//! measurement:
+//!
+//! ```
+//! use sgp30::{
+//!     confidence::{Confidence, ConfidenceConfig, ConfidenceTracker},
+//!     Measurement,
+//! };
+//!
+//! let mut tracker = ConfidenceTracker::new(ConfidenceConfig::DEFAULT);
+//! tracker.on_init();
+//! tracker.on_baseline_restored(24);
+//! tracker.on_humidity_set();
+//! for _ in 0..15 {
+//!     let assessed = tracker.on_measurement(Measurement::new(400, 0));
+//!     assert_eq!(assessed.confidence, Confidence::Low);
+//! }
+//! let assessed = tracker.on_measurement(Measurement::new(412, 3));
+//! assert_eq!(assessed.confidence, Confidence::High);
+//! ```
+//!
+//! The tracker doesn't access the driver, so the application has to report
+//! the relevant events. Time is counted in measurements, which are expected
+//! to be taken at the configured interval.
+
+use crate::{
+    timings::{BASELINE_LEARNING_S, BASELINE_MAX_AGE_HOURS, INIT_PHASE_S},
+    Measurement,
+};
+
+/// How much a measurement can be trusted.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum Confidence {
+    /// The measurement should be discarded.
+    Low,
+    /// The measurement is plausible, but less accurate.
+    Medium,
+    /// All conditions for accurate measurements are met.
+    High,
+}
+
+/// A measurement together with its confidence.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct AssessedMeasurement {
+    /// The measurement.
+    pub measurement: Measurement,
+    /// The confidence of the measurement.
+    pub confidence: Confidence,
+}
+
+/// Parameters of the [`ConfidenceTracker`].
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct ConfidenceConfig {
+    /// The interval between two measurements in seconds.
+    pub sample_interval_s: u32,
+    /// The maximum age of the humidity compensation value in seconds.
+    /// Older values reduce the confidence to [`Confidence::Medium`].
+    pub humidity_max_age_s: u32,
+    /// The number of measurements after which an error is forgotten.
+    pub error_window: u32,
+    /// The number of errors within the window at which the confidence drops
+    /// to [`Confidence::Low`]. Fewer errors reduce the confidence to
+    /// [`Confidence::Medium`].
+    pub max_errors: u32,
+}
+
+impl ConfidenceConfig {
+    /// Default configuration for measurements taken at 1 Hz, with humidity
+    /// updates at least every 10 minutes.
+    pub const DEFAULT: Self = Self {
+        sample_interval_s: 1,
+        humidity_max_age_s: 10 * 60,
+        error_window: 60,
+        max_errors: 3,
+    };
+}
+
+impl Default for ConfidenceConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Tracks the conditions affecting the measurement confidence.
+#[derive(Debug, Clone)]
+pub struct ConfidenceTracker {
+    config: ConfidenceConfig,
+    /// Measurements since initialization, `None` if not initialized.
+    samples_since_init: Option<u32>,
+    baseline_restored: bool,
+    /// Measurements since the humidity was set, `None` if never set.
+    samples_since_humidity: Option<u32>,
+    recent_errors: u32,
+    samples_since_error: u32,
+}
+
+impl ConfidenceTracker {
+    /// Create a new tracker for a sensor which was not initialized yet.
+    pub const fn new(config: ConfidenceConfig) -> Self {
+        Self {
+            config,
+            samples_since_init: None,
+            baseline_restored: false,
+            samples_since_humidity: None,
+            recent_errors: 0,
+            samples_since_error: 0,
+        }
+    }
+
+    /// Report that the air quality measurement was (re-)initialized.
+    ///
+    /// Initialization resets the baseline, so it has to be restored again.
+    pub fn on_init(&mut self) {
+        self.samples_since_init = Some(0);
+        self.baseline_restored = false;
+    }
+
+    /// Report that a baseline was restored, which was saved `age_hours`
+    /// hours ago.
+    ///
+    /// Baselines older than 7 days are not valid and are ignored.
+    pub fn on_baseline_restored(&mut self, age_hours: u32) {
+        if age_hours <= BASELINE_MAX_AGE_HOURS {
+            self.baseline_restored = true;
+        }
+    }
+
+    /// Report that a humidity value for compensation was sent to the sensor.
+    pub fn on_humidity_set(&mut self) {
+        self.samples_since_humidity = Some(0);
+    }
+
+    /// Report that humidity compensation was turned off.
+    pub fn on_humidity_cleared(&mut self) {
+        self.samples_since_humidity = None;
+    }
+
+    /// Report a bus or CRC error, including errors that were recovered by
+    /// retrying.
+    pub fn on_error(&mut self) {
+        self.recent_errors = self.recent_errors.saturating_add(1);
+        self.samples_since_error = 0;
+    }
+
+    /// Report a measurement, return it together with its confidence.
+    pub fn on_measurement(&mut self, measurement: Measurement) -> AssessedMeasurement {
+        let confidence = self.confidence();
+        self.samples_since_init = self.samples_since_init.map(|n| n.saturating_add(1));
+        self.samples_since_humidity = self.samples_since_humidity.map(|n| n.saturating_add(1));
+        if self.recent_errors > 0 {
+            self.samples_since_error += 1;
+            if self.samples_since_error >= self.config.error_window {
+                self.recent_errors = 0;
+            }
+        }
+        AssessedMeasurement {
+            measurement,
+            confidence,
+        }
+    }
+
+    /// Return the confidence of the next measurement.
+    pub fn confidence(&self) -> Confidence {
+        let interval = self.config.sample_interval_s;
+        let samples_since_init = match self.samples_since_init {
+            Some(n) if n >= INIT_PHASE_S => n,
+            _ => return Confidence::Low,
+        };
+        if self.recent_errors >= self.config.max_errors {
+            return Confidence::Low;
+        }
+
+        let baseline_settled = self.baseline_restored
+            || samples_since_init.saturating_mul(interval) >= BASELINE_LEARNING_S;
+        let humidity_fresh = self
+            .samples_since_humidity
+            .is_some_and(|n| n.saturating_mul(interval) <= self.config.humidity_max_age_s);
+        if baseline_settled && humidity_fresh && self.recent_errors == 0 {
+            Confidence::High
+        } else {
+            Confidence::Medium
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEASUREMENT: Measurement = Measurement::new(400, 0);
+
+    /// Return a tracker past the warm-up phase with all conditions met.
+    fn settled() -> ConfidenceTracker {
+        let mut tracker = ConfidenceTracker::new(ConfidenceConfig::DEFAULT);
+        tracker.on_init();
+        tracker.on_baseline_restored(1);
+        tracker.on_humidity_set();
+        for _ in 0..INIT_PHASE_S {
+            tracker.on_measurement(MEASUREMENT);
+        }
+        tracker
+    }
+
+    #[test]
+    fn not_initialized() {
+        let mut tracker = ConfidenceTracker::new(ConfidenceConfig::DEFAULT);
+        assert_eq!(tracker.confidence(), Confidence::Low);
+        tracker.on_measurement(MEASUREMENT);
+        assert_eq!(
+            tracker.on_measurement(MEASUREMENT).confidence,
+            Confidence::Low
+        );
+    }
+
+    #[test]
+    fn baseline_learning() {
+        assert_eq!(settled().confidence(), Confidence::High);
+
+        let mut tracker = ConfidenceTracker::new(ConfidenceConfig {
+            humidity_max_age_s: u32::MAX,
+            ..ConfidenceConfig::DEFAULT
+        });
+        tracker.on_init();
+        tracker.on_humidity_set();
+        // Too old to be valid
+        tracker.on_baseline_restored(BASELINE_MAX_AGE_HOURS + 1);
+        for _ in 0..BASELINE_LEARNING_S - 1 {
+            tracker.on_measurement(MEASUREMENT);
+        }
+        assert_eq!(tracker.confidence(), Confidence::Medium);
+        tracker.on_measurement(MEASUREMENT);
+        assert_eq!(tracker.confidence(), Confidence::High);
+    }
+
+    #[test]
+    fn humidity_age() {
+        let mut tracker = settled();
+        for _ in INIT_PHASE_S..600 {
+            tracker.on_measurement(MEASUREMENT);
+        }
+        assert_eq!(tracker.confidence(), Confidence::High);
+        tracker.on_measurement(MEASUREMENT);
+        assert_eq!(tracker.confidence(), Confidence::Medium);
+        tracker.on_humidity_set();
+        assert_eq!(tracker.confidence(), Confidence::High);
+        tracker.on_humidity_cleared();
+        assert_eq!(tracker.confidence(), Confidence::Medium);
+    }
+
+    #[test]
+    fn errors() {
+        let mut tracker = settled();
+        tracker.on_error();
+        assert_eq!(tracker.confidence(), Confidence::Medium);
+        tracker.on_error();
+        tracker.on_error();
+        assert_eq!(tracker.confidence(), Confidence::Low);
+        for _ in 0..59 {
+            tracker.on_measurement(MEASUREMENT);
+        }
+        assert_eq!(tracker.confidence(), Confidence::Low);
+        tracker.on_measurement(MEASUREMENT);
+        assert_eq!(tracker.confidence(), Confidence::High);
+    }
+}
@@ -0,0 +1,304 @@
+//! Consistency check of two sensors measuring side by side.
+//!
+//! Two SGP30 units exposed to the same air should report similar values.
+//! A [`Consistency`] keeps running sums of the differences between pairs of
+//! simultaneous measurements, and summarizes them as a [`ConsistencyReport`]
+//! which can be checked against the thresholds of a [`ConsistencyConfig`],
+//! e.g. for incoming inspection against a known good reference unit, or to
+//! detect a degraded sensor in a redundant installation:
+//!
+//! ```
+//! use sgp30::{
+//!     consistency::{Consistency, ConsistencyConfig},
+//!     Measurement,
+//! };
+//!
+//! let mut consistency = Consistency::new();
+//! consistency.add(&Measurement::new(420, 30), &Measurement::new(440, 25));
+//! consistency.add(&Measurement::new(450, 40), &Measurement::new(460, 40));
+//!
+//! let report = consistency.report();
+//! assert_eq!(report.co2eq.mean, -15.0);
+//! assert_eq!(report.tvoc.max_abs, 5);
+//! assert!(report.passed(&ConsistencyConfig::DEFAULT));
+//! ```
+//!
+//! The pairs can come from any source, e.g. from two async drivers. For two
+//! blocking drivers, [`compare()`] takes the measurements itself. Since
+//! both sensors return the fixed values of
+//! [`Measurement::WARM_UP`](crate::Measurement::WARM_UP) during their
+//! initialization phase, which would make them look consistent, only pairs
+//! measured after it should be added.
+
+use embedded_hal::delay::DelayNs;
+
+use crate::{commands, sampler::Cadence, transport::Sgp30Transport, Error, Measurement, Sgp30};
+
+/// Statistics of the differences of a single signal between the two
+/// sensors.
+///
+/// Without any pairs of measurements, all statistics are 0.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DifferenceStats {
+    /// The mean of the differences (first minus second sensor), i.e. the
+    /// systematic offset between the sensors.
+    pub mean: f32,
+    /// The mean of the absolute differences.
+    pub mean_abs: f32,
+    /// The largest absolute difference.
+    pub max_abs: u16,
+}
+
+/// The summary of the differences collected by a [`Consistency`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConsistencyReport {
+    /// The number of pairs of measurements the statistics are based on.
+    pub samples: u32,
+    /// Differences of the CO₂eq values in ppm.
+    pub co2eq: DifferenceStats,
+    /// Differences of the TVOC values in ppb.
+    pub tvoc: DifferenceStats,
+}
+
+impl ConsistencyReport {
+    /// Return whether the mean absolute differences are within the
+    /// thresholds of `config`.
+    ///
+    /// Note that an empty report always passes, check
+    /// [`samples`](Self::samples) to require a minimum number of pairs.
+    pub fn passed(&self, config: &ConsistencyConfig) -> bool {
+        self.co2eq.mean_abs <= f32::from(config.max_co2eq_ppm)
+            && self.tvoc.mean_abs <= f32::from(config.max_tvoc_ppb)
+    }
+}
+
+/// Pass/fail thresholds for a [`ConsistencyReport`].
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct ConsistencyConfig {
+    /// The largest acceptable mean absolute difference of the CO₂eq values
+    /// in ppm.
+    pub max_co2eq_ppm: u16,
+    /// The largest acceptable mean absolute difference of the TVOC values
+    /// in ppb.
+    pub max_tvoc_ppb: u16,
+}
+
+impl ConsistencyConfig {
+    /// Default thresholds of 100 ppm CO₂eq and 50 ppb TVOC, suitable for
+    /// sensors in indoor air.
+    pub const DEFAULT: Self = Self {
+        max_co2eq_ppm: 100,
+        max_tvoc_ppb: 50,
+    };
+}
+
+impl Default for ConsistencyConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The sums of the differences of a single signal.
+#[derive(Debug, Clone)]
+struct DifferenceSums {
+    sum: i64,
+    sum_abs: u64,
+    max_abs: u16,
+}
+
+impl DifferenceSums {
+    const ZERO: Self = Self {
+        sum: 0,
+        sum_abs: 0,
+        max_abs: 0,
+    };
+
+    fn add(&mut self, first: u16, second: u16) {
+        let difference = i64::from(first) - i64::from(second);
+        self.sum += difference;
+        self.sum_abs += difference.unsigned_abs();
+        self.max_abs = self.max_abs.max(first.abs_diff(second));
+    }
+
+    fn stats(&self, samples: u32) -> DifferenceStats {
+        if samples == 0 {
+            return DifferenceStats::default();
+        }
+        let n = f64::from(samples);
+        DifferenceStats {
+            mean: (self.sum as f64 / n) as f32,
+            mean_abs: (self.sum_abs as f64 / n) as f32,
+            max_abs: self.max_abs,
+        }
+    }
+}
+
+/// Compares pairs of measurements of two sensors, see the
+/// [module documentation](self).
+///
+/// Only the sums of the differences are kept, so a comparison can run over
+/// an arbitrarily long window.
+#[derive(Debug, Clone)]
+pub struct Consistency {
+    samples: u32,
+    co2eq: DifferenceSums,
+    tvoc: DifferenceSums,
+}
+
+impl Default for Consistency {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Consistency {
+    /// Create a new consistency check without any pairs.
+    pub const fn new() -> Self {
+        Self {
+            samples: 0,
+            co2eq: DifferenceSums::ZERO,
+            tvoc: DifferenceSums::ZERO,
+        }
+    }
+
+    /// Add a pair of measurements of the first and the second sensor,
+    /// taken at the same time.
+    pub fn add(&mut self, first: &Measurement, second: &Measurement) {
+        self.co2eq.add(first.co2eq_ppm, second.co2eq_ppm);
+        self.tvoc.add(first.tvoc_ppb, second.tvoc_ppb);
+        self.samples += 1;
+    }
+
+    /// Return the number of pairs added so far.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Return the summary of the differences of the pairs added so far.
+    pub fn report(&self) -> ConsistencyReport {
+        ConsistencyReport {
+            samples: self.samples,
+            co2eq: self.co2eq.stats(self.samples),
+            tvoc: self.tvoc.stats(self.samples),
+        }
+    }
+}
+
+/// The error returned by [`compare()`], identifying the failed sensor.
+#[derive(Debug)]
+pub enum CompareError<E1, E2> {
+    /// Measuring with the first sensor failed.
+    First(Error<E1>),
+    /// Measuring with the second sensor failed.
+    Second(Error<E2>),
+}
+
+/// Measure with both sensors `samples` times at 1 s intervals and return the
+/// summary of the differences.
+///
+/// Since the SGP30 has a fixed address, the sensors are either on different
+/// buses or on the same bus behind a multiplexer, with the drivers sharing
+/// it (e.g. through `embedded-hal-bus`). The air quality measurement of
+/// both sensors must have been initialized before, and the measurements
+/// continue their 1 s cadence, using the delay of the first driver.
+pub fn compare<I2C1, D1, I2C2, D2>(
+    first: &mut Sgp30<I2C1, D1>,
+    second: &mut Sgp30<I2C2, D2>,
+    samples: u32,
+) -> Result<ConsistencyReport, CompareError<I2C1::Error, I2C2::Error>>
+where
+    I2C1: Sgp30Transport,
+    D1: DelayNs,
+    I2C2: Sgp30Transport,
+    D2: DelayNs,
+{
+    let mut cadence = Cadence::new(2 * commands::MEASURE_AIR_QUALITY.max_duration_us);
+    let mut consistency = Consistency::new();
+    for _ in 0..samples {
+        cadence.wait(&mut first.delay);
+        let a = first.measure().map_err(CompareError::First)?;
+        let b = second.measure().map_err(CompareError::Second)?;
+        consistency.add(&a, &b);
+    }
+    Ok(consistency.report())
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::{
+        delay::NoopDelay,
+        i2c::{Mock as I2cMock, Transaction},
+    };
+    use super::*;
+    use crate::commands::CommandId;
+
+    #[test]
+    fn empty() {
+        let report = Consistency::new().report();
+        assert_eq!(report.samples, 0);
+        assert_eq!(report.co2eq, DifferenceStats::default());
+        assert!(report.passed(&ConsistencyConfig::DEFAULT));
+    }
+
+    #[test]
+    fn differences() {
+        let mut consistency = Consistency::new();
+        consistency.add(&Measurement::new(450, 10), &Measurement::new(400, 20));
+        consistency.add(&Measurement::new(500, 30), &Measurement::new(430, 20));
+        consistency.add(&Measurement::new(400, 0), &Measurement::new(430, 0));
+        let report = consistency.report();
+        assert_eq!(report.samples, 3);
+        assert_eq!(
+            report.co2eq,
+            DifferenceStats {
+                mean: 30.0,
+                mean_abs: 50.0,
+                max_abs: 70,
+            }
+        );
+        assert_eq!(report.tvoc.mean, 0.0);
+        assert_eq!(report.tvoc.max_abs, 10);
+        assert!(report.passed(&ConsistencyConfig::DEFAULT));
+        assert!(!report.passed(&ConsistencyConfig {
+            max_co2eq_ppm: 40,
+            ..ConsistencyConfig::DEFAULT
+        }));
+    }
+
+    /// Test the `compare` function
+    #[test]
+    fn compare_sensors() {
+        let measure =
+            || Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into());
+        let first = I2cMock::new(&[
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            measure(),
+            Transaction::read(0x58, vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x16]),
+            measure(),
+            Transaction::read(0x58, vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x16]),
+        ]);
+        let second = I2cMock::new(&[
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            measure(),
+            Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x07, 0x16]),
+            measure(),
+            Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x07, 0x00]),
+        ]);
+        let mut first = Sgp30::new(first, 0x58, NoopDelay);
+        let mut second = Sgp30::new(second, 0x58, NoopDelay);
+        first.init().unwrap();
+        second.init().unwrap();
+        let report = compare(&mut first, &mut second, 1).unwrap();
+        assert_eq!(report.samples, 1);
+        assert_eq!(report.co2eq.mean, 12.0);
+        assert_eq!(report.tvoc.max_abs, 0);
+        assert!(matches!(
+            compare(&mut first, &mut second, 1),
+            Err(CompareError::Second(Error::Crc { .. }))
+        ));
+        first.destroy().done();
+        second.destroy().done();
+    }
+}
@@ -0,0 +1,180 @@
+//! Logging of measurements in CSV format.
+//!
+//! The [`CsvLogger`] appends one row per measurement to any
+//! [`std::io::Write`] sink, e.g. a file. Every row starts with the time of
+//! the measurement in seconds since the Unix epoch. The baseline and raw
+//! signal columns are optional:
+//!
+//! ```
+//! use std::time::{Duration, SystemTime};
+//!
+//! use sgp30::{
+//!     csv::{CsvColumns, CsvLogger},
+//!     Measurement, RawSignals,
+//! };
+//!
+//! let columns = CsvColumns {
+//!     baseline: false,
+//!     raw_signals: true,
+//! };
+//! let mut logger = CsvLogger::new(Vec::new(), columns);
+//! logger.write_header().unwrap();
+//! let at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+//! let signals = RawSignals::new(13_000, 18_000);
+//! logger
+//!     .log(at, &Measurement::new(400, 0), None, Some(&signals))
+//!     .unwrap();
+//! assert_eq!(
+//!     String::from_utf8(logger.into_inner()).unwrap(),
+//!     "timestamp,co2eq_ppm,tvoc_ppb,h2,ethanol\n\
+//!      1700000000.500,400,0,13000,18000\n"
+//! );
+//! ```
+//!
+//! This module requires the `std` feature.
+
+use std::{
+    io::{self, Write},
+    time::SystemTime,
+};
+
+use crate::{Baseline, Measurement, RawSignals};
+
+/// The optional columns of a [`CsvLogger`].
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct CsvColumns {
+    /// Add the `baseline_co2eq` and `baseline_tvoc` columns.
+    pub baseline: bool,
+    /// Add the `h2` and `ethanol` raw signal columns.
+    pub raw_signals: bool,
+}
+
+/// Writes measurements as CSV rows, see the [module documentation](self).
+#[derive(Debug)]
+pub struct CsvLogger<W> {
+    writer: W,
+    columns: CsvColumns,
+}
+
+impl<W: Write> CsvLogger<W> {
+    /// Create a new logger writing to `writer`.
+    ///
+    /// No header is written, call [`write_header()`](Self::write_header)
+    /// when starting a new file.
+    pub fn new(writer: W, columns: CsvColumns) -> Self {
+        Self { writer, columns }
+    }
+
+    /// Write the header row.
+    pub fn write_header(&mut self) -> io::Result<()> {
+        write!(self.writer, "timestamp,co2eq_ppm,tvoc_ppb")?;
+        if self.columns.baseline {
+            write!(self.writer, ",baseline_co2eq,baseline_tvoc")?;
+        }
+        if self.columns.raw_signals {
+            write!(self.writer, ",h2,ethanol")?;
+        }
+        writeln!(self.writer)
+    }
+
+    /// Write a row for a measurement taken at `at`.
+    ///
+    /// The `baseline` and `signals` are written to their columns if these
+    /// were enabled, and left empty if `None` is passed (e.g. when the
+    /// baseline is only read every few minutes). Times before the Unix
+    /// epoch are written as `0`.
+    pub fn log(
+        &mut self,
+        at: SystemTime,
+        measurement: &Measurement,
+        baseline: Option<&Baseline>,
+        signals: Option<&RawSignals>,
+    ) -> io::Result<()> {
+        let since_epoch = at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        write!(
+            self.writer,
+            "{}.{:03},{},{}",
+            since_epoch.as_secs(),
+            since_epoch.subsec_millis(),
+            measurement.co2eq_ppm,
+            measurement.tvoc_ppb
+        )?;
+        if self.columns.baseline {
+            match baseline {
+                Some(baseline) => write!(self.writer, ",{},{}", baseline.co2eq, baseline.tvoc)?,
+                None => write!(self.writer, ",,")?,
+            }
+        }
+        if self.columns.raw_signals {
+            match signals {
+                Some(signals) => write!(self.writer, ",{},{}", signals.h2, signals.ethanol)?,
+                None => write!(self.writer, ",,")?,
+            }
+        }
+        writeln!(self.writer)
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Return a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Destroy the logger, return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn all_columns() {
+        let columns = CsvColumns {
+            baseline: true,
+            raw_signals: true,
+        };
+        let mut logger = CsvLogger::new(Vec::new(), columns);
+        logger.write_header().unwrap();
+        let at = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        let measurement = Measurement::new(412, 7);
+        let baseline = Baseline::new(0x8A3C, 0x91F0);
+        let signals = RawSignals::new(13_000, 18_000);
+        logger
+            .log(at, &measurement, Some(&baseline), Some(&signals))
+            .unwrap();
+        logger.log(at, &measurement, None, None).unwrap();
+        assert_eq!(
+            String::from_utf8(logger.into_inner()).unwrap(),
+            "timestamp,co2eq_ppm,tvoc_ppb,baseline_co2eq,baseline_tvoc,h2,ethanol\n\
+             60.000,412,7,35388,37360,13000,18000\n\
+             60.000,412,7,,,,\n"
+        );
+    }
+
+    #[test]
+    fn measurement_only() {
+        let mut logger = CsvLogger::new(Vec::new(), CsvColumns::default());
+        let before_epoch = SystemTime::UNIX_EPOCH - Duration::from_secs(1);
+        let baseline = Baseline::new(1, 2);
+        logger
+            .log(
+                before_epoch,
+                &Measurement::new(400, 0),
+                Some(&baseline),
+                None,
+            )
+            .unwrap();
+        assert_eq!(logger.get_mut().as_slice(), b"0.000,400,0\n");
+    }
+}
@@ -0,0 +1,188 @@
+//! Type-erased access to the blocking driver.
+//!
+//! The [`Sgp30`] driver is generic over the I²C bus and its error type, so
+//! drivers attached to different buses have different types. The
+//! object-safe [`DynAirQualitySensor`] trait erases these types, which
+//! allows heterogeneous collections of sensors:
+//!
+//! ```no_run
+//! use linux_embedded_hal::{Delay, I2cdev};
+//! use sgp30::{erased::DynAirQualitySensor, Sgp30};
+//!
+//! let mut sensors: Vec<Box<dyn DynAirQualitySensor>> = vec![
+//!     Box::new(Sgp30::new(I2cdev::new("/dev/i2c-0").unwrap(), 0x58, Delay)),
+//!     Box::new(Sgp30::new(I2cdev::new("/dev/i2c-1").unwrap(), 0x58, Delay)),
+//! ];
+//! for sensor in &mut sensors {
+//!     sensor.init().unwrap();
+//! }
+//! ```
+//!
+//! Bus errors are converted to the bus-independent
+//! [`embedded_hal::i2c::ErrorKind`], see [`ErasedError`].
+
+use embedded_hal::{delay::DelayNs, i2c};
+
+use crate::{
+    Baseline, Error, FeatureSet, Humidity, Measurement, RawSignals, Sgp30, Sgp30Transport,
+};
+
+/// An [`Error`] with the bus error erased to an [`i2c::ErrorKind`].
+///
+/// The kind of the error and the command in flight are retained.
+pub type ErasedError = Error<i2c::ErrorKind>;
+
+/// An object-safe interface to an SGP30 driver.
+///
+/// See the documentation of the equally named methods of [`Sgp30`] for
+/// details.
+pub trait DynAirQualitySensor {
+    /// Return the 48 bit serial number of the SGP30.
+    fn serial(&mut self) -> Result<[u8; 6], ErasedError>;
+
+    /// Run an on-chip self-test. Return a boolean indicating whether the test
+    /// succeeded.
+    fn selftest(&mut self) -> Result<bool, ErasedError>;
+
+    /// Initialize the air quality measurement.
+    fn init(&mut self) -> Result<(), ErasedError>;
+
+    /// Return whether the air quality measurement was initialized.
+    fn is_initialized(&self) -> bool;
+
+    /// Get an air quality measurement.
+    fn measure(&mut self) -> Result<Measurement, ErasedError>;
+
+    /// Return sensor raw signals.
+    fn measure_raw_signals(&mut self) -> Result<RawSignals, ErasedError>;
+
+    /// Return the baseline values of the baseline correction algorithm.
+    fn get_baseline(&mut self) -> Result<Baseline, ErasedError>;
+
+    /// Set the baseline values for the baseline correction algorithm.
+    fn set_baseline(&mut self, baseline: &Baseline) -> Result<(), ErasedError>;
+
+    /// Set the humidity value for the baseline correction algorithm.
+    fn set_humidity(&mut self, humidity: Option<&Humidity>) -> Result<(), ErasedError>;
+
+    /// Get the feature set.
+    fn get_feature_set(&mut self) -> Result<FeatureSet, ErasedError>;
+}
+
+/// Erase the bus error type of `err`.
+fn erase<E: i2c::Error>(err: Error<E>) -> ErasedError {
+    match err {
+        Error::I2cWrite { command, source } => Error::I2cWrite {
+            command,
+            source: source.kind(),
+        },
+        Error::I2cRead { command, source } => Error::I2cRead {
+            command,
+            source: source.kind(),
+        },
+        Error::DeviceNotResponding { command, source } => Error::DeviceNotResponding {
+            command,
+            source: source.kind(),
+        },
+        Error::Crc { command } => Error::Crc { command },
+        Error::NotInitialized => Error::NotInitialized,
+        Error::BaselineMismatch { written, read } => Error::BaselineMismatch { written, read },
+        Error::Timeout { command } => Error::Timeout { command },
+    }
+}
+
+impl<I2C, D> DynAirQualitySensor for Sgp30<I2C, D>
+where
+    I2C: Sgp30Transport,
+    I2C::Error: i2c::Error,
+    D: DelayNs,
+{
+    fn serial(&mut self) -> Result<[u8; 6], ErasedError> {
+        Sgp30::serial(self).map_err(erase)
+    }
+
+    fn selftest(&mut self) -> Result<bool, ErasedError> {
+        Sgp30::selftest(self).map_err(erase)
+    }
+
+    fn init(&mut self) -> Result<(), ErasedError> {
+        Sgp30::init(self).map_err(erase)
+    }
+
+    fn is_initialized(&self) -> bool {
+        Sgp30::is_initialized(self)
+    }
+
+    fn measure(&mut self) -> Result<Measurement, ErasedError> {
+        Sgp30::measure(self).map_err(erase)
+    }
+
+    fn measure_raw_signals(&mut self) -> Result<RawSignals, ErasedError> {
+        Sgp30::measure_raw_signals(self).map_err(erase)
+    }
+
+    fn get_baseline(&mut self) -> Result<Baseline, ErasedError> {
+        Sgp30::get_baseline(self).map_err(erase)
+    }
+
+    fn set_baseline(&mut self, baseline: &Baseline) -> Result<(), ErasedError> {
+        Sgp30::set_baseline(self, baseline).map_err(erase)
+    }
+
+    fn set_humidity(&mut self, humidity: Option<&Humidity>) -> Result<(), ErasedError> {
+        Sgp30::set_humidity(self, humidity).map_err(erase)
+    }
+
+    fn get_feature_set(&mut self) -> Result<FeatureSet, ErasedError> {
+        Sgp30::get_feature_set(self).map_err(erase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::{
+        delay::NoopDelay,
+        i2c::{Mock as I2cMock, Transaction},
+    };
+    use super::*;
+    use crate::commands::CommandId;
+
+    #[test]
+    fn dyn_sensor() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+        ];
+        let mut sgp = Sgp30::new(I2cMock::new(&expectations), 0x58, NoopDelay);
+        let sensor: &mut dyn DynAirQualitySensor = &mut sgp;
+        assert!(matches!(sensor.measure(), Err(Error::NotInitialized)));
+        sensor.init().unwrap();
+        assert!(sensor.is_initialized());
+        assert_eq!(sensor.measure().unwrap(), Measurement::new(4_660, 54_274));
+        sgp.destroy().done();
+    }
+
+    #[test]
+    fn erased_error() {
+        let expectations =
+            [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into())
+                    .with_error(i2c::ErrorKind::NoAcknowledge(
+                        i2c::NoAcknowledgeSource::Address,
+                    )),
+            ];
+        let mut sgp = Sgp30::new(I2cMock::new(&expectations), 0x58, NoopDelay);
+        let sensor: &mut dyn DynAirQualitySensor = &mut sgp;
+        assert!(matches!(
+            sensor.init(),
+            Err(Error::DeviceNotResponding {
+                command: CommandId::InitAirQuality,
+                source: i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Address),
+            })
+        ));
+        sgp.destroy().done();
+    }
+}
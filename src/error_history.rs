@@ -0,0 +1,106 @@
+use crate::ErrorKind;
+
+/// Number of entries retained in the [`ErrorHistory`].
+pub const ERROR_HISTORY_LEN: usize = 8;
+
+/// A single entry in the [`ErrorHistory`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ErrorRecord {
+    /// Monotonic sequence number of this error, starting at 0 and counting
+    /// every error recorded by the driver (wrapping on overflow).
+    pub seq: u32,
+    /// The kind of error that occurred.
+    pub kind: ErrorKind,
+}
+
+/// A bounded history of the most recent bus and CRC errors of a driver.
+///
+/// The history holds the last [`ERROR_HISTORY_LEN`] errors. Older entries are
+/// overwritten, but the total number of errors is still counted, so gaps in
+/// the [`seq`](ErrorRecord::seq) numbers show how many errors were dropped.
+#[derive(Debug, Default, Clone)]
+pub struct ErrorHistory {
+    entries: [Option<ErrorRecord>; ERROR_HISTORY_LEN],
+    count: u32,
+}
+
+impl ErrorHistory {
+    pub(crate) const fn new() -> Self {
+        Self {
+            entries: [None; ERROR_HISTORY_LEN],
+            count: 0,
+        }
+    }
+
+    /// Record a new error.
+    pub(crate) fn push(&mut self, kind: ErrorKind) {
+        let seq = self.count;
+        self.entries[seq as usize % ERROR_HISTORY_LEN] = Some(ErrorRecord { seq, kind });
+        self.count = seq.wrapping_add(1);
+    }
+
+    /// Return the total number of errors recorded (wrapping on overflow).
+    pub fn total(&self) -> u32 {
+        self.count
+    }
+
+    /// Return the most recent error, if any.
+    pub fn last(&self) -> Option<ErrorRecord> {
+        let index = self.count.wrapping_sub(1) as usize % ERROR_HISTORY_LEN;
+        self.entries[index]
+    }
+
+    /// Iterate over the retained errors, from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = ErrorRecord> + '_ {
+        let start = self.count as usize % ERROR_HISTORY_LEN;
+        (0..ERROR_HISTORY_LEN).filter_map(move |i| self.entries[(start + i) % ERROR_HISTORY_LEN])
+    }
+
+    /// Remove all retained errors. The total error count is not reset.
+    pub fn clear(&mut self) {
+        self.entries = [None; ERROR_HISTORY_LEN];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let history = ErrorHistory::default();
+        assert_eq!(history.total(), 0);
+        assert_eq!(history.last(), None);
+        assert_eq!(history.iter().count(), 0);
+    }
+
+    #[test]
+    fn wraps_around() {
+        let mut history = ErrorHistory::default();
+        history.push(ErrorKind::I2cWrite);
+        for _ in 0..ERROR_HISTORY_LEN {
+            history.push(ErrorKind::Crc);
+        }
+        history.push(ErrorKind::I2cRead);
+        assert_eq!(history.total(), ERROR_HISTORY_LEN as u32 + 2);
+        assert_eq!(
+            history.last(),
+            Some(ErrorRecord {
+                seq: ERROR_HISTORY_LEN as u32 + 1,
+                kind: ErrorKind::I2cRead,
+            })
+        );
+        let seqs: Vec<u32> = history.iter().map(|record| record.seq).collect();
+        assert_eq!(seqs, (2..ERROR_HISTORY_LEN as u32 + 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn clear() {
+        let mut history = ErrorHistory::default();
+        history.push(ErrorKind::Crc);
+        history.clear();
+        assert_eq!(history.total(), 1);
+        assert_eq!(history.last(), None);
+        assert_eq!(history.iter().count(), 0);
+    }
+}
@@ -0,0 +1,448 @@
+//! A circular log of downsampled measurements in NOR flash.
+//!
+//! Battery powered data loggers often have a few spare flash sectors, but no
+//! filesystem. The [`FlashLog`] averages the 1 Hz measurements (see
+//! [`Downsample`]) and appends the averages as fixed-size records to a
+//! region of flash accessed through the [`embedded_storage`] `NorFlash`
+//! traits. When the region is full, the oldest sector is erased and reused:
+//!
+//! ```no_run
+//! # fn run<F: embedded_storage::nor_flash::NorFlash>(flash: F, measurements: &[sgp30::Measurement]) {
+//! use sgp30::flash_log::FlashLog;
+//!
+//! // Use the last 16 KiB of a 2 MiB flash, and log 1 record per minute
+//! let mut log = FlashLog::new(flash, 0x1F_C000, 0x4000, 60).unwrap();
+//! for (seconds, measurement) in measurements.iter().enumerate() {
+//!     log.push(measurement, seconds as u32).unwrap();
+//! }
+//! for record in log.records() {
+//!     println!("{:?}", record.unwrap());
+//! }
+//! # }
+//! ```
+//!
+//! The sectors are used in turn, so every sector is erased once per pass
+//! through the region. Every record carries a sequence number and a CRC,
+//! so the write position is recovered after a restart, and records torn by
+//! a power loss are skipped.
+//!
+//! This module requires the `flash-log` feature.
+
+use embedded_storage::nor_flash::NorFlash;
+use sensirion_i2c::crc8;
+
+use crate::{
+    pipeline::{Downsample, Processor},
+    Measurement,
+};
+
+/// The size of a record in flash in bytes.
+///
+/// The read and write sizes of the flash must divide this size, and this
+/// size must divide the erase size of the flash.
+pub const RECORD_LEN: usize = 16;
+
+/// Marks a written record, erased flash reads as `0xFF`.
+const RECORD_MARKER: u8 = 0x00;
+
+/// A logged measurement.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct LogRecord {
+    /// The sequence number of the record, counting up from 0.
+    pub seq: u32,
+    /// The timestamp passed with the last measurement of the record.
+    pub timestamp: u32,
+    /// The (averaged) measurement.
+    pub measurement: Measurement,
+}
+
+impl LogRecord {
+    /// Encode the record.
+    fn to_bytes(self) -> [u8; RECORD_LEN] {
+        let mut buf = [0; RECORD_LEN];
+        buf[0..4].copy_from_slice(&self.seq.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.timestamp.to_le_bytes());
+        buf[8..10].copy_from_slice(&self.measurement.co2eq_ppm.to_le_bytes());
+        buf[10..12].copy_from_slice(&self.measurement.tvoc_ppb.to_le_bytes());
+        buf[12] = crc8::calculate(&buf[0..12]);
+        buf[13] = RECORD_MARKER;
+        buf
+    }
+
+    /// Decode a record, return `None` for erased or corrupted records.
+    fn from_bytes(buf: &[u8; RECORD_LEN]) -> Option<Self> {
+        if buf[13] != RECORD_MARKER || crc8::calculate(&buf[0..12]) != buf[12] {
+            return None;
+        }
+        let word = |i: usize| u16::from_le_bytes([buf[i], buf[i + 1]]);
+        let dword = |i: usize| u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+        Some(Self {
+            seq: dword(0),
+            timestamp: dword(4),
+            measurement: Measurement::new(word(8), word(10)),
+        })
+    }
+}
+
+/// Errors of the flash log.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum FlashLogError<E> {
+    /// Error of the underlying flash
+    #[cfg_attr(feature = "std", error("flash error: {0:?}"))]
+    Flash(E),
+    /// The region is not aligned to erase sectors, exceeds the flash, or the
+    /// flash geometry is not supported (see [`RECORD_LEN`]).
+    #[cfg_attr(feature = "std", error("invalid flash region"))]
+    InvalidRegion,
+}
+
+/// A circular log of measurements in a region of NOR flash.
+#[derive(Debug)]
+pub struct FlashLog<F> {
+    flash: F,
+    /// Offset of the region from the start of the flash.
+    offset: u32,
+    /// Number of record slots in the region.
+    slots: u32,
+    /// The slot the next record is written to.
+    next_slot: u32,
+    /// The sequence number of the next record.
+    next_seq: u32,
+    downsample: Downsample,
+}
+
+impl<F: NorFlash> FlashLog<F> {
+    /// Open the log in the region of `len` bytes at `offset` of the flash,
+    /// writing a record for every `factor` measurements.
+    ///
+    /// The region must consist of whole erase sectors. It is scanned for
+    /// existing records, and new records are appended after the newest one.
+    pub fn new(
+        flash: F,
+        offset: u32,
+        len: u32,
+        factor: u16,
+    ) -> Result<Self, FlashLogError<F::Error>> {
+        let erase_size = F::ERASE_SIZE as u32;
+        let geometry_supported = RECORD_LEN % F::READ_SIZE == 0
+            && RECORD_LEN % F::WRITE_SIZE == 0
+            && F::ERASE_SIZE % RECORD_LEN == 0;
+        let region_valid = len != 0
+            && offset % erase_size == 0
+            && len % erase_size == 0
+            && offset
+                .checked_add(len)
+                .is_some_and(|end| end as usize <= flash.capacity());
+        if !geometry_supported || !region_valid {
+            return Err(FlashLogError::InvalidRegion);
+        }
+
+        let mut log = Self {
+            flash,
+            offset,
+            slots: len / RECORD_LEN as u32,
+            next_slot: 0,
+            next_seq: 0,
+            downsample: Downsample::new(factor),
+        };
+        let mut newest: Option<(u32, u32)> = None;
+        for slot in 0..log.slots {
+            if let Some(record) = log.read_slot(slot)? {
+                if newest.map_or(true, |(_, seq)| record.seq >= seq) {
+                    newest = Some((slot, record.seq));
+                }
+            }
+        }
+        if let Some((slot, seq)) = newest {
+            log.next_slot = (slot + 1) % log.slots;
+            log.next_seq = seq.wrapping_add(1);
+            log.skip_written_slots()?;
+        }
+        Ok(log)
+    }
+
+    /// Destroy the log, return the flash.
+    pub fn destroy(self) -> F {
+        self.flash
+    }
+
+    /// Return the maximum number of records in the log.
+    ///
+    /// Since a full sector is erased at once, the number of records that can
+    /// be read back varies between this value minus the records of a sector,
+    /// and this value.
+    pub fn capacity(&self) -> u32 {
+        self.slots
+    }
+
+    /// Add a measurement taken at `timestamp` (e.g. in seconds since boot).
+    ///
+    /// Every `factor` measurements, the average of the measurements is
+    /// written to flash, and the written record is returned.
+    pub fn push(
+        &mut self,
+        measurement: &Measurement,
+        timestamp: u32,
+    ) -> Result<Option<LogRecord>, FlashLogError<F::Error>> {
+        match self.downsample.process(*measurement) {
+            Some(averaged) => self.append(&averaged, timestamp).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Write a record with the measurement and `timestamp` immediately,
+    /// without downsampling.
+    pub fn append(
+        &mut self,
+        measurement: &Measurement,
+        timestamp: u32,
+    ) -> Result<LogRecord, FlashLogError<F::Error>> {
+        let address = self.slot_address(self.next_slot);
+        if address % F::ERASE_SIZE as u32 == 0 {
+            // Entering the sector with the oldest records
+            self.flash
+                .erase(address, address + F::ERASE_SIZE as u32)
+                .map_err(FlashLogError::Flash)?;
+        }
+        let record = LogRecord {
+            seq: self.next_seq,
+            timestamp,
+            measurement: *measurement,
+        };
+        self.flash
+            .write(address, &record.to_bytes())
+            .map_err(FlashLogError::Flash)?;
+        self.next_slot = (self.next_slot + 1) % self.slots;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        Ok(record)
+    }
+
+    /// Return an iterator over the records in the log, from the oldest to
+    /// the newest.
+    pub fn records(&mut self) -> Records<'_, F> {
+        Records {
+            remaining: self.slots,
+            slot: self.next_slot,
+            log: self,
+        }
+    }
+
+    /// Erase all records.
+    pub fn clear(&mut self) -> Result<(), FlashLogError<F::Error>> {
+        let start = self.slot_address(0);
+        self.flash
+            .erase(start, start + self.slots * RECORD_LEN as u32)
+            .map_err(FlashLogError::Flash)?;
+        self.next_slot = 0;
+        self.next_seq = 0;
+        Ok(())
+    }
+
+    fn slot_address(&self, slot: u32) -> u32 {
+        self.offset + slot * RECORD_LEN as u32
+    }
+
+    /// Advance the write position past slots which are not erased, e.g. a
+    /// record torn by a power loss, since they cannot be written again. A
+    /// slot at the start of a sector is erased before writing anyway.
+    fn skip_written_slots(&mut self) -> Result<(), FlashLogError<F::Error>> {
+        while self.slot_address(self.next_slot) % F::ERASE_SIZE as u32 != 0
+            && self.read_raw(self.next_slot)? != [0xFF; RECORD_LEN]
+        {
+            self.next_slot = (self.next_slot + 1) % self.slots;
+        }
+        Ok(())
+    }
+
+    fn read_raw(&mut self, slot: u32) -> Result<[u8; RECORD_LEN], FlashLogError<F::Error>> {
+        let mut buf = [0; RECORD_LEN];
+        self.flash
+            .read(self.slot_address(slot), &mut buf)
+            .map_err(FlashLogError::Flash)?;
+        Ok(buf)
+    }
+
+    fn read_slot(&mut self, slot: u32) -> Result<Option<LogRecord>, FlashLogError<F::Error>> {
+        Ok(LogRecord::from_bytes(&self.read_raw(slot)?))
+    }
+}
+
+/// Iterator over the records of a [`FlashLog`], see [`FlashLog::records()`].
+#[derive(Debug)]
+pub struct Records<'a, F> {
+    log: &'a mut FlashLog<F>,
+    slot: u32,
+    remaining: u32,
+}
+
+impl<F: NorFlash> Iterator for Records<'_, F> {
+    type Item = Result<LogRecord, FlashLogError<F::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            let slot = self.slot;
+            self.slot = (self.slot + 1) % self.log.slots;
+            self.remaining -= 1;
+            match self.log.read_slot(slot) {
+                Ok(Some(record)) => return Some(Ok(record)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_storage::nor_flash::{ErrorType, NorFlashErrorKind, ReadNorFlash};
+
+    use super::*;
+
+    const SECTOR: usize = 64;
+
+    /// An in-memory NOR flash with 4 sectors of 4 records each.
+    struct RamFlash {
+        data: [u8; 4 * SECTOR],
+        erases: [u32; 4],
+    }
+
+    impl RamFlash {
+        fn new() -> Self {
+            Self {
+                data: [0xFF; 4 * SECTOR],
+                erases: [0; 4],
+            }
+        }
+    }
+
+    impl ErrorType for RamFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for RamFlash {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl NorFlash for RamFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = SECTOR;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            let (from, to) = (from as usize, to as usize);
+            self.data[from..to].fill(0xFF);
+            for sector in from / SECTOR..to / SECTOR {
+                self.erases[sector] += 1;
+            }
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            for (cell, byte) in self.data[offset..].iter_mut().zip(bytes) {
+                // NOR flash can only clear bits
+                assert_eq!(*cell, 0xFF, "write to unerased cell");
+                *cell = *byte;
+            }
+            Ok(())
+        }
+    }
+
+    fn measurement(i: u32) -> Measurement {
+        Measurement::new(400 + i as u16, i as u16)
+    }
+
+    #[test]
+    fn invalid_region() {
+        assert!(matches!(
+            FlashLog::new(RamFlash::new(), 1, 64, 1),
+            Err(FlashLogError::InvalidRegion)
+        ));
+        assert!(matches!(
+            FlashLog::new(RamFlash::new(), 192, 128, 1),
+            Err(FlashLogError::InvalidRegion)
+        ));
+        assert!(matches!(
+            FlashLog::new(RamFlash::new(), 0, 0, 1),
+            Err(FlashLogError::InvalidRegion)
+        ));
+    }
+
+    #[test]
+    fn downsample() {
+        let mut log = FlashLog::new(RamFlash::new(), 64, 128, 2).unwrap();
+        assert_eq!(log.capacity(), 8);
+        assert_eq!(log.push(&Measurement::new(400, 10), 1), Ok(None));
+        assert_eq!(
+            log.push(&Measurement::new(410, 20), 2),
+            Ok(Some(LogRecord {
+                seq: 0,
+                timestamp: 2,
+                measurement: Measurement::new(405, 15),
+            }))
+        );
+        let records: Vec<_> = log.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 1);
+        // The sector before the region is untouched
+        let flash = log.destroy();
+        assert!(flash.data[..SECTOR].iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut log = FlashLog::new(RamFlash::new(), 0, 4 * SECTOR as u32, 1).unwrap();
+        for i in 0..40 {
+            log.append(&measurement(i), i).unwrap();
+        }
+        // The oldest sector was erased to make room for the newest records
+        let seqs: Vec<_> = log.records().map(|r| r.unwrap().seq).collect();
+        assert_eq!(seqs, (24..40).collect::<Vec<_>>());
+        // Wear is spread evenly
+        assert_eq!(log.destroy().erases, [3, 3, 2, 2]);
+    }
+
+    #[test]
+    fn reopen() {
+        let mut log = FlashLog::new(RamFlash::new(), 0, 4 * SECTOR as u32, 1).unwrap();
+        for i in 0..21 {
+            log.append(&measurement(i), i).unwrap();
+        }
+        let mut flash = log.destroy();
+        // A corrupted record is skipped
+        flash.data[12 * RECORD_LEN] ^= 0x01;
+
+        let mut log = FlashLog::new(flash, 0, 4 * SECTOR as u32, 1).unwrap();
+        let record = log.append(&measurement(99), 99).unwrap();
+        assert_eq!(record.seq, 21);
+        let records: Vec<_> = log.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 13);
+        assert_eq!(records.first().unwrap().seq, 8);
+        assert_eq!(records.last().unwrap(), &record);
+
+        // The slot of a record torn by a power loss is not written again
+        log.append(&measurement(100), 100).unwrap();
+        let mut flash = log.destroy();
+        flash.data[6 * RECORD_LEN] ^= 0x01;
+        let mut log = FlashLog::new(flash, 0, 4 * SECTOR as u32, 1).unwrap();
+        let record = log.append(&measurement(101), 101).unwrap();
+        assert_eq!(record.seq, 22);
+        let records: Vec<_> = log.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 14);
+        assert_eq!(records.last().unwrap(), &record);
+
+        log.clear().unwrap();
+        assert_eq!(log.records().count(), 0);
+        assert_eq!(log.append(&measurement(0), 0).unwrap().seq, 0);
+    }
+}
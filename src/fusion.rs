@@ -0,0 +1,240 @@
+//! Fusion with the readings of a true CO₂ sensor.
+//!
+//! The CO₂eq value of the SGP30 is derived from the H2 signal, so it only
+//! approximates the CO₂ level. Many products therefore combine the SGP30
+//! for TVOC with an NDIR sensor (such as the SCD4x) for the true CO₂ level.
+//! The [`Fusion`] reads the CO₂ level from a [`Co2Source`] for every
+//! measurement and combines both into an [`IndoorAirReport`]. If the CO₂eq
+//! value and the true CO₂ level diverge badly, the report is flagged, e.g.
+//! to detect a faulty sensor or strong VOC sources:
+//!
+//! ```
+//! use sgp30::{
+//!     fusion::{Fusion, FusionConfig},
+//!     Measurement,
+//! };
+//!
+//! // E.g. reading an SCD4x
+//! let read_co2_ppm = || Ok::<u16, ()>(650);
+//!
+//! let config = FusionConfig {
+//!     diverged_samples: 2,
+//!     ..FusionConfig::DEFAULT
+//! };
+//! let mut fusion = Fusion::new(read_co2_ppm, config);
+//!
+//! let report = fusion.report(&Measurement::new(700, 120)).unwrap();
+//! assert_eq!(report.co2_ppm, 650);
+//! assert_eq!(report.deviation_ppm, 50);
+//! assert!(!report.diverged);
+//!
+//! fusion.report(&Measurement::new(1500, 120)).unwrap();
+//! let report = fusion.report(&Measurement::new(1600, 120)).unwrap();
+//! assert!(report.diverged);
+//! ```
+//!
+//! Both sensors respond with a different delay to changes of the air, so a
+//! divergence is only flagged after it persisted for
+//! [`diverged_samples`](FusionConfig::diverged_samples) reports.
+
+use crate::{aqi::Aqi, Measurement};
+
+/// A source of true CO₂ readings, e.g. an NDIR sensor driver.
+///
+/// Closures of type `FnMut() -> Result<u16, E>` implement this trait.
+pub trait Co2Source {
+    /// The error of a failed reading.
+    type Error;
+
+    /// Return the current CO₂ level in ppm.
+    fn read_co2_ppm(&mut self) -> Result<u16, Self::Error>;
+}
+
+impl<F, E> Co2Source for F
+where
+    F: FnMut() -> Result<u16, E>,
+{
+    type Error = E;
+
+    fn read_co2_ppm(&mut self) -> Result<u16, E> {
+        self()
+    }
+}
+
+/// Thresholds of the [`Fusion`].
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct FusionConfig {
+    /// The CO₂eq value diverges if it differs from the true CO₂ level by
+    /// more than this many ppm...
+    pub max_deviation_ppm: u16,
+    /// ...and by more than this fraction of the true CO₂ level (e.g. `0.5`
+    /// for 50 %).
+    pub max_relative_deviation: f32,
+    /// The number of consecutive diverging reports after which the reports
+    /// are flagged. A value of 0 is treated as 1.
+    pub diverged_samples: u32,
+}
+
+impl FusionConfig {
+    /// Default configuration for reports at 1 Hz, flagging a deviation of
+    /// more than 400 ppm and 50 % that persisted for 10 minutes.
+    pub const DEFAULT: Self = Self {
+        max_deviation_ppm: 400,
+        max_relative_deviation: 0.5,
+        diverged_samples: 10 * 60,
+    };
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The combined readings of the SGP30 and a true CO₂ sensor.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct IndoorAirReport {
+    /// True CO₂ level (parts per million, ppm)
+    pub co2_ppm: u16,
+    /// CO₂ equivalent of the SGP30 (parts per million, ppm)
+    pub co2eq_ppm: u16,
+    /// Total Volatile Organic Compounds (parts per billion, ppb)
+    pub tvoc_ppb: u16,
+    /// The CO₂eq value minus the true CO₂ level in ppm.
+    pub deviation_ppm: i32,
+    /// Whether the CO₂eq value and the true CO₂ level diverged for too
+    /// long, see [`FusionConfig`].
+    pub diverged: bool,
+    /// The air quality index, based on the true CO₂ level and the TVOC
+    /// value.
+    pub aqi: Aqi,
+}
+
+/// Combines the measurements with the readings of a true CO₂ sensor, see
+/// the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Fusion<S> {
+    source: S,
+    config: FusionConfig,
+    diverging: u32,
+}
+
+impl<S: Co2Source> Fusion<S> {
+    /// Create a new fusion reading the true CO₂ level from `source`.
+    pub const fn new(source: S, config: FusionConfig) -> Self {
+        Self {
+            source,
+            config,
+            diverging: 0,
+        }
+    }
+
+    /// Read the true CO₂ level and combine it with `measurement`.
+    ///
+    /// If the reading fails, the error is returned and the divergence
+    /// tracking is not updated.
+    pub fn report(&mut self, measurement: &Measurement) -> Result<IndoorAirReport, S::Error> {
+        let co2_ppm = self.source.read_co2_ppm()?;
+        Ok(self.update(measurement, co2_ppm))
+    }
+
+    /// Combine `measurement` with a true CO₂ level which was read
+    /// elsewhere, e.g. by an async driver.
+    pub fn update(&mut self, measurement: &Measurement, co2_ppm: u16) -> IndoorAirReport {
+        let deviation_ppm = i32::from(measurement.co2eq_ppm) - i32::from(co2_ppm);
+        let deviation = deviation_ppm.unsigned_abs();
+        let relative_limit = self.config.max_relative_deviation * f32::from(co2_ppm);
+        if deviation > u32::from(self.config.max_deviation_ppm) && deviation as f32 > relative_limit
+        {
+            self.diverging = self.diverging.saturating_add(1);
+        } else {
+            self.diverging = 0;
+        }
+        IndoorAirReport {
+            co2_ppm,
+            co2eq_ppm: measurement.co2eq_ppm,
+            tvoc_ppb: measurement.tvoc_ppb,
+            deviation_ppm,
+            diverged: self.diverging >= self.config.diverged_samples.max(1),
+            aqi: Aqi::from_measurement(&Measurement::new(co2_ppm, measurement.tvoc_ppb)),
+        }
+    }
+
+    /// Return a mutable reference to the CO₂ source.
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Destroy the fusion, return the CO₂ source.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A CO₂ sensor returning fixed readings.
+    struct FakeCo2(u16);
+
+    impl Co2Source for FakeCo2 {
+        type Error = ();
+
+        fn read_co2_ppm(&mut self) -> Result<u16, ()> {
+            match self.0 {
+                0 => Err(()),
+                co2_ppm => Ok(co2_ppm),
+            }
+        }
+    }
+
+    #[test]
+    fn divergence() {
+        let config = FusionConfig {
+            diverged_samples: 3,
+            ..FusionConfig::DEFAULT
+        };
+        let mut fusion = Fusion::new(FakeCo2(1000), config);
+        // Within 50 %, even if more than 400 ppm
+        let report = fusion.report(&Measurement::new(1450, 0)).unwrap();
+        assert_eq!(report.deviation_ppm, 450);
+        assert!(!report.diverged);
+
+        // Diverging for 3 samples, interrupted by a failed reading
+        assert!(!fusion.report(&Measurement::new(1600, 0)).unwrap().diverged);
+        assert!(!fusion.report(&Measurement::new(400, 0)).unwrap().diverged);
+        fusion.source_mut().0 = 0;
+        assert_eq!(fusion.report(&Measurement::new(400, 0)), Err(()));
+        fusion.source_mut().0 = 1000;
+        let report = fusion.report(&Measurement::new(400, 0)).unwrap();
+        assert_eq!(report.deviation_ppm, -600);
+        assert!(report.diverged);
+
+        // Recovered
+        assert!(!fusion.update(&Measurement::new(1100, 0), 1000).diverged);
+    }
+
+    #[test]
+    fn small_levels() {
+        // More than 50 %, but within 400 ppm
+        let mut fusion = Fusion::new(FakeCo2(400), FusionConfig::DEFAULT);
+        for _ in 0..FusionConfig::DEFAULT.diverged_samples {
+            assert!(!fusion.report(&Measurement::new(750, 0)).unwrap().diverged);
+        }
+    }
+
+    #[test]
+    fn aqi() {
+        let mut fusion = Fusion::new(FakeCo2(450), FusionConfig::DEFAULT);
+        let measurement = Measurement::new(2000, 100);
+        let report = fusion.report(&measurement).unwrap();
+        assert_eq!(report.co2eq_ppm, 2000);
+        assert_eq!(report.tvoc_ppb, 100);
+        assert_eq!(
+            report.aqi,
+            Aqi::from_measurement(&Measurement::new(450, 100))
+        );
+        assert_ne!(report.aqi, Aqi::from_measurement(&measurement));
+    }
+}
@@ -0,0 +1,137 @@
+//! Interrupt-safe access to a driver stored in a `static`.
+//!
+//! On bare-metal targets the driver often has to be shared between the main
+//! loop and interrupt handlers, e.g. a timer interrupt triggering the 1 s
+//! measurements. The [`StaticSgp30`] wraps the driver in a
+//! [`critical_section::Mutex`], so it can be placed into a `static` and
+//! accessed from any context:
+//!
+//! ```
+//! use embedded_hal_mock::eh1::{
+//!     delay::NoopDelay,
+//!     i2c::{Mock as I2cMock, Transaction},
+//! };
+//! use sgp30::{global::StaticSgp30, Sgp30};
+//!
+//! static SGP30: StaticSgp30<I2cMock, NoopDelay> = StaticSgp30::new();
+//!
+//! # let mock = I2cMock::new(&[Transaction::write(0x58, vec![0x20, 0x03])]);
+//! // During startup
+//! SGP30.put(Sgp30::new(mock, 0x58, NoopDelay)).ok().unwrap();
+//!
+//! // In an interrupt handler or any other context
+//! let result = SGP30.with(|sgp| sgp.init());
+//! assert!(matches!(result, Some(Ok(()))));
+//! # SGP30.take().unwrap().destroy().done();
+//! ```
+//!
+//! The driver is used inside a critical section, so interrupts are blocked
+//! for the duration of every command, including the command's execution
+//! time (e.g. 12 ms for a measurement). Keep this in mind for
+//! latency-sensitive interrupts. A driver which is available from the start
+//! can be shared using [`SharedSgp30`](crate::shared::SharedSgp30) instead.
+//!
+//! This module requires the `critical-section` feature. An implementation
+//! of the critical section must be provided by the application, usually by
+//! enabling a feature of the HAL or of the `cortex-m` crate.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use crate::Sgp30;
+
+/// A driver which can be stored in a `static` and accessed from interrupt
+/// handlers, see the [module documentation](self).
+#[derive(Debug)]
+pub struct StaticSgp30<I2C, D> {
+    inner: Mutex<RefCell<Option<Sgp30<I2C, D>>>>,
+}
+
+impl<I2C, D> StaticSgp30<I2C, D> {
+    /// Create a new empty wrapper.
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Store the driver in the wrapper.
+    ///
+    /// If a driver was already stored, the new driver is returned in the
+    /// error variant instead.
+    pub fn put(&self, sgp: Sgp30<I2C, D>) -> Result<(), Sgp30<I2C, D>> {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            if inner.is_some() {
+                return Err(sgp);
+            }
+            *inner = Some(sgp);
+            Ok(())
+        })
+    }
+
+    /// Call `f` with the driver inside a critical section, return its
+    /// result.
+    ///
+    /// Returns `None` if no driver was stored.
+    ///
+    /// # Panics
+    ///
+    /// If called from within `f`.
+    pub fn with<R>(&self, f: impl FnOnce(&mut Sgp30<I2C, D>) -> R) -> Option<R> {
+        critical_section::with(|cs| self.inner.borrow_ref_mut(cs).as_mut().map(f))
+    }
+
+    /// Return whether a driver was stored.
+    pub fn is_set(&self) -> bool {
+        critical_section::with(|cs| self.inner.borrow_ref(cs).is_some())
+    }
+
+    /// Remove the driver from the wrapper and return it.
+    pub fn take(&self) -> Option<Sgp30<I2C, D>> {
+        critical_section::with(|cs| self.inner.borrow_ref_mut(cs).take())
+    }
+}
+
+impl<I2C, D> Default for StaticSgp30<I2C, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        i2c::{Mock as I2cMock, Transaction},
+    };
+
+    use super::*;
+    use crate::commands::CommandId;
+
+    #[test]
+    fn put_with_take() {
+        let wrapper = StaticSgp30::<I2cMock, NoopDelay>::new();
+        assert!(!wrapper.is_set());
+        assert_eq!(wrapper.with(|sgp| sgp.is_initialized()), None);
+
+        let expectations = [Transaction::write(
+            0x58,
+            CommandId::InitAirQuality.as_bytes()[..].into(),
+        )];
+        let sgp = Sgp30::new(I2cMock::new(&expectations), 0x58, NoopDelay);
+        assert!(wrapper.put(sgp).is_ok());
+        assert!(wrapper.is_set());
+        let other = Sgp30::new(I2cMock::new(&[]), 0x58, NoopDelay);
+        match wrapper.put(other) {
+            Ok(()) => panic!("driver was replaced"),
+            Err(other) => other.destroy().done(),
+        }
+
+        assert_eq!(wrapper.with(|sgp| sgp.init().is_ok()), Some(true));
+        assert_eq!(wrapper.with(|sgp| sgp.is_initialized()), Some(true));
+        wrapper.take().unwrap().destroy().done();
+        assert!(wrapper.take().is_none());
+    }
+}
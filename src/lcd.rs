@@ -0,0 +1,128 @@
+//! Fixed-width formatting of measurements for character displays.
+//!
+//! The formatted output has a constant width regardless of the measured
+//! values, which makes it suitable for character LCDs. On 16x2 or 20x4
+//! displays, the two fields are written to separate rows using
+//! [`write_co2eq()`] and [`write_tvoc()`]:
+//!
+//! ```
+//! use sgp30::{lcd, Measurement};
+//!
+//! let measurement = Measurement {
+//!     co2eq_ppm: 412,
+//!     tvoc_ppb: 13,
+//! };
+//! let (mut row1, mut row2) = (String::new(), String::new());
+//! lcd::write_co2eq(&mut row1, &measurement).unwrap();
+//! lcd::write_tvoc(&mut row2, &measurement).unwrap();
+//! assert_eq!(row1, "CO2   412ppm");
+//! assert_eq!(row2, "VOC    13ppb");
+//! ```
+//!
+//! Displays with at least [`LINE_LEN`] columns fit both fields on a single
+//! line, see [`format_line()`].
+//!
+//! All functions writing to a [`core::fmt::Write`] implementation can also
+//! be used with string types such as `heapless::String`.
+
+use core::fmt::{self, Write};
+
+use crate::Measurement;
+
+/// The width of a single field (e.g. `"CO2   412ppm"`).
+///
+/// The label is always followed by a space, so that five digit values
+/// remain readable.
+pub const FIELD_LEN: usize = 12;
+
+/// The width of a full line containing both fields, separated by a space.
+pub const LINE_LEN: usize = 2 * FIELD_LEN + 1;
+
+/// Write the CO₂eq field (e.g. `"CO2   412ppm"`) to `w`.
+pub fn write_co2eq<W: Write>(w: &mut W, measurement: &Measurement) -> fmt::Result {
+    write!(w, "CO2 {:>5}ppm", measurement.co2eq_ppm)
+}
+
+/// Write the TVOC field (e.g. `"VOC    13ppb"`) to `w`.
+pub fn write_tvoc<W: Write>(w: &mut W, measurement: &Measurement) -> fmt::Result {
+    write!(w, "VOC {:>5}ppb", measurement.tvoc_ppb)
+}
+
+/// Write both fields (e.g. `"CO2   412ppm VOC    13ppb"`) to `w`.
+///
+/// The line is [`LINE_LEN`] characters wide, which is too wide for 16 or
+/// 20 column displays.
+pub fn write_line<W: Write>(w: &mut W, measurement: &Measurement) -> fmt::Result {
+    write_co2eq(w, measurement)?;
+    w.write_str(" ")?;
+    write_tvoc(w, measurement)
+}
+
+/// Format both fields into the caller-provided `buf`, returning the
+/// formatted line.
+///
+/// This is meant for displays with at least [`LINE_LEN`] columns, on 16 or
+/// 20 column displays use [`write_co2eq()`] and [`write_tvoc()`] instead.
+pub fn format_line<'buf>(measurement: &Measurement, buf: &'buf mut [u8; LINE_LEN]) -> &'buf str {
+    let mut cursor = Cursor { buf, pos: 0 };
+    // The output always fits into the buffer, since every value of a u16
+    // fits into the five character wide number columns.
+    let _ = write_line(&mut cursor, measurement);
+    let len = cursor.pos;
+    core::str::from_utf8(&buf[..len]).unwrap_or_default()
+}
+
+/// A [`fmt::Write`] implementation writing into a fixed-size byte buffer.
+struct Cursor<'buf> {
+    buf: &'buf mut [u8],
+    pos: usize,
+}
+
+impl Write for Cursor<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.pos + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.pos..end].copy_from_slice(s.as_bytes());
+        self.pos = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(co2eq_ppm: u16, tvoc_ppb: u16) -> Measurement {
+        Measurement {
+            co2eq_ppm,
+            tvoc_ppb,
+        }
+    }
+
+    #[test]
+    fn format_line_fixed_width() {
+        let mut buf = [0; LINE_LEN];
+        assert_eq!(
+            format_line(&measurement(400, 0), &mut buf),
+            "CO2   400ppm VOC     0ppb"
+        );
+        assert_eq!(
+            format_line(&measurement(65535, 65535), &mut buf),
+            "CO2 65535ppm VOC 65535ppb"
+        );
+    }
+
+    #[test]
+    fn write_fields() {
+        let mut co2eq = String::new();
+        write_co2eq(&mut co2eq, &measurement(1234, 56)).unwrap();
+        assert_eq!(co2eq, "CO2  1234ppm");
+        let mut tvoc = String::new();
+        write_tvoc(&mut tvoc, &measurement(1234, 56)).unwrap();
+        assert_eq!(tvoc, "VOC    56ppb");
+        assert_eq!(co2eq.len(), FIELD_LEN);
+        assert_eq!(tvoc.len(), FIELD_LEN);
+    }
+}
@@ -104,6 +104,11 @@
 //!
 //! A new init command has to be sent after every power-up or soft reset.
 //!
+//! The [`sampler`] module provides an iterator that takes care of the
+//! initialization and the 1 s cadence, optionally attaching timestamps to
+//! every measurement. With the `std` feature enabled, a wall-clock based
+//! `SystemTimeClock` is available for that purpose.
+//!
 //! ### Restoring Baseline Values
 //!
 //! The SGP30 provides the possibility to read and write the values of the
@@ -150,8 +155,9 @@
 //! # fn main() {
 //! # let dev = I2cdev::new("/dev/i2c-1").unwrap();
 //! # let mut sgp = Sgp30::new(dev, 0x58, Delay);
-//! // This value must be obtained from a separate humidity sensor
-//! let humidity = Humidity::from_f32(23.42).unwrap();
+//! // This value must be obtained from a separate humidity sensor, here
+//! // 23.42 g/m³ as integer and fractional part (see also `from_f32()`)
+//! let humidity = Humidity::new(0x17, 0x6B).unwrap();
 //!
 //! sgp.init().unwrap();
 //! sgp.set_humidity(Some(&humidity)).unwrap();
@@ -162,8 +168,59 @@
 //! on-chip humidity compensation algorithm until a new humidity value is
 //! set. Restarting the sensor (power-on or soft reset) or calling the
 //! function with a `None` value sets the humidity value used for
-//! compensation to its default value (11.57 g/m³) until a new humidity
-//! value is sent.
+//! compensation to its default value ([`Humidity::DEFAULT`], 11.57 g/m³)
+//! until a new humidity value is sent.
+//!
+//! With the `serde` feature, [`Humidity`] can be serialized. Human-readable
+//! formats like JSON use the value in g/m³ (e.g. `16.5`), while binary
+//! formats like postcard use the compact 8.8 fixed-point value as `u16`.
+//! Both representations round-trip without loss.
+//!
+//! With the `rkyv` feature, [`Measurement`] and [`Baseline`] implement the
+//! [rkyv](https://docs.rs/rkyv/0.7) `Archive`, `Serialize` and
+//! `Deserialize` traits, so histories of them can be memory-mapped or
+//! shared over zero-copy IPC. The archived types can be validated with
+//! `rkyv::check_archived_root()` and compared with the original types.
+//!
+//! With the `prost` feature, the [`proto`] module provides Protocol Buffers
+//! messages for measurements, baselines and health reports, matching the
+//! definitions in `proto/sgp30.proto`.
+//!
+//! With the `uom` feature, [`Humidity`] converts from and into
+//! [`uom`](https://docs.rs/uom) mass concentrations, and the values of a
+//! [`Measurement`] are available as typed quantities (e.g.
+//! [`Measurement::co2eq()`] and [`Measurement::co2eq_concentration()`]).
+//!
+//! ### Raw Signals
+//!
+//! For part verification and testing, the sensor raw signals (H2 and
+//! Ethanol) can be read with `measure_raw_signals()`. The [`calibration`]
+//! module summarizes the raw signals collected over a period of time. To
+//! read the raw signals without initializing the air quality measurement,
+//! enable the raw signals only mode with
+//! [`set_raw_signals_only()`](Sgp30::set_raw_signals_only).
+//!
+//! ## Optional functionality
+//!
+//! The processing modules on top of the driver are enabled by default and
+//! can be disabled individually to trim dependencies and code size, e.g. on
+//! small microcontrollers:
+//!
+//! - `alerts`: [`spike`] and [`fusion`] (requires `aqi`)
+//! - `aqi`: [`aqi`]
+//! - `diagnostics`: [`aging`], [`calibration`], [`confidence`] and
+//!   [`consistency`]
+//! - `filters`: [`pipeline`] and [`archive`]
+//! - `humidity-float`: the floating point constructors of [`Humidity`],
+//!   e.g. [`Humidity::from_f32()`]
+//! - `lcd`: [`lcd`]
+//!
+//! The `diagnostics` and `humidity-float` features depend on `num-traits`.
+//! To use the bare driver, disable the default features:
+//!
+//! ```toml
+//! sgp30 = { version = "1", default-features = false }
+//! ```
 //!
 //! ## `embedded-hal-async` support
 //!
@@ -185,127 +242,256 @@
 //! traits. The [`Sgp30Async`] struct is identical to the [`Sgp30`] struct,
 //! except that its methods are `async fn`s.
 //!
+//! With the `embassy` feature, [`Sgp30Async::new_shared()`] creates a driver
+//! on an I²C bus shared with other devices through `embassy-embedded-hal`,
+//! and the samplers can publish their measurements into an `embassy-sync`
+//! `Watch`. The dependencies of this feature require a more recent Rust
+//! version than the rest of the crate.
+//!
 //! [`embedded-hal-async`]: https://crates.io/crates/embedded-hal-async
 //! [`embedded_hal_async::i2c::I2c`]: https://docs.rs/embedded-hal-async/embedded-hal-async
 
 #![deny(unsafe_code)]
 #![deny(missing_docs)]
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(
+    not(test),
+    deny(clippy::panic, clippy::unwrap_used, clippy::expect_used)
+)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
-use byteorder::{BigEndian, ByteOrder};
 use embedded_hal as hal;
-use sensirion_i2c::{crc8, i2c};
+use sensirion_i2c::crc8;
 
-use crate::hal::{
-    delay::DelayNs,
-    i2c::{ErrorType, I2c},
-};
+use crate::{commands::CommandData, hal::delay::DelayNs, timings::SAMPLE_INTERVAL_US};
 
 #[cfg(feature = "embedded-hal-async")]
 mod async_impl;
 #[cfg(feature = "embedded-hal-async")]
 pub use async_impl::Sgp30Async;
-
+#[cfg(feature = "embassy")]
+pub use async_impl::SharedBusSgp30Async;
+
+#[cfg(feature = "diagnostics")]
+pub mod aging;
+#[cfg(feature = "aqi")]
+pub mod aqi;
+#[cfg(feature = "filters")]
+pub mod archive;
+mod buffer;
+#[cfg(feature = "diagnostics")]
+pub mod calibration;
+pub mod commands;
+#[cfg(feature = "diagnostics")]
+pub mod confidence;
+#[cfg(feature = "diagnostics")]
+pub mod consistency;
+#[cfg(feature = "std")]
+pub mod csv;
+pub mod erased;
+#[cfg(feature = "error-history")]
+mod error_history;
+#[cfg(feature = "flash-log")]
+pub mod flash_log;
+#[cfg(feature = "alerts")]
+pub mod fusion;
+#[cfg(feature = "critical-section")]
+pub mod global;
+#[cfg(feature = "lcd")]
+pub mod lcd;
+#[cfg(feature = "occupancy")]
+pub mod occupancy;
+#[cfg(feature = "filters")]
+pub mod pipeline;
+#[cfg(feature = "profiling")]
+pub mod profiling;
+#[cfg(feature = "prost")]
+pub mod proto;
+#[cfg(feature = "heapless")]
+pub mod queue;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod sampler;
+#[cfg(any(feature = "critical-section", feature = "std"))]
+pub mod shared;
+#[cfg(feature = "alerts")]
+pub mod spike;
+#[cfg(feature = "fugit")]
+pub mod timer;
+pub mod timings;
+pub mod transport;
 mod types;
-
-pub use crate::types::{Baseline, FeatureSet, Humidity, Measurement, ProductType, RawSignals};
+#[cfg(feature = "uom")]
+mod units;
+
+pub use crate::buffer::{MeasureBuf, ReadBuf, SerialBuf};
+pub use crate::commands::CommandId;
+#[cfg(feature = "error-history")]
+pub use crate::error_history::{ErrorHistory, ErrorRecord, ERROR_HISTORY_LEN};
+pub use crate::transport::Sgp30Transport;
+pub use crate::types::{
+    Baseline, BaselineHex, FeatureSet, Humidity, Measurement, ParseBaselineError, ProductType,
+    RawSignals,
+};
 
 /// All possible errors in this crate
+///
+/// Errors caused by a specific command carry the [`CommandId`] of the
+/// command that was in flight.
 #[derive(Debug)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
 pub enum Error<E> {
     /// I²C bus error during a write
-    I2cWrite(E),
+    #[cfg_attr(
+        feature = "std",
+        error("I²C bus error while writing command {command:?}")
+    )]
+    I2cWrite {
+        /// The command that was being sent
+        command: CommandId,
+        /// The underlying bus error
+        source: E,
+    },
+    /// I²C bus error during a read
+    #[cfg_attr(
+        feature = "std",
+        error("I²C bus error while reading the response to command {command:?}")
+    )]
+    I2cRead {
+        /// The command whose response was being read
+        command: CommandId,
+        /// The underlying bus error
+        source: E,
+    },
+    /// The sensor did not acknowledge its address, e.g. because it is not
+    /// connected or not powered
+    #[cfg_attr(feature = "std", error("sensor not responding to command {command:?}"))]
+    DeviceNotResponding {
+        /// The command that was being sent, or whose response was being read
+        command: CommandId,
+        /// The underlying bus error
+        source: E,
+    },
+    /// CRC checksum validation failed
+    #[cfg_attr(
+        feature = "std",
+        error("CRC validation failed for the response to command {command:?}")
+    )]
+    Crc {
+        /// The command whose response failed validation
+        command: CommandId,
+    },
+    /// User tried to measure the air quality without starting the
+    /// initialization phase.
+    #[cfg_attr(feature = "std", error("air quality measurement not initialized"))]
+    NotInitialized,
+    /// The baseline read back after writing it differs from the written
+    /// baseline, see [`Sgp30::set_baseline_verified()`].
+    #[cfg_attr(
+        feature = "std",
+        error("baseline read back as {read:?} after writing {written:?}")
+    )]
+    BaselineMismatch {
+        /// The baseline that was written
+        written: Baseline,
+        /// The baseline that was read back
+        read: Baseline,
+    },
+    /// The expected response was not received within the timeout.
+    #[cfg_attr(
+        feature = "std",
+        error("timeout while waiting for command {command:?}")
+    )]
+    Timeout {
+        /// The command that was repeated until the timeout
+        command: CommandId,
+    },
+}
+
+/// The kind of an [`Error`], without the underlying bus error.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum ErrorKind {
+    /// I²C bus error during a write
+    #[cfg_attr(feature = "std", error("I²C bus error during a write"))]
+    I2cWrite,
     /// I²C bus error during a read
-    I2cRead(E),
+    #[cfg_attr(feature = "std", error("I²C bus error during a read"))]
+    I2cRead,
+    /// The sensor did not acknowledge its address
+    #[cfg_attr(feature = "std", error("sensor not responding"))]
+    DeviceNotResponding,
     /// CRC checksum validation failed
+    #[cfg_attr(feature = "std", error("CRC validation failed"))]
     Crc,
     /// User tried to measure the air quality without starting the
     /// initialization phase.
+    #[cfg_attr(feature = "std", error("air quality measurement not initialized"))]
     NotInitialized,
+    /// The baseline read back after writing it differs from the written
+    /// baseline.
+    #[cfg_attr(feature = "std", error("baseline verification failed"))]
+    BaselineMismatch,
+    /// The expected response was not received within the timeout.
+    #[cfg_attr(feature = "std", error("timeout"))]
+    Timeout,
 }
 
-impl<I> From<i2c::Error<I>> for Error<I::Error>
-where
-    I: ErrorType,
-{
-    fn from(err: i2c::Error<I>) -> Self {
-        match err {
-            i2c::Error::Crc => Error::Crc,
-            i2c::Error::I2cWrite(e) => Error::I2cRead(e),
-            i2c::Error::I2cRead(e) => Error::I2cWrite(e),
+impl<E> Error<E> {
+    /// Return the kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::I2cWrite { .. } => ErrorKind::I2cWrite,
+            Error::I2cRead { .. } => ErrorKind::I2cRead,
+            Error::DeviceNotResponding { .. } => ErrorKind::DeviceNotResponding,
+            Error::Crc { .. } => ErrorKind::Crc,
+            Error::NotInitialized => ErrorKind::NotInitialized,
+            Error::BaselineMismatch { .. } => ErrorKind::BaselineMismatch,
+            Error::Timeout { .. } => ErrorKind::Timeout,
         }
     }
-}
-
-/// I²C commands sent to the sensor.
-#[derive(Debug, Copy, Clone)]
-enum Command {
-    /// Return the serial number.
-    GetSerial,
-    /// Run an on-chip self-test.
-    SelfTest,
-    /// Initialize air quality measurements.
-    InitAirQuality,
-    /// Get a current air quality measurement.
-    MeasureAirQuality,
-    /// Measure raw signals.
-    MeasureRawSignals,
-    /// Return the baseline value.
-    GetBaseline,
-    /// Set the baseline value.
-    SetBaseline,
-    /// Set the current absolute humidity.
-    SetHumidity,
-    /// Set the feature set.
-    GetFeatureSet,
-}
 
-impl Command {
-    fn as_bytes(self) -> [u8; 2] {
+    /// Return the command that was in flight when the error occurred, if
+    /// any.
+    pub fn command(&self) -> Option<CommandId> {
         match self {
-            Command::GetSerial => [0x36, 0x82],
-            Command::SelfTest => [0x20, 0x32],
-            Command::InitAirQuality => [0x20, 0x03],
-            Command::MeasureAirQuality => [0x20, 0x08],
-            Command::MeasureRawSignals => [0x20, 0x50],
-            Command::GetBaseline => [0x20, 0x15],
-            Command::SetBaseline => [0x20, 0x1E],
-            Command::SetHumidity => [0x20, 0x61],
-            Command::GetFeatureSet => [0x20, 0x2F],
+            Error::I2cWrite { command, .. }
+            | Error::I2cRead { command, .. }
+            | Error::DeviceNotResponding { command, .. }
+            | Error::Crc { command }
+            | Error::Timeout { command } => Some(*command),
+            Error::BaselineMismatch { .. } => Some(CommandId::SetBaseline),
+            Error::NotInitialized => None,
         }
     }
 
-    /// Writes this command and the provided `data` bytes to `buf`, returning a
-    /// slice of the written portion of `buf`.
-    ///
-    /// # Arguments
-    ///
-    /// - `buf`: The buffer into which to write the command and data bytes.
-    ///   This buffer must be 8 bytes long.
-    /// - `data`: The data bytes to write after the command bytes. This slice
-    ///   must contain either 2 or 4 bytes.
-    ///
-    /// # Panics
+    /// Convert a bus error which occurred while writing `command`.
     ///
-    /// - If `data` is not either 2 or 4 bytes long.
-    fn as_bytes_with_data<'buf>(self, buf: &'buf mut [u8; 8], data: &[u8]) -> &'buf [u8] {
-        assert!(data.len() == 2 || data.len() == 4);
-        buf[0..2].copy_from_slice(&self.as_bytes());
-        buf[2..4].copy_from_slice(&data[0..2]);
-        buf[4] = crc8::calculate(&data[0..2]);
-        if data.len() > 2 {
-            buf[5..7].copy_from_slice(&data[2..4]);
-            buf[7] = crc8::calculate(&data[2..4]);
+    /// NACKs of the address byte, as classified by `is_address_nack`, are
+    /// reported as [`Error::DeviceNotResponding`].
+    fn from_write(command: CommandId, source: E, is_address_nack: fn(&E) -> bool) -> Self {
+        if is_address_nack(&source) {
+            Error::DeviceNotResponding { command, source }
+        } else {
+            Error::I2cWrite { command, source }
         }
-        if data.len() > 2 {
-            &buf[0..8]
+    }
+
+    /// Convert a bus error which occurred while reading the response to
+    /// `command`, see [`from_write()`](Self::from_write).
+    fn from_read(command: CommandId, source: E, is_address_nack: fn(&E) -> bool) -> Self {
+        if is_address_nack(&source) {
+            Error::DeviceNotResponding { command, source }
         } else {
-            &buf[0..5]
+            Error::I2cRead { command, source }
         }
     }
 }
 
+/// A correction applied to every air quality measurement, see
+/// [`Sgp30::set_correction()`].
+pub type Correction = fn(Measurement) -> Measurement;
+
 /// Driver for the SGP30
 #[derive(Debug, Default)]
 pub struct Sgp30<I2C, D> {
@@ -317,6 +503,29 @@ pub struct Sgp30<I2C, D> {
     delay: D,
     /// Whether the air quality measurement was initialized.
     initialized: bool,
+    /// Whether to use combined write/read transactions where possible.
+    combined_reads: bool,
+    /// Whether the raw signals can be measured without initialization.
+    raw_signals_only: bool,
+    /// Whether the CRC checksums of responses are not validated.
+    skip_crc: bool,
+    /// The additional delay before reading a response, in microseconds.
+    extra_read_delay_us: u32,
+    /// The interval for polling responses until they are ready, if enabled.
+    poll_interval_us: Option<u32>,
+    /// The correction applied to every air quality measurement.
+    correction: Option<Correction>,
+    /// Whether a humidity value for compensation was sent to the sensor.
+    humidity_compensation: bool,
+    /// The most recent successful air quality measurement.
+    last_measurement: Option<Measurement>,
+    /// The most recent successful raw signals measurement.
+    last_raw_signals: Option<RawSignals>,
+    /// The feature set read from the sensor.
+    feature_set: Option<FeatureSet>,
+    /// The most recent bus and CRC errors.
+    #[cfg(feature = "error-history")]
+    error_history: ErrorHistory,
 }
 
 /// The fixed data pattern returned when the on-chip self-test is successful.
@@ -324,74 +533,390 @@ const SELFTEST_SUCCESS: &[u8] = &[0xd4, 0x00];
 
 impl<I2C, D> Sgp30<I2C, D>
 where
-    I2C: I2c,
+    I2C: Sgp30Transport,
     D: DelayNs,
 {
     /// Create a new instance of the SGP30 driver.
-    pub fn new(i2c: I2C, address: u8, delay: D) -> Self {
+    pub const fn new(i2c: I2C, address: u8, delay: D) -> Self {
         Sgp30 {
             i2c,
             address,
             delay,
             initialized: false,
+            combined_reads: false,
+            raw_signals_only: false,
+            skip_crc: false,
+            extra_read_delay_us: 0,
+            poll_interval_us: None,
+            correction: None,
+            humidity_compensation: false,
+            last_measurement: None,
+            last_raw_signals: None,
+            feature_set: None,
+            #[cfg(feature = "error-history")]
+            error_history: ErrorHistory::new(),
         }
     }
 
+    /// Create a new instance of the SGP30 driver for a sensor which is
+    /// already measuring.
+    ///
+    /// Use this if the microcontroller was reset while the sensor kept its
+    /// power supply, and the air quality measurement was initialized before
+    /// the reset. Sending the init command again would restart the 15 s
+    /// initialization phase and reset the baseline, so this constructor marks
+    /// the driver as initialized instead, allowing to resume measuring
+    /// immediately.
+    ///
+    /// Note that the 1 s measurement interval should be kept up across the
+    /// reset as well as possible.
+    pub const fn assume_initialized(i2c: I2C, address: u8, delay: D) -> Self {
+        let mut sgp = Self::new(i2c, address, delay);
+        sgp.initialized = true;
+        sgp
+    }
+
     /// Destroy driver instance, return I²C bus instance.
     pub fn destroy(self) -> I2C {
         self.i2c
     }
 
+    /// Replace the I²C bus instance, keeping the state of the driver.
+    ///
+    /// Some HALs can only recover from fatal bus errors by re-creating the
+    /// I²C peripheral. This constructs a driver around `i2c`, carrying over
+    /// the address, the initialization state and all other settings, so the
+    /// 1 s measurements can be continued without sending the init command
+    /// again. The baseline is kept by the sensor itself and is not affected.
+    ///
+    /// The old I²C bus instance is dropped.
+    pub fn rebuild(self, i2c: I2C) -> Self {
+        Self { i2c, ..self }
+    }
+
+    /// Return whether the air quality measurement was initialized.
+    ///
+    /// This is set by [`init()`](Self::init) and
+    /// [`force_init()`](Self::force_init).
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// Return whether humidity compensation with a humidity value sent by
+    /// this driver is active.
+    ///
+    /// This is set by [`set_humidity()`](Self::set_humidity) and cleared by
+    /// [`disable_humidity_compensation()`](Self::disable_humidity_compensation).
+    /// Note that a soft reset or power cycle of the sensor also resets the
+    /// humidity to its default value, which is not tracked by the driver.
+    pub fn humidity_compensation_active(&self) -> bool {
+        self.humidity_compensation
+    }
+
+    /// Return the most recent successful air quality measurement, without
+    /// communicating with the sensor.
+    ///
+    /// This allows answering requests (e.g. Modbus or BLE reads) immediately
+    /// without adding bus traffic to the 1 s measurement cadence. The
+    /// [correction](Self::set_correction) is already applied.
+    pub fn last_measurement(&self) -> Option<Measurement> {
+        self.last_measurement
+    }
+
+    /// Return the most recent successful raw signals measurement, without
+    /// communicating with the sensor.
+    pub fn last_raw_signals(&self) -> Option<RawSignals> {
+        self.last_raw_signals
+    }
+
+    /// Return the history of the most recent bus and CRC errors.
+    ///
+    /// This can be used to report what went wrong on devices without any
+    /// logging infrastructure.
+    #[cfg(feature = "error-history")]
+    pub fn recent_errors(&self) -> &ErrorHistory {
+        &self.error_history
+    }
+
+    /// Remove all entries from the error history.
+    #[cfg(feature = "error-history")]
+    pub fn clear_recent_errors(&mut self) {
+        self.error_history.clear();
+    }
+
+    /// Enable or disable combined write/read transactions.
+    ///
+    /// By default, every command is sent in a separate write, followed by
+    /// the maximum command duration specified in the datasheet and a separate
+    /// read. When enabled, commands with a very short execution time (the
+    /// serial number and feature set commands) are instead issued as a single
+    /// `write_read` transaction, which reduces bus arbitration overhead and
+    /// latency on busy shared buses.
+    ///
+    /// Since no delay is inserted between the command and the read in this
+    /// mode, the sensor might NACK the read if the response isn't ready yet.
+    /// Only enable this option after verifying that it works with your I²C
+    /// master and bus speed.
+    pub fn set_combined_reads(&mut self, enabled: bool) {
+        self.combined_reads = enabled;
+    }
+
+    /// Enable or disable the raw signals only mode.
+    ///
+    /// By default, the [raw signals](Self::measure_raw_signals) can only be
+    /// measured after the air quality measurement was initialized. The
+    /// sensor doesn't require this for the raw signals, so when enabled,
+    /// they can be measured without calling [`init()`](Self::init) and
+    /// without keeping up the 1 Hz cadence of the air quality measurement,
+    /// e.g. on part verification rigs or in applications which only use the
+    /// H2 and Ethanol signals.
+    pub fn set_raw_signals_only(&mut self, enabled: bool) {
+        self.raw_signals_only = enabled;
+    }
+
+    /// Return whether the raw signals only mode is enabled, see
+    /// [`set_raw_signals_only()`](Self::set_raw_signals_only).
+    pub fn raw_signals_only(&self) -> bool {
+        self.raw_signals_only
+    }
+
+    /// Enable or disable the CRC check of responses.
+    ///
+    /// By default, the CRC checksum of every word read from the sensor is
+    /// validated, and a single flipped bit fails the whole command with an
+    /// [`Error::Crc`]. On trusted buses (e.g. a short point-to-point
+    /// connection on the same board), the check can be skipped to save the
+    /// computation and to avoid these failures. This is a trade-off: A
+    /// corrupted word is then returned as a wrong value without any
+    /// indication, e.g. as a spike in the measurements or as a wrong
+    /// baseline.
+    ///
+    /// The CRC checksums of the data written to the sensor are always
+    /// computed, since the sensor requires them.
+    pub fn set_skip_crc(&mut self, enabled: bool) {
+        self.skip_crc = enabled;
+    }
+
+    /// Return whether the CRC check of responses is skipped, see
+    /// [`set_skip_crc()`](Self::set_skip_crc).
+    pub fn skip_crc(&self) -> bool {
+        self.skip_crc
+    }
+
+    /// Set an additional delay in microseconds, which is inserted between
+    /// sending a command and reading its response, on top of the command
+    /// durations specified in the datasheet.
+    ///
+    /// Some I²C masters (e.g. bit-banged ones) need more margin before the
+    /// response can be read. The delay does not apply to
+    /// [combined reads](Self::set_combined_reads). Defaults to 0.
+    pub fn set_extra_read_delay_us(&mut self, delay_us: u32) {
+        self.extra_read_delay_us = delay_us;
+    }
+
+    /// Return the additional delay before reading a response, see
+    /// [`set_extra_read_delay_us()`](Self::set_extra_read_delay_us).
+    pub fn extra_read_delay_us(&self) -> u32 {
+        self.extra_read_delay_us
+    }
+
+    /// Enable or disable polling for responses.
+    ///
+    /// By default, the driver waits for the maximum command duration
+    /// specified in the datasheet before reading a response, e.g. 220 ms
+    /// for the self-test and 25 ms for the raw signals. The sensor NACKs
+    /// reads while a command is still being processed, so with a polling
+    /// interval set, the driver instead only waits for the typical command
+    /// duration and then retries NACKed reads every `interval_us`, until the
+    /// maximum command duration has passed. This reduces the latency and
+    /// the time spent waiting.
+    ///
+    /// Commands without a response still wait for their maximum duration.
+    /// The NACKs are detected with
+    /// [`Sgp30Transport::is_address_nack()`](crate::transport::Sgp30Transport::is_address_nack).
+    pub fn set_poll_interval_us(&mut self, interval_us: Option<u32>) {
+        self.poll_interval_us = interval_us;
+    }
+
+    /// Return the interval for polling responses, see
+    /// [`set_poll_interval_us()`](Self::set_poll_interval_us).
+    pub fn poll_interval_us(&self) -> Option<u32> {
+        self.poll_interval_us
+    }
+
+    /// Set a correction that is applied to every air quality measurement
+    /// before it is returned, or remove it by passing `None`.
+    ///
+    /// This can be used to apply per-installation calibrations, e.g. to
+    /// compensate a known cross-sensitivity or a co-located heat source, in
+    /// a single place. The correction is not applied to the raw signals.
+    pub fn set_correction(&mut self, correction: Option<Correction>) {
+        self.correction = correction;
+    }
+
     /// Write an I²C command to the sensor.
-    fn send_command(&mut self, command: Command) -> Result<(), Error<I2C::Error>> {
-        self.i2c
+    fn send_command(&mut self, command: CommandId) -> Result<(), Error<I2C::Error>> {
+        let result = self
+            .i2c
             .write(self.address, &command.as_bytes())
-            .map_err(Error::I2cWrite)
+            .map_err(|source| Error::from_write(command, source, I2C::is_address_nack));
+        self.record(result)
     }
 
     /// Write an I²C command and data to the sensor.
     ///
-    /// The data slice must have a length of 2 or 4.
-    ///
-    /// CRC checksums will automatically be added to the data.
+    /// The data is a single word ([`CommandData::Data2`]) or two words
+    /// ([`CommandData::Data4`]), a CRC checksum is automatically added after
+    /// every word.
     fn send_command_and_data(
         &mut self,
-        command: Command,
-        data: &[u8],
+        command: CommandId,
+        data: CommandData,
     ) -> Result<(), Error<I2C::Error>> {
         let mut buf = [0; 2 /* command */ + 6 /* max length of data + crc */];
         let payload = command.as_bytes_with_data(&mut buf, data);
-        self.i2c
+        let result = self
+            .i2c
             .write(self.address, payload)
-            .map_err(Error::I2cWrite)
+            .map_err(|source| Error::from_write(command, source, I2C::is_address_nack));
+        self.record(result)
+    }
+
+    /// Write an I²C command and read the response words in a single combined
+    /// transaction, validating the CRC checksums.
+    ///
+    /// Bus errors are reported as [`Error::I2cRead`].
+    fn write_read_words_with_crc(
+        &mut self,
+        command: CommandId,
+        buf: &mut [u8],
+    ) -> Result<(), Error<I2C::Error>> {
+        let result = self
+            .i2c
+            .write_read(self.address, &command.as_bytes(), buf)
+            .map_err(|source| Error::from_read(command, source, I2C::is_address_nack))
+            .and_then(|()| self.check_crc(command, buf));
+        self.record(result)
+    }
+
+    /// Wait until the response to `command` may be read: for the maximum
+    /// command duration, or for the typical duration when polling.
+    fn wait_for_completion(&mut self, command: CommandId) {
+        let info = command.info();
+        let duration_us = match self.poll_interval_us {
+            Some(_) => info.typical_duration_us,
+            None => info.max_duration_us,
+        };
+        self.delay.delay_us(duration_us);
+    }
+
+    /// Return the time in microseconds for which NACKed reads of the
+    /// response to `command` are retried.
+    fn poll_budget_us(&self, command: CommandId) -> u32 {
+        let info = command.info();
+        match self.poll_interval_us {
+            Some(_) => info
+                .max_duration_us
+                .saturating_sub(info.typical_duration_us),
+            None => 0,
+        }
+    }
+
+    /// Wait for the additional delay before reading a response, if any.
+    fn extra_read_delay(&mut self) {
+        if self.extra_read_delay_us > 0 {
+            self.delay.delay_us(self.extra_read_delay_us);
+        }
+    }
+
+    /// Read the response words to `command` from the sensor, validating the
+    /// CRC checksums.
+    fn read_words_with_crc(
+        &mut self,
+        command: CommandId,
+        buf: &mut [u8],
+    ) -> Result<(), Error<I2C::Error>> {
+        self.extra_read_delay();
+        let mut poll_budget_us = self.poll_budget_us(command);
+        let result = loop {
+            match self.i2c.read(self.address, buf) {
+                // The command is still being processed
+                Err(ref e) if poll_budget_us > 0 && I2C::is_address_nack(e) => {
+                    let interval_us = self.poll_interval_us.unwrap_or(0).clamp(1, poll_budget_us);
+                    self.delay.delay_us(interval_us);
+                    poll_budget_us -= interval_us;
+                }
+                result => break result,
+            }
+        };
+        let result = result
+            .map_err(|source| Error::from_read(command, source, I2C::is_address_nack))
+            .and_then(|()| self.check_crc(command, buf));
+        self.record(result)
+    }
+
+    /// Validate the CRC checksums of the response `buf` to `command`, unless
+    /// the check is [skipped](Self::set_skip_crc).
+    fn check_crc(&self, command: CommandId, buf: &[u8]) -> Result<(), Error<I2C::Error>> {
+        if self.skip_crc {
+            return Ok(());
+        }
+        crc8::validate(buf).map_err(|_| Error::Crc { command })
+    }
+
+    /// Record a failed bus operation in the error history.
+    fn record<T>(&mut self, result: Result<T, Error<I2C::Error>>) -> Result<T, Error<I2C::Error>> {
+        #[cfg(feature = "error-history")]
+        if let Err(ref e) = result {
+            self.error_history.push(e.kind());
+        }
+        result
+    }
+
+    /// Apply the correction (if any) to a measurement.
+    fn correct(&self, measurement: Measurement) -> Measurement {
+        match self.correction {
+            Some(correction) => correction(measurement),
+            None => measurement,
+        }
     }
 
     /// Return the 48 bit serial number of the SGP30.
     pub fn serial(&mut self) -> Result<[u8; 6], Error<I2C::Error>> {
-        // Request serial number
-        self.send_command(Command::GetSerial)?;
+        self.serial_into(&mut SerialBuf::new())
+    }
 
-        // Recommended wait time according to datasheet (6.5)
-        self.delay.delay_us(500);
+    /// Like [`serial()`](Self::serial), but reading the raw response into a
+    /// caller-provided buffer. See [`ReadBuf`] for details.
+    pub fn serial_into(&mut self, buf: &mut SerialBuf) -> Result<[u8; 6], Error<I2C::Error>> {
+        if self.combined_reads {
+            self.write_read_words_with_crc(CommandId::GetSerial, buf.as_mut_bytes())?;
+        } else {
+            // Request serial number
+            self.send_command(CommandId::GetSerial)?;
+
+            // Recommended wait time according to datasheet (6.5)
+            self.wait_for_completion(CommandId::GetSerial);
 
-        // Read serial number
-        let mut buf = [0; 9];
-        i2c::read_words_with_crc(&mut self.i2c, self.address, &mut buf)?;
+            // Read serial number
+            self.read_words_with_crc(CommandId::GetSerial, buf.as_mut_bytes())?;
+        }
 
+        let buf = buf.as_bytes();
         Ok([buf[0], buf[1], buf[3], buf[4], buf[6], buf[7]])
     }
 
     /// Run an on-chip self-test. Return a boolean indicating whether the test succeeded.
     pub fn selftest(&mut self) -> Result<bool, Error<I2C::Error>> {
         // Start self test
-        self.send_command(Command::SelfTest)?;
+        self.send_command(CommandId::SelfTest)?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(220);
+        self.wait_for_completion(CommandId::SelfTest);
 
         // Read result
         let mut buf = [0; 3];
-        i2c::read_words_with_crc(&mut self.i2c, self.address, &mut buf)?;
+        self.read_words_with_crc(CommandId::SelfTest, &mut buf)?;
 
         // Compare with self-test success pattern
         Ok(&buf[0..2] == SELFTEST_SUCCESS)
@@ -430,10 +955,11 @@ where
     /// This might be necessary after a sensor soft or hard reset.
     pub fn force_init(&mut self) -> Result<(), Error<I2C::Error>> {
         // Send command to sensor
-        self.send_command(Command::InitAirQuality)?;
+        self.send_command(CommandId::InitAirQuality)?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10);
+        self.delay
+            .delay_us(commands::INIT_AIR_QUALITY.max_duration_us);
 
         self.initialized = true;
         Ok(())
@@ -458,21 +984,28 @@ where
     /// values of 400 ppm CO₂eq and 0 ppb TVOC. After 15 s (15 measurements)
     /// the values should start to change.
     pub fn measure(&mut self) -> Result<Measurement, Error<I2C::Error>> {
+        self.measure_into(&mut MeasureBuf::new())
+    }
+
+    /// Like [`measure()`](Self::measure), but reading the raw response into a
+    /// caller-provided buffer. See [`ReadBuf`] for details.
+    pub fn measure_into(&mut self, buf: &mut MeasureBuf) -> Result<Measurement, Error<I2C::Error>> {
         if !self.initialized {
             // Measurements weren't initialized
             return Err(Error::NotInitialized);
         }
 
         // Send command to sensor
-        self.send_command(Command::MeasureAirQuality)?;
+        self.send_command(CommandId::MeasureAirQuality)?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(12);
+        self.wait_for_completion(CommandId::MeasureAirQuality);
 
         // Read result
-        let mut buf = [0; 6];
-        i2c::read_words_with_crc(&mut self.i2c, self.address, &mut buf)?;
-        Ok(Measurement::from_bytes(&buf))
+        self.read_words_with_crc(CommandId::MeasureAirQuality, buf.as_mut_bytes())?;
+        let measurement = self.correct(Measurement::from_bytes(buf.as_bytes()));
+        self.last_measurement = Some(measurement);
+        Ok(measurement)
     }
 
     /// Return sensor raw signals.
@@ -482,24 +1015,153 @@ where
     /// calibration and baseline compensation algorithm. The command performs a
     /// measurement to which the sensor responds with the two signals for H2
     /// and Ethanol.
+    ///
+    /// The air quality measurement must have been initialized using the
+    /// [`init()`](Self::init) method, unless the
+    /// [raw signals only mode](Self::set_raw_signals_only) is enabled.
+    /// Otherwise an [`Error::NotInitialized`] will be returned.
     pub fn measure_raw_signals(&mut self) -> Result<RawSignals, Error<I2C::Error>> {
-        if !self.initialized {
+        self.measure_raw_signals_into(&mut MeasureBuf::new())
+    }
+
+    /// Like [`measure_raw_signals()`](Self::measure_raw_signals), but reading
+    /// the raw response into a caller-provided buffer. See [`ReadBuf`] for
+    /// details.
+    pub fn measure_raw_signals_into(
+        &mut self,
+        buf: &mut MeasureBuf,
+    ) -> Result<RawSignals, Error<I2C::Error>> {
+        if !self.initialized && !self.raw_signals_only {
             // Measurements weren't initialized
             return Err(Error::NotInitialized);
         }
 
         // Send command to sensor
-        self.send_command(Command::MeasureRawSignals)?;
+        self.send_command(CommandId::MeasureRawSignals)?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(25);
+        self.wait_for_completion(CommandId::MeasureRawSignals);
 
         // Read result
-        let mut buf = [0; 6];
-        i2c::read_words_with_crc(&mut self.i2c, self.address, &mut buf)?;
+        self.read_words_with_crc(CommandId::MeasureRawSignals, buf.as_mut_bytes())?;
+        let signals = RawSignals::from_bytes(buf.as_bytes());
+        self.last_raw_signals = Some(signals);
+        Ok(signals)
+    }
+
+    /// Measure at 1 s intervals until the sensor returns live data, return
+    /// the first live measurement.
+    ///
+    /// During the initialization phase, the sensor returns the fixed values
+    /// of [`Measurement::WARM_UP`]. This keeps measuring until the values
+    /// differ from these (before applying the [correction](Self::set_correction)),
+    /// e.g. to validate units on a production line. If no live data was
+    /// returned within `timeout_s` measurements, an [`Error::Timeout`] is
+    /// returned.
+    ///
+    /// Note that a sensor in clean air may keep returning 400 ppm CO₂eq and
+    /// 0 ppb TVOC after the initialization phase as well, so the timeout
+    /// should be chosen accordingly. The initialization phase takes 15 s.
+    ///
+    /// The air quality measurement must have been initialized using the
+    /// [`init()`](Self::init) method, otherwise an [`Error::NotInitialized`]
+    /// is returned.
+    pub fn wait_for_live_data(&mut self, timeout_s: u32) -> Result<Measurement, Error<I2C::Error>> {
+        let mut buf = MeasureBuf::new();
+        for i in 0..timeout_s {
+            if i > 0 {
+                self.delay
+                    .delay_us(SAMPLE_INTERVAL_US - commands::MEASURE_AIR_QUALITY.max_duration_us);
+            }
+            let measurement = self.measure_into(&mut buf)?;
+            if Measurement::from_bytes(buf.as_bytes()) != Measurement::WARM_UP {
+                return Ok(measurement);
+            }
+        }
+        Err(Error::Timeout {
+            command: CommandId::MeasureAirQuality,
+        })
+    }
+
+    /// Measure `N` times at 1 s intervals, return the measurements.
+    ///
+    /// This is convenient for duty-cycled applications which sample for a
+    /// while and then decide what to do, e.g. collect 30 s worth of
+    /// measurements and average them. If a measurement fails, the error is
+    /// returned and the measurements collected so far are discarded.
+    ///
+    /// Note that the measurements of the initialization phase (the first
+    /// 15 s after [`init()`](Self::init)) are the fixed values of
+    /// [`Measurement::WARM_UP`].
+    ///
+    /// The air quality measurement must have been initialized using the
+    /// [`init()`](Self::init) method, otherwise an [`Error::NotInitialized`]
+    /// is returned.
+    #[cfg(feature = "heapless")]
+    pub fn collect_n<const N: usize>(
+        &mut self,
+    ) -> Result<heapless::Vec<Measurement, N>, Error<I2C::Error>> {
+        let mut measurements = heapless::Vec::new();
+        while !measurements.is_full() {
+            if !measurements.is_empty() {
+                self.delay
+                    .delay_us(SAMPLE_INTERVAL_US - commands::MEASURE_AIR_QUALITY.max_duration_us);
+            }
+            // Cannot fail, the vector is not full
+            let _ = measurements.push(self.measure()?);
+        }
+        Ok(measurements)
+    }
+
+    /// Get an air quality measurement without any checks and bookkeeping.
+    ///
+    /// Unlike [`measure()`](Self::measure), this does not check whether the
+    /// air quality measurement was initialized, and skips the correction,
+    /// the [last measurement](Self::last_measurement) cache and the error
+    /// history. The CRC is still validated, unless the check is
+    /// [skipped](Self::set_skip_crc). Use this only if the
+    /// initialization is guaranteed by the structure of the application.
+    ///
+    /// Measuring without initialization doesn't return an error, but
+    /// undefined values.
+    pub fn measure_unchecked(&mut self) -> Result<Measurement, Error<I2C::Error>> {
+        let buf = self.transfer_unchecked(
+            CommandId::MeasureAirQuality,
+            commands::MEASURE_AIR_QUALITY.max_duration_us,
+        )?;
+        Ok(Measurement::from_bytes(&buf))
+    }
+
+    /// Return sensor raw signals without any checks and bookkeeping.
+    ///
+    /// See [`measure_unchecked()`](Self::measure_unchecked).
+    pub fn measure_raw_signals_unchecked(&mut self) -> Result<RawSignals, Error<I2C::Error>> {
+        let buf = self.transfer_unchecked(
+            CommandId::MeasureRawSignals,
+            commands::MEASURE_RAW_SIGNALS.max_duration_us,
+        )?;
         Ok(RawSignals::from_bytes(&buf))
     }
 
+    /// Send `command`, wait for its completion and read a two word response.
+    fn transfer_unchecked(
+        &mut self,
+        command: CommandId,
+        max_duration_us: u32,
+    ) -> Result<[u8; 6], Error<I2C::Error>> {
+        self.i2c
+            .write(self.address, &command.as_bytes())
+            .map_err(|source| Error::from_write(command, source, I2C::is_address_nack))?;
+        self.delay.delay_us(max_duration_us);
+        self.extra_read_delay();
+        let mut buf = [0; 6];
+        self.i2c
+            .read(self.address, &mut buf)
+            .map_err(|source| Error::from_read(command, source, I2C::is_address_nack))?;
+        self.check_crc(command, &buf)?;
+        Ok(buf)
+    }
+
     /// Return the baseline values of the baseline correction algorithm.
     ///
     /// The SGP30 provides the possibility to read and write the baseline
@@ -514,16 +1176,24 @@ where
     /// [`init()`](struct.Sgp30.html#method.init) followed by
     /// [`set_baseline()`](struct.Sgp30.html#method.set_baseline).
     pub fn get_baseline(&mut self) -> Result<Baseline, Error<I2C::Error>> {
+        self.get_baseline_into(&mut MeasureBuf::new())
+    }
+
+    /// Like [`get_baseline()`](Self::get_baseline), but reading the raw response into a
+    /// caller-provided buffer. See [`ReadBuf`] for details.
+    pub fn get_baseline_into(
+        &mut self,
+        buf: &mut MeasureBuf,
+    ) -> Result<Baseline, Error<I2C::Error>> {
         // Send command to sensor
-        self.send_command(Command::GetBaseline)?;
+        self.send_command(CommandId::GetBaseline)?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10);
+        self.wait_for_completion(CommandId::GetBaseline);
 
         // Read result
-        let mut buf = [0; 6];
-        i2c::read_words_with_crc(&mut self.i2c, self.address, &mut buf)?;
-        Ok(Baseline::from_bytes(&buf))
+        self.read_words_with_crc(CommandId::GetBaseline, buf.as_mut_bytes())?;
+        Ok(Baseline::from_bytes(buf.as_bytes()))
     }
 
     /// Set the baseline values for the baseline correction algorithm.
@@ -549,17 +1219,34 @@ where
         // Send command and data to sensor
         // Note that the order of the two parameters is inverted when writing
         // compared to when reading.
-        let mut buf = [0; 4];
-        BigEndian::write_u16(&mut buf[0..2], baseline.tvoc);
-        BigEndian::write_u16(&mut buf[2..4], baseline.co2eq);
-        self.send_command_and_data(Command::SetBaseline, &buf)?;
+        let data = CommandData::Data4(baseline.as_bytes());
+        self.send_command_and_data(CommandId::SetBaseline, data)?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10);
+        self.delay.delay_us(commands::SET_BASELINE.max_duration_us);
 
         Ok(())
     }
 
+    /// Set the baseline values and read them back to verify that they were
+    /// written correctly.
+    ///
+    /// This catches data corrupted on the bus, which would otherwise only
+    /// show as drifting measurements. If the values read back differ, an
+    /// [`Error::BaselineMismatch`] is returned, and the baseline should be
+    /// written again.
+    pub fn set_baseline_verified(&mut self, baseline: &Baseline) -> Result<(), Error<I2C::Error>> {
+        self.set_baseline(baseline)?;
+        let read = self.get_baseline()?;
+        if read != *baseline {
+            return Err(Error::BaselineMismatch {
+                written: *baseline,
+                read,
+            });
+        }
+        Ok(())
+    }
+
     /// Set the humidity value for the baseline correction algorithm.
     ///
     /// The SGP30 features an on-chip humidity compensation for the air quality
@@ -572,7 +1259,9 @@ where
     /// set. Restarting the sensor (power-on or soft reset) or calling the
     /// function with a `None` value sets the humidity value used for
     /// compensation to its default value (11.57 g/m³) until a new humidity
-    /// value is sent.
+    /// value is sent. Prefer
+    /// [`disable_humidity_compensation()`](Self::disable_humidity_compensation)
+    /// over passing `None` to make the intent explicit.
     ///
     /// Before calling this method, the air quality measurements must have been
     /// initialized using the [`init()`](struct.Sgp30.html#method.init) method.
@@ -585,35 +1274,113 @@ where
         }
 
         // Send command and data to sensor
-        let buf = match humidity {
+        let data = CommandData::Data2(match humidity {
             Some(humi) => humi.as_bytes(),
             None => [0, 0],
-        };
-        self.send_command_and_data(Command::SetHumidity, &buf)?;
+        });
+        self.send_command_and_data(CommandId::SetHumidity, data)?;
 
         // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10);
+        self.delay.delay_us(commands::SET_HUMIDITY.max_duration_us);
+
+        self.humidity_compensation = humidity.is_some();
 
         Ok(())
     }
 
+    /// Disable the humidity compensation with a humidity value sent by this
+    /// driver.
+    ///
+    /// This sends a humidity value of zero, which makes the sensor fall back
+    /// to its default humidity value ([`Humidity::DEFAULT`], 11.57 g/m³).
+    ///
+    /// Before calling this method, the air quality measurements must have been
+    /// initialized using the [`init()`](Self::init) method.
+    /// Otherwise an [`Error::NotInitialized`] will be returned.
+    pub fn disable_humidity_compensation(&mut self) -> Result<(), Error<I2C::Error>> {
+        self.set_humidity(None)
+    }
+
+    /// Shut the air quality measurement down, return the final baseline.
+    ///
+    /// This is the teardown path before putting the microcontroller to sleep
+    /// or updating its firmware: It reads the baseline, which should be
+    /// stored and restored with [`set_baseline()`](Self::set_baseline) after
+    /// the next [`init()`](Self::init). If `clear_humidity` is true and a
+    /// humidity value sent by this driver is active, the humidity
+    /// compensation is disabled. Finally, the driver is marked as
+    /// uninitialized, so the next [`init()`](Self::init) sends the init
+    /// command again.
+    ///
+    /// The driver does not control the power supply of the sensor. To save
+    /// power, switch the sensor off after this returned.
+    ///
+    /// Before calling this method, the air quality measurements must have been
+    /// initialized using the [`init()`](Self::init) method.
+    /// Otherwise an [`Error::NotInitialized`] will be returned. If a command
+    /// fails, the driver stays initialized.
+    pub fn shutdown(&mut self, clear_humidity: bool) -> Result<Baseline, Error<I2C::Error>> {
+        if !self.initialized {
+            // Measurements weren't initialized
+            return Err(Error::NotInitialized);
+        }
+
+        let baseline = self.get_baseline()?;
+        if clear_humidity && self.humidity_compensation {
+            self.disable_humidity_compensation()?;
+        }
+        self.initialized = false;
+
+        Ok(baseline)
+    }
+
     /// Get the feature set.
     ///
     /// The SGP30 features a versioning system for the available set of
     /// measurement commands and on-chip algorithms. This so called feature set
     /// version number can be read out with this method.
     pub fn get_feature_set(&mut self) -> Result<FeatureSet, Error<I2C::Error>> {
-        // Send command to sensor
-        self.send_command(Command::GetFeatureSet)?;
+        let mut buf = [0; 3];
+        if self.combined_reads {
+            self.write_read_words_with_crc(CommandId::GetFeatureSet, &mut buf)?;
+        } else {
+            // Send command to sensor
+            self.send_command(CommandId::GetFeatureSet)?;
 
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(2);
+            // Max duration according to datasheet (Table 10)
+            self.wait_for_completion(CommandId::GetFeatureSet);
 
-        // Read result
-        let mut buf = [0; 3];
-        i2c::read_words_with_crc(&mut self.i2c, self.address, &mut buf)?;
+            // Read result
+            self.read_words_with_crc(CommandId::GetFeatureSet, &mut buf)?;
+        }
+
+        let feature_set = FeatureSet::parse(buf[0], buf[1]);
+        self.feature_set = Some(feature_set);
+        Ok(feature_set)
+    }
+
+    /// Return the feature set, reading it from the sensor only on the first
+    /// call.
+    ///
+    /// The feature set doesn't change while the sensor is running, so
+    /// subsequent calls return the cached value without any I²C transaction.
+    /// Every call to [`get_feature_set()`](Self::get_feature_set) updates
+    /// the cached value.
+    pub fn feature_set(&mut self) -> Result<FeatureSet, Error<I2C::Error>> {
+        match self.feature_set {
+            Some(feature_set) => Ok(feature_set),
+            None => self.get_feature_set(),
+        }
+    }
 
-        Ok(FeatureSet::parse(buf[0], buf[1]))
+    /// Read the feature set from the sensor again and update the cached
+    /// value returned by [`feature_set()`](Self::feature_set).
+    ///
+    /// Use this if the sensor might have been replaced while the driver
+    /// kept running, e.g. on a hot-pluggable connector.
+    pub fn refresh_feature_set(&mut self) -> Result<FeatureSet, Error<I2C::Error>> {
+        self.feature_set = None;
+        self.feature_set()
     }
 }
 
@@ -631,7 +1398,7 @@ mod tests {
     #[test]
     fn serial() {
         let expectations = [
-            Transaction::write(0x58, Command::GetSerial.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::GetSerial.as_bytes()[..].into()),
             Transaction::read(0x58, vec![0, 0, 129, 0, 100, 254, 204, 130, 135]),
         ];
         let mock = I2cMock::new(&expectations);
@@ -645,7 +1412,7 @@ mod tests {
     #[test]
     fn selftest_ok() {
         let expectations = [
-            Transaction::write(0x58, Command::SelfTest.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::SelfTest.as_bytes()[..].into()),
             Transaction::read(0x58, vec![0xD4, 0x00, 0xC6]),
         ];
         let mock = I2cMock::new(&expectations);
@@ -658,7 +1425,7 @@ mod tests {
     #[test]
     fn selftest_fail() {
         let expectations = [
-            Transaction::write(0x58, Command::SelfTest.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::SelfTest.as_bytes()[..].into()),
             Transaction::read(0x58, vec![0x12, 0x34, 0x37]),
         ];
         let mock = I2cMock::new(&expectations);
@@ -684,16 +1451,79 @@ mod tests {
     #[test]
     fn measure_success() {
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
-            Transaction::write(0x58, Command::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        assert!(!sgp.is_initialized());
+        sgp.init().unwrap();
+        assert!(sgp.is_initialized());
+        let measurements = sgp.measure().unwrap();
+        assert_eq!(measurements.co2eq_ppm, 4_660);
+        assert_eq!(measurements.tvoc_ppb, 54_274);
+        sgp.destroy().done();
+    }
+
+    /// Test the `set_correction` function: Correction is applied to measurements
+    #[test]
+    fn measure_corrected() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
             Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
         ];
         let mock = I2cMock::new(&expectations);
         let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
         sgp.init().unwrap();
+        sgp.set_correction(Some(|m| Measurement {
+            co2eq_ppm: m.co2eq_ppm - 60,
+            tvoc_ppb: m.tvoc_ppb / 2,
+        }));
+        let measurements = sgp.measure().unwrap();
+        assert_eq!(measurements.co2eq_ppm, 4_600);
+        assert_eq!(measurements.tvoc_ppb, 27_137);
+        sgp.set_correction(None);
+        let measurements = sgp.measure().unwrap();
+        assert_eq!(measurements.co2eq_ppm, 4_660);
+        sgp.destroy().done();
+    }
+
+    /// Test the `assume_initialized` function: No init command is sent
+    #[test]
+    fn assume_initialized() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::assume_initialized(mock, 0x58, NoopDelay);
+        assert!(sgp.is_initialized());
+        sgp.init().unwrap();
         let measurements = sgp.measure().unwrap();
         assert_eq!(measurements.co2eq_ppm, 4_660);
+        sgp.destroy().done();
+    }
+
+    /// Test the `measure_into` function: Response is read into the caller-provided buffer
+    #[test]
+    fn measure_into() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        sgp.init().unwrap();
+        let mut buf = MeasureBuf::new();
+        let measurements = sgp.measure_into(&mut buf).unwrap();
+        assert_eq!(measurements.co2eq_ppm, 4_660);
         assert_eq!(measurements.tvoc_ppb, 54_274);
+        assert_eq!(buf.as_bytes(), &[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
         sgp.destroy().done();
     }
 
@@ -701,8 +1531,8 @@ mod tests {
     #[test]
     fn get_baseline() {
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
-            Transaction::write(0x58, Command::GetBaseline.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
             Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
         ];
         let mock = I2cMock::new(&expectations);
@@ -719,7 +1549,7 @@ mod tests {
     fn set_baseline() {
         #[rustfmt::skip]
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
             Transaction::write(0x58, vec![
                 /* command: */ 0x20, 0x1E,
                 /* data + crc8: */ 0x56, 0x78, 0x7D, 0x12, 0x34, 0x37,
@@ -736,12 +1566,47 @@ mod tests {
         sgp.destroy().done();
     }
 
+    /// Test the `set_baseline_verified` function
+    #[test]
+    fn set_baseline_verified() {
+        #[rustfmt::skip]
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, vec![
+                /* command: */ 0x20, 0x1E,
+                /* data + crc8: */ 0x56, 0x78, 0x7D, 0x12, 0x34, 0x37,
+            ]),
+            Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+            Transaction::write(0x58, vec![
+                /* command: */ 0x20, 0x1E,
+                /* data + crc8: */ 0x56, 0x78, 0x7D, 0x12, 0x34, 0x37,
+            ]),
+            Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x00, 0x00, 0x81]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        sgp.init().unwrap();
+        let baseline = Baseline::new(0x1234, 0x5678);
+        sgp.set_baseline_verified(&baseline).unwrap();
+        match sgp.set_baseline_verified(&baseline) {
+            Err(Error::BaselineMismatch { written, read }) => {
+                assert_eq!(written, baseline);
+                assert_eq!(read, Baseline::new(0x1234, 0));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        sgp.destroy().done();
+    }
+
     /// Test the `set_humidity` function
+    #[cfg(feature = "humidity-float")]
     #[test]
     fn set_humidity() {
         #[rustfmt::skip]
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
             Transaction::write(0x58, vec![
                 /* command: */ 0x20, 0x61,
                 /* data + crc8: */ 0x0F, 0x80, 0x62,
@@ -751,7 +1616,9 @@ mod tests {
         let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
         sgp.init().unwrap();
         let humidity = Humidity::from_f32(15.5).unwrap();
+        assert!(!sgp.humidity_compensation_active());
         sgp.set_humidity(Some(&humidity)).unwrap();
+        assert!(sgp.humidity_compensation_active());
         sgp.destroy().done();
     }
 
@@ -760,7 +1627,7 @@ mod tests {
     fn set_humidity_none() {
         #[rustfmt::skip]
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
             Transaction::write(0x58, vec![
                 /* command: */ 0x20, 0x61,
                 /* data + crc8: */ 0x00, 0x00, 0x81,
@@ -773,12 +1640,75 @@ mod tests {
         sgp.destroy().done();
     }
 
+    /// Test the `disable_humidity_compensation` function
+    #[cfg(feature = "humidity-float")]
+    #[test]
+    fn disable_humidity_compensation() {
+        #[rustfmt::skip]
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, vec![
+                /* command: */ 0x20, 0x61,
+                /* data + crc8: */ 0x0F, 0x80, 0x62,
+            ]),
+            Transaction::write(0x58, vec![
+                /* command: */ 0x20, 0x61,
+                /* data + crc8: */ 0x00, 0x00, 0x81,
+            ]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        sgp.init().unwrap();
+        let humidity = Humidity::from_f32(15.5).unwrap();
+        sgp.set_humidity(Some(&humidity)).unwrap();
+        sgp.disable_humidity_compensation().unwrap();
+        assert!(!sgp.humidity_compensation_active());
+        sgp.destroy().done();
+    }
+
+    /// Test the `shutdown` function
+    #[test]
+    fn shutdown() {
+        #[rustfmt::skip]
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, vec![
+                /* command: */ 0x20, 0x61,
+                /* data + crc8: */ 0x0F, 0x80, 0x62,
+            ]),
+            Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            Transaction::write(0x58, vec![
+                /* command: */ 0x20, 0x61,
+                /* data + crc8: */ 0x00, 0x00, 0x81,
+            ]),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        assert!(matches!(sgp.shutdown(true), Err(Error::NotInitialized)));
+        sgp.init().unwrap();
+        let humidity = Humidity::new(0x0F, 0x80).unwrap();
+        sgp.set_humidity(Some(&humidity)).unwrap();
+        let baseline = sgp.shutdown(true).unwrap();
+        assert_eq!(baseline, Baseline::new(4_660, 54_274));
+        assert!(!sgp.is_initialized());
+        assert!(!sgp.humidity_compensation_active());
+
+        // Without humidity compensation, no humidity is sent
+        sgp.init().unwrap();
+        sgp.shutdown(true).unwrap();
+        sgp.destroy().done();
+    }
+
     /// Test the `get_feature_set` function.
     #[test]
     fn get_feature_set() {
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
-            Transaction::write(0x58, Command::GetFeatureSet.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
             Transaction::read(0x58, vec![0x00, 0x42, 0xDE]),
         ];
         let mock = I2cMock::new(&expectations);
@@ -787,6 +1717,162 @@ mod tests {
         let feature_set = sgp.get_feature_set().unwrap();
         assert_eq!(feature_set.product_type, ProductType::Sgp30);
         assert_eq!(feature_set.product_version, 0x42);
+        assert_eq!(feature_set.raw(), 0x0042);
+        sgp.destroy().done();
+    }
+
+    /// Test the `feature_set` and `refresh_feature_set` functions.
+    #[test]
+    fn feature_set_cached() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x00, 0x42, 0xDE]),
+            Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x00, 0x22, 0x65]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        // Only the first call reads from the sensor
+        assert_eq!(sgp.feature_set().unwrap().product_version, 0x42);
+        assert_eq!(sgp.feature_set().unwrap().product_version, 0x42);
+        assert_eq!(sgp.refresh_feature_set().unwrap().product_version, 0x22);
+        assert_eq!(sgp.feature_set().unwrap().product_version, 0x22);
+        sgp.destroy().done();
+    }
+
+    /// Test the `get_feature_set` function with combined reads enabled.
+    #[test]
+    fn get_feature_set_combined() {
+        let expectations = [Transaction::write_read(
+            0x58,
+            CommandId::GetFeatureSet.as_bytes()[..].into(),
+            vec![0x00, 0x42, 0xDE],
+        )];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        sgp.set_combined_reads(true);
+        let feature_set = sgp.get_feature_set().unwrap();
+        assert_eq!(feature_set.product_type, ProductType::Sgp30);
+        assert_eq!(feature_set.product_version, 0x42);
+        assert_eq!(feature_set.raw(), 0x0042);
+        sgp.destroy().done();
+    }
+
+    /// Test that bus errors during a read are reported as `Error::I2cRead`
+    #[test]
+    fn read_error() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x00, 0x42, 0xDE])
+                .with_error(embedded_hal::i2c::ErrorKind::Other),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        let err = sgp.get_feature_set().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::I2cRead);
+        assert_eq!(err.command(), Some(CommandId::GetFeatureSet));
+        sgp.destroy().done();
+    }
+
+    /// Test that bus errors during a write carry the command
+    #[test]
+    fn write_error() {
+        let expectations =
+            [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into())
+                    .with_error(embedded_hal::i2c::ErrorKind::Other),
+            ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        assert!(matches!(
+            sgp.init(),
+            Err(Error::I2cWrite {
+                command: CommandId::InitAirQuality,
+                source: embedded_hal::i2c::ErrorKind::Other,
+            })
+        ));
+        assert!(!sgp.is_initialized());
+        sgp.destroy().done();
+    }
+
+    /// Test that NACKs of the address are reported as
+    /// `Error::DeviceNotResponding`
+    #[test]
+    fn device_not_responding() {
+        use embedded_hal::i2c::{ErrorKind as BusErrorKind, NoAcknowledgeSource};
+
+        let expectations = [
+            Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into())
+                .with_error(BusErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+            Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x00, 0x42, 0xDE])
+                .with_error(BusErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+            // Other NACKs are regular bus errors
+            Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into())
+                .with_error(BusErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        let err = sgp.get_feature_set().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DeviceNotResponding);
+        assert_eq!(err.command(), Some(CommandId::GetFeatureSet));
+        let err = sgp.get_feature_set().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DeviceNotResponding);
+        let err = sgp.get_feature_set().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::I2cWrite);
+        sgp.destroy().done();
+    }
+
+    /// Test the `Display` and `source` implementations of the error type
+    #[cfg(feature = "std")]
+    #[test]
+    fn std_error() {
+        use std::error::Error as _;
+
+        let err: Error<std::io::Error> = Error::I2cRead {
+            command: CommandId::MeasureAirQuality,
+            source: std::io::Error::other("nack"),
+        };
+        assert_eq!(
+            err.to_string(),
+            "I²C bus error while reading the response to command MeasureAirQuality"
+        );
+        assert_eq!(err.source().unwrap().to_string(), "nack");
+        assert_eq!(err.kind().to_string(), "I²C bus error during a read");
+
+        let err: Error<std::io::Error> = Error::NotInitialized;
+        assert!(err.source().is_none());
+    }
+
+    /// Test that failures are recorded in the error history
+    #[cfg(feature = "error-history")]
+    #[test]
+    fn recent_errors() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x00, 0x42, 0x00]),
+            Transaction::write(0x58, CommandId::GetFeatureSet.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x00, 0x42, 0xDE]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        assert!(matches!(
+            sgp.get_feature_set(),
+            Err(Error::Crc {
+                command: CommandId::GetFeatureSet
+            })
+        ));
+        sgp.get_feature_set().unwrap();
+        assert_eq!(sgp.recent_errors().total(), 1);
+        assert_eq!(
+            sgp.recent_errors().last(),
+            Some(ErrorRecord {
+                seq: 0,
+                kind: ErrorKind::Crc,
+            })
+        );
+        sgp.clear_recent_errors();
+        assert_eq!(sgp.recent_errors().last(), None);
         sgp.destroy().done();
     }
 
@@ -794,8 +1880,8 @@ mod tests {
     #[test]
     fn measure_raw_signals() {
         let expectations = [
-            Transaction::write(0x58, Command::InitAirQuality.as_bytes()[..].into()),
-            Transaction::write(0x58, Command::MeasureRawSignals.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
             Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
         ];
         let mock = I2cMock::new(&expectations);
@@ -806,4 +1892,287 @@ mod tests {
         assert_eq!(signals.ethanol, (0x56 << 8) + 0x78);
         sgp.destroy().done();
     }
+
+    /// Test the `measure_raw_signals` function in the raw signals only mode
+    #[test]
+    fn measure_raw_signals_only() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        assert!(matches!(
+            sgp.measure_raw_signals(),
+            Err(Error::NotInitialized)
+        ));
+        assert!(!sgp.raw_signals_only());
+        sgp.set_raw_signals_only(true);
+        assert!(sgp.raw_signals_only());
+        let signals = sgp.measure_raw_signals().unwrap();
+        assert_eq!(signals.h2, 0x1234);
+        assert_eq!(signals.ethanol, 0x5678);
+        // The air quality measurement still requires initialization
+        assert!(matches!(sgp.measure(), Err(Error::NotInitialized)));
+        sgp.destroy().done();
+    }
+
+    /// Test the `wait_for_live_data` function
+    #[test]
+    fn wait_for_live_data() {
+        let warm_up = [0x01, 0x90, 0x4C, 0x00, 0x00, 0x81];
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, warm_up.into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x16]),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, warm_up.into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, warm_up.into()),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        // The correction doesn't affect the detection of live data
+        sgp.set_correction(Some(|m| Measurement::new(m.co2eq_ppm + 10, m.tvoc_ppb)));
+        assert!(matches!(
+            sgp.wait_for_live_data(1),
+            Err(Error::NotInitialized)
+        ));
+        sgp.init().unwrap();
+        let measurement = sgp.wait_for_live_data(5).unwrap();
+        assert_eq!(measurement, Measurement::new(422, 7));
+        assert!(matches!(
+            sgp.wait_for_live_data(2),
+            Err(Error::Timeout {
+                command: CommandId::MeasureAirQuality
+            })
+        ));
+        sgp.destroy().done();
+    }
+
+    /// Test the `collect_n` function
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn collect_n() {
+        let measure =
+            || Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into());
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            measure(),
+            Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+            measure(),
+            Transaction::read(0x58, vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x16]),
+            measure(),
+            Transaction::read(0x58, vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x16]),
+            measure(),
+            Transaction::read(0x58, vec![0x01, 0x9C, 0x00, 0x00, 0x07, 0x16]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        assert!(matches!(sgp.collect_n::<2>(), Err(Error::NotInitialized)));
+        sgp.init().unwrap();
+        let measurements = sgp.collect_n::<2>().unwrap();
+        assert_eq!(
+            measurements,
+            [Measurement::new(400, 0), Measurement::new(412, 7)]
+        );
+        assert!(sgp.collect_n::<0>().unwrap().is_empty());
+        // An error discards the collected measurements
+        assert!(matches!(sgp.collect_n::<2>(), Err(Error::Crc { .. })));
+        sgp.destroy().done();
+    }
+
+    /// Test the `set_skip_crc` function
+    #[test]
+    fn skip_crc() {
+        let measure =
+            || Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into());
+        // Corrupted CRC of the TVOC word
+        let response = vec![0x01, 0x9C, 0x31, 0x00, 0x07, 0x00];
+        #[rustfmt::skip]
+        let expectations = [
+            measure(),
+            Transaction::read(0x58, response.clone()),
+            measure(),
+            Transaction::read(0x58, response),
+            // The CRC is still sent to the sensor
+            Transaction::write(0x58, vec![
+                /* command: */ 0x20, 0x61,
+                /* data + crc8: */ 0x0F, 0x80, 0x62,
+            ]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::assume_initialized(mock, 0x58, NoopDelay);
+        assert!(!sgp.skip_crc());
+        assert!(matches!(sgp.measure(), Err(Error::Crc { .. })));
+        sgp.set_skip_crc(true);
+        assert!(sgp.skip_crc());
+        assert_eq!(sgp.measure().unwrap(), Measurement::new(412, 7));
+        let humidity = Humidity::new(0x0F, 0x80).unwrap();
+        sgp.set_humidity(Some(&humidity)).unwrap();
+        sgp.destroy().done();
+    }
+
+    /// Test the `rebuild` function
+    #[test]
+    fn rebuild() {
+        let expectations = [Transaction::write(
+            0x42,
+            CommandId::InitAirQuality.as_bytes()[..].into(),
+        )];
+        let mock = I2cMock::new(&expectations);
+        let mut old_mock = mock.clone();
+        let mut sgp = Sgp30::new(mock, 0x42, NoopDelay);
+        sgp.init().unwrap();
+        sgp.set_combined_reads(true);
+
+        let expectations = [
+            Transaction::write_read(
+                0x42,
+                CommandId::GetFeatureSet.as_bytes()[..].into(),
+                vec![0x00, 0x42, 0xDE],
+            ),
+            Transaction::write(0x42, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x42, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+        ];
+        let mut sgp = sgp.rebuild(I2cMock::new(&expectations));
+        old_mock.done();
+        assert!(sgp.is_initialized());
+        sgp.get_feature_set().unwrap();
+        sgp.measure().unwrap();
+        sgp.destroy().done();
+    }
+
+    /// Test the `set_extra_read_delay_us` function
+    #[test]
+    fn extra_read_delay() {
+        use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+
+        let expectations = [
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            Transaction::write_read(
+                0x58,
+                CommandId::GetFeatureSet.as_bytes()[..].into(),
+                vec![0x00, 0x42, 0xDE],
+            ),
+        ];
+        let delays = [
+            DelayTransaction::delay_us(commands::MEASURE_AIR_QUALITY.max_duration_us),
+            DelayTransaction::delay_us(500),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut delay = CheckedDelay::new(&delays);
+        let mut sgp = Sgp30::assume_initialized(mock, 0x58, delay.clone());
+        assert_eq!(sgp.extra_read_delay_us(), 0);
+        sgp.set_extra_read_delay_us(500);
+        assert_eq!(sgp.extra_read_delay_us(), 500);
+        sgp.measure().unwrap();
+        // Combined reads are not delayed
+        sgp.set_combined_reads(true);
+        sgp.get_feature_set().unwrap();
+        sgp.destroy().done();
+        delay.done();
+    }
+
+    /// Test the `set_poll_interval_us` function
+    #[test]
+    fn poll_until_ready() {
+        use embedded_hal::i2c::{ErrorKind as BusErrorKind, NoAcknowledgeSource};
+        use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+
+        let nack = BusErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+        let response = vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4];
+        let expectations = [
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, response.clone()).with_error(nack),
+            Transaction::read(0x58, response.clone()),
+            // Not ready within the maximum duration
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, response.clone()).with_error(nack),
+            Transaction::read(0x58, response.clone()).with_error(nack),
+            Transaction::read(0x58, response).with_error(nack),
+        ];
+        let delays = [
+            DelayTransaction::delay_us(10_000),
+            DelayTransaction::delay_us(1_500),
+            DelayTransaction::delay_us(10_000),
+            DelayTransaction::delay_us(1_500),
+            DelayTransaction::delay_us(500),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut delay = CheckedDelay::new(&delays);
+        let mut sgp = Sgp30::assume_initialized(mock, 0x58, delay.clone());
+        sgp.set_poll_interval_us(Some(1_500));
+        assert_eq!(sgp.poll_interval_us(), Some(1_500));
+        assert_eq!(sgp.measure().unwrap(), Measurement::new(4_660, 54_274));
+        assert_eq!(
+            sgp.measure().unwrap_err().kind(),
+            ErrorKind::DeviceNotResponding
+        );
+        sgp.destroy().done();
+        delay.done();
+    }
+
+    /// Test the `measure_unchecked` and `measure_raw_signals_unchecked`
+    /// functions
+    #[test]
+    fn measure_unchecked() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            // Invalid CRC
+            Transaction::read(0x58, vec![0x01, 0x90, 0x00, 0x00, 0x00, 0x81]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        sgp.set_correction(Some(|m| Measurement::new(0, m.tvoc_ppb)));
+        // Neither initialized nor corrected
+        let measurement = sgp.measure_unchecked().unwrap();
+        assert_eq!(measurement, Measurement::new(4_660, 54_274));
+        assert_eq!(sgp.last_measurement(), None);
+        let signals = sgp.measure_raw_signals_unchecked().unwrap();
+        assert_eq!(signals, RawSignals::new(0x1234, 0x5678));
+        assert!(matches!(
+            sgp.measure_unchecked(),
+            Err(Error::Crc {
+                command: CommandId::MeasureAirQuality
+            })
+        ));
+        sgp.destroy().done();
+    }
+
+    /// Test the `last_measurement` and `last_raw_signals` functions
+    #[test]
+    fn last_measurement() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            // Invalid CRC
+            Transaction::read(0x58, vec![0x01, 0x90, 0x00, 0x00, 0x00, 0x81]),
+            Transaction::write(0x58, CommandId::MeasureRawSignals.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]),
+        ];
+        let mock = I2cMock::new(&expectations);
+        let mut sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        assert_eq!(sgp.last_measurement(), None);
+        assert_eq!(sgp.last_raw_signals(), None);
+        sgp.init().unwrap();
+        let measurement = sgp.measure().unwrap();
+        assert_eq!(sgp.last_measurement(), Some(measurement));
+        // A failed measurement keeps the last successful one
+        assert!(sgp.measure().is_err());
+        assert_eq!(sgp.last_measurement(), Some(measurement));
+        assert_eq!(sgp.last_raw_signals(), None);
+        let signals = sgp.measure_raw_signals().unwrap();
+        assert_eq!(sgp.last_raw_signals(), Some(signals));
+        sgp.destroy().done();
+    }
 }
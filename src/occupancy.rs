@@ -0,0 +1,186 @@
+//! Room occupancy estimation from the CO₂eq trend.
+//!
+//! People exhale CO₂, so in a room with limited ventilation both the CO₂
+//! level and its rate of rise indicate whether the room is occupied. The
+//! [`OccupancyEstimator`] combines both into a coarse [`Occupancy`] level:
+//!
+//! ```
+//! use sgp30::{
+//!     occupancy::{Occupancy, OccupancyConfig, OccupancyEstimator},
+//!     Measurement,
+//! };
+//!
+//! let mut estimator = OccupancyEstimator::new(OccupancyConfig::DEFAULT);
+//! assert_eq!(estimator.update(&Measurement::new(450, 0)), Occupancy::Empty);
+//! assert_eq!(estimator.update(&Measurement::new(1500, 0)), Occupancy::High);
+//! ```
+//!
+//! Keep in mind that the CO₂eq value of the SGP30 is derived from the H2
+//! signal rather than measured directly, and that ventilation, open windows
+//! and other sources of VOCs affect it as well. The estimate is a cheap
+//! heuristic, not a people counter.
+//!
+//! This module requires the `occupancy` feature.
+
+use crate::Measurement;
+
+/// The estimated occupancy level.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum Occupancy {
+    /// The room is likely empty
+    Empty,
+    /// The room is likely occupied by few people
+    Low,
+    /// The room is likely occupied by many people
+    High,
+}
+
+/// Thresholds and parameters of the [`OccupancyEstimator`].
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct OccupancyConfig {
+    /// CO₂eq level in ppm at or above which the room is considered to have
+    /// a low occupancy.
+    pub low_ppm: u16,
+    /// CO₂eq level in ppm at or above which the room is considered to have
+    /// a high occupancy.
+    pub high_ppm: u16,
+    /// Rate of rise in ppm per minute at or above which the room is
+    /// considered to have a low occupancy.
+    pub low_rise_ppm_per_min: f32,
+    /// Rate of rise in ppm per minute at or above which the room is
+    /// considered to have a high occupancy.
+    pub high_rise_ppm_per_min: f32,
+    /// The interval between two measurements in seconds.
+    pub sample_interval_s: f32,
+    /// The weight of a new sample when smoothing the rate of rise, between
+    /// 0 (exclusive) and 1. Smaller values smooth over a longer period.
+    pub smoothing: f32,
+}
+
+impl OccupancyConfig {
+    /// Default configuration for measurements taken at 1 Hz, smoothing the
+    /// rate of rise over roughly one minute.
+    pub const DEFAULT: Self = Self {
+        low_ppm: 800,
+        high_ppm: 1200,
+        low_rise_ppm_per_min: 5.0,
+        high_rise_ppm_per_min: 20.0,
+        sample_interval_s: 1.0,
+        smoothing: 1.0 / 60.0,
+    };
+}
+
+impl Default for OccupancyConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Estimates the occupancy from a series of measurements.
+#[derive(Debug, Clone)]
+pub struct OccupancyEstimator {
+    config: OccupancyConfig,
+    last_co2eq_ppm: Option<u16>,
+    rate_ppm_per_min: f32,
+}
+
+impl OccupancyEstimator {
+    /// Create a new estimator.
+    pub const fn new(config: OccupancyConfig) -> Self {
+        Self {
+            config,
+            last_co2eq_ppm: None,
+            rate_ppm_per_min: 0.0,
+        }
+    }
+
+    /// Add a measurement, return the updated occupancy estimate.
+    ///
+    /// The measurements are expected to be taken at the configured
+    /// [`sample_interval_s`](OccupancyConfig::sample_interval_s).
+    pub fn update(&mut self, measurement: &Measurement) -> Occupancy {
+        let co2eq_ppm = measurement.co2eq_ppm;
+        if let Some(last) = self.last_co2eq_ppm {
+            let delta = f32::from(co2eq_ppm) - f32::from(last);
+            let rate = delta * 60.0 / self.config.sample_interval_s;
+            self.rate_ppm_per_min += self.config.smoothing * (rate - self.rate_ppm_per_min);
+        }
+        self.last_co2eq_ppm = Some(co2eq_ppm);
+        self.occupancy()
+    }
+
+    /// Return the current occupancy estimate.
+    pub fn occupancy(&self) -> Occupancy {
+        let level = self.last_co2eq_ppm.unwrap_or(0);
+        let rate = self.rate_ppm_per_min;
+        if level >= self.config.high_ppm || rate >= self.config.high_rise_ppm_per_min {
+            Occupancy::High
+        } else if level >= self.config.low_ppm || rate >= self.config.low_rise_ppm_per_min {
+            Occupancy::Low
+        } else {
+            Occupancy::Empty
+        }
+    }
+
+    /// Return the smoothed rate of rise of the CO₂eq value in ppm per minute.
+    pub fn rate_ppm_per_min(&self) -> f32 {
+        self.rate_ppm_per_min
+    }
+
+    /// Forget all measurements, e.g. after the sensor was restarted.
+    pub fn reset(&mut self) {
+        self.last_co2eq_ppm = None;
+        self.rate_ppm_per_min = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn co2eq(ppm: u16) -> Measurement {
+        Measurement::new(ppm, 0)
+    }
+
+    #[test]
+    fn steady() {
+        let mut estimator = OccupancyEstimator::new(OccupancyConfig::DEFAULT);
+        for _ in 0..120 {
+            assert_eq!(estimator.update(&co2eq(400)), Occupancy::Empty);
+        }
+        assert_eq!(estimator.rate_ppm_per_min(), 0.0);
+        // A sudden jump counts as a steep rise
+        assert_eq!(estimator.update(&co2eq(1000)), Occupancy::High);
+        estimator.reset();
+        assert_eq!(estimator.update(&co2eq(1000)), Occupancy::Low);
+        estimator.reset();
+        assert_eq!(estimator.update(&co2eq(1200)), Occupancy::High);
+    }
+
+    #[test]
+    fn rising() {
+        let mut estimator = OccupancyEstimator::new(OccupancyConfig::DEFAULT);
+        // Rising by 12 ppm per minute
+        let mut occupancy = Occupancy::Empty;
+        for i in 0..120 {
+            occupancy = estimator.update(&co2eq(450 + i / 5));
+        }
+        assert_eq!(occupancy, Occupancy::Low);
+        // Rising by 60 ppm per minute
+        for i in 0..120 {
+            occupancy = estimator.update(&co2eq(474 + i));
+        }
+        assert!(estimator.rate_ppm_per_min() > 50.0);
+        assert_eq!(occupancy, Occupancy::High);
+    }
+
+    #[test]
+    fn falling() {
+        let mut estimator = OccupancyEstimator::new(OccupancyConfig::DEFAULT);
+        for i in 0..300 {
+            estimator.update(&co2eq(790 - i));
+        }
+        assert!(estimator.rate_ppm_per_min() < -50.0);
+        assert_eq!(estimator.occupancy(), Occupancy::Empty);
+    }
+}
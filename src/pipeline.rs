@@ -0,0 +1,242 @@
+//! Chainable processing of measurements.
+//!
+//! A [`Processor`] transforms a measurement, or drops it by returning `None`.
+//! Processors can be chained using [`Processor::chain()`], and applied to
+//! any iterator yielding measurements (such as the
+//! [`Sampler`](crate::sampler::Sampler)) using
+//! [`ProcessExt::process_with()`]:
+//!
+//! ```no_run
+//! use linux_embedded_hal::{Delay, I2cdev};
+//! use sgp30::{
+//!     pipeline::{Downsample, ProcessExt, Processor},
+//!     sampler::Sampler,
+//!     Measurement, Sgp30,
+//! };
+//!
+//! let dev = I2cdev::new("/dev/i2c-1").unwrap();
+//! let sgp = Sgp30::new(dev, 0x58, Delay);
+//!
+//! // Skip the fixed values during the 15 s initialization phase, apply a
+//! // correction and report the average of every minute.
+//! let mut warm_up = 15;
+//! let pipeline = (move |m: Measurement| {
+//!     if warm_up > 0 {
+//!         warm_up -= 1;
+//!         return None;
+//!     }
+//!     Some(m)
+//! })
+//! .chain(|m: Measurement| {
+//!     Some(Measurement {
+//!         co2eq_ppm: m.co2eq_ppm.saturating_sub(20),
+//!         ..m
+//!     })
+//! })
+//! .chain(Downsample::new(60));
+//!
+//! for measurement in Sampler::new(sgp).process_with(pipeline) {
+//!     println!("{:?}", measurement.unwrap());
+//! }
+//! ```
+//!
+//! Closures of type `FnMut(Measurement) -> Option<Measurement>` implement
+//! [`Processor`].
+
+use crate::Measurement;
+
+/// A processing step for measurements.
+pub trait Processor {
+    /// Process a measurement. Return `None` to drop the measurement.
+    fn process(&mut self, measurement: Measurement) -> Option<Measurement>;
+
+    /// Return a processor passing the output of this processor to `next`.
+    fn chain<P: Processor>(self, next: P) -> Chain<Self, P>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+        }
+    }
+}
+
+impl<F> Processor for F
+where
+    F: FnMut(Measurement) -> Option<Measurement>,
+{
+    fn process(&mut self, measurement: Measurement) -> Option<Measurement> {
+        self(measurement)
+    }
+}
+
+/// Two chained processors, see [`Processor::chain()`].
+#[derive(Debug, Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Processor, B: Processor> Processor for Chain<A, B> {
+    fn process(&mut self, measurement: Measurement) -> Option<Measurement> {
+        self.first
+            .process(measurement)
+            .and_then(|measurement| self.second.process(measurement))
+    }
+}
+
+/// A processor averaging every `factor` measurements into a single one.
+#[derive(Debug, Clone)]
+pub struct Downsample {
+    factor: u16,
+    count: u16,
+    co2eq_sum: u32,
+    tvoc_sum: u32,
+}
+
+impl Downsample {
+    /// Create a new downsampler. A `factor` of 0 is treated as 1.
+    pub const fn new(factor: u16) -> Self {
+        Self {
+            factor: if factor == 0 { 1 } else { factor },
+            count: 0,
+            co2eq_sum: 0,
+            tvoc_sum: 0,
+        }
+    }
+}
+
+impl Processor for Downsample {
+    fn process(&mut self, measurement: Measurement) -> Option<Measurement> {
+        self.co2eq_sum += u32::from(measurement.co2eq_ppm);
+        self.tvoc_sum += u32::from(measurement.tvoc_ppb);
+        self.count += 1;
+        if self.count < self.factor {
+            return None;
+        }
+        let factor = u32::from(self.factor);
+        // The mean of u16 values always fits into a u16
+        let averaged = Measurement {
+            co2eq_ppm: (self.co2eq_sum / factor) as u16,
+            tvoc_ppb: (self.tvoc_sum / factor) as u16,
+        };
+        self.count = 0;
+        self.co2eq_sum = 0;
+        self.tvoc_sum = 0;
+        Some(averaged)
+    }
+}
+
+/// Extension trait applying a [`Processor`] to an iterator of measurements.
+pub trait ProcessExt<E>: Iterator<Item = Result<Measurement, E>> + Sized {
+    /// Pass every measurement through `processor`. Dropped measurements are
+    /// skipped, errors are passed through unchanged.
+    fn process_with<P: Processor>(self, processor: P) -> Processed<Self, P> {
+        Processed {
+            inner: self,
+            processor,
+        }
+    }
+}
+
+impl<I, E> ProcessExt<E> for I where I: Iterator<Item = Result<Measurement, E>> {}
+
+/// An iterator adapter processing measurements, see
+/// [`ProcessExt::process_with()`].
+#[derive(Debug)]
+pub struct Processed<I, P> {
+    inner: I,
+    processor: P,
+}
+
+impl<I, P> Processed<I, P> {
+    /// Destroy the adapter, return the wrapped iterator and processor.
+    pub fn into_parts(self) -> (I, P) {
+        (self.inner, self.processor)
+    }
+}
+
+impl<I, P, E> Iterator for Processed<I, P>
+where
+    I: Iterator<Item = Result<Measurement, E>>,
+    P: Processor,
+{
+    type Item = Result<Measurement, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(measurement) => {
+                    if let Some(measurement) = self.processor.process(measurement) {
+                        return Some(Ok(measurement));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(co2eq_ppm: u16, tvoc_ppb: u16) -> Measurement {
+        Measurement {
+            co2eq_ppm,
+            tvoc_ppb,
+        }
+    }
+
+    #[test]
+    fn downsample() {
+        let mut downsample = Downsample::new(3);
+        assert_eq!(downsample.process(measurement(400, 0)), None);
+        assert_eq!(downsample.process(measurement(500, 10)), None);
+        assert_eq!(
+            downsample.process(measurement(600, 21)),
+            Some(measurement(500, 10))
+        );
+        assert_eq!(downsample.process(measurement(400, 0)), None);
+
+        let mut passthrough = Downsample::new(0);
+        assert_eq!(
+            passthrough.process(measurement(65535, 65535)),
+            Some(measurement(65535, 65535))
+        );
+    }
+
+    #[test]
+    fn chain() {
+        let mut pipeline = (|m: Measurement| (m.tvoc_ppb != 0).then_some(m))
+            .chain(|m: Measurement| Some(measurement(m.co2eq_ppm + 1, m.tvoc_ppb)))
+            .chain(Downsample::new(2));
+        assert_eq!(pipeline.process(measurement(400, 0)), None);
+        assert_eq!(pipeline.process(measurement(401, 1)), None);
+        assert_eq!(
+            pipeline.process(measurement(403, 3)),
+            Some(measurement(403, 2))
+        );
+    }
+
+    #[test]
+    fn process_with() {
+        let input = [
+            Ok(measurement(400, 0)),
+            Err("error"),
+            Ok(measurement(410, 10)),
+            Ok(measurement(420, 20)),
+            Ok(measurement(430, 30)),
+        ];
+        let output: Vec<_> = input.into_iter().process_with(Downsample::new(2)).collect();
+        assert_eq!(
+            output,
+            [
+                Err("error"),
+                Ok(measurement(405, 5)),
+                Ok(measurement(425, 25)),
+            ]
+        );
+    }
+}
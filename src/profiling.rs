@@ -0,0 +1,333 @@
+//! Profiling of the command latencies.
+//!
+//! The [`ProfiledI2c`] wraps the I²C bus of a driver and measures the time
+//! from sending each command to successfully reading its response, using
+//! a user-provided [`Clock`]. The aggregated [`CommandTiming`]s show how
+//! long the commands actually take with the configured delays, e.g. to
+//! validate the delay implementation and clock configuration, or to tune
+//! the [polling interval](crate::Sgp30::set_poll_interval_us):
+//!
+//! ```
+//! use embedded_hal_mock::eh1::{
+//!     delay::NoopDelay,
+//!     i2c::{Mock as I2cMock, Transaction},
+//! };
+//! use sgp30::{commands::CommandId, profiling::ProfiledI2c, sampler::Clock, Sgp30};
+//!
+//! /// A clock advancing by 1 ms on every call.
+//! struct FakeClock(u32);
+//!
+//! impl Clock for FakeClock {
+//!     type Instant = u32;
+//!
+//!     fn now(&mut self) -> u32 {
+//!         self.0 += 1_000;
+//!         self.0
+//!     }
+//! }
+//!
+//! # let mock = I2cMock::new(&[
+//! #     Transaction::write(0x58, vec![0x20, 0x03]),
+//! #     Transaction::write(0x58, vec![0x20, 0x08]),
+//! #     Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+//! # ]);
+//! let mut sgp = Sgp30::new(ProfiledI2c::new(mock, FakeClock(0)), 0x58, NoopDelay);
+//! sgp.init().unwrap();
+//! sgp.measure().unwrap();
+//!
+//! let profiled = sgp.destroy();
+//! let timing = profiled.timing(CommandId::MeasureAirQuality);
+//! assert_eq!(timing.count, 1);
+//! assert_eq!(timing.max, 1_000);
+//! # profiled.into_inner().done();
+//! ```
+//!
+//! Commands without a response (e.g. the init command) are not profiled.
+//! NACKed reads while [polling](crate::Sgp30::set_poll_interval_us) for a
+//! response are included in the latency of the command. The wrapper
+//! implements both the blocking and the async `I2c` traits, so it can be
+//! used with both drivers.
+//!
+//! This module requires the `profiling` feature.
+
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+use crate::{commands::CommandId, sampler::Clock};
+
+/// Aggregated latencies of a command, in the unit of the [`Clock`].
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct CommandTiming {
+    /// The number of measured round-trips.
+    pub count: u32,
+    /// The shortest round-trip.
+    pub min: u32,
+    /// The longest round-trip.
+    pub max: u32,
+    /// The sum of all round-trips.
+    pub total: u64,
+}
+
+impl CommandTiming {
+    /// Return the average round-trip, or `None` if nothing was measured.
+    pub fn mean(&self) -> Option<u32> {
+        let mean = self.total.checked_div(u64::from(self.count))?;
+        Some(u32::try_from(mean).unwrap_or(u32::MAX))
+    }
+
+    /// Add a round-trip.
+    fn add(&mut self, elapsed: u32) {
+        self.min = if self.count == 0 {
+            elapsed
+        } else {
+            self.min.min(elapsed)
+        };
+        self.max = self.max.max(elapsed);
+        self.count = self.count.saturating_add(1);
+        self.total = self.total.saturating_add(u64::from(elapsed));
+    }
+}
+
+/// An I²C bus measuring the command latencies, see the
+/// [module documentation](self).
+///
+/// The clock must return monotonic timestamps, e.g. in microseconds. The
+/// timestamps may wrap around.
+#[derive(Debug)]
+pub struct ProfiledI2c<I2C, C> {
+    i2c: I2C,
+    clock: C,
+    /// The command whose response wasn't read yet, and the time at which it
+    /// was sent.
+    pending: Option<(CommandId, u32)>,
+    timings: [CommandTiming; CommandId::ALL.len()],
+}
+
+impl<I2C, C: Clock<Instant = u32>> ProfiledI2c<I2C, C> {
+    /// Wrap the I²C bus `i2c`, measuring the latencies with `clock`.
+    pub fn new(i2c: I2C, clock: C) -> Self {
+        Self {
+            i2c,
+            clock,
+            pending: None,
+            timings: [CommandTiming::default(); CommandId::ALL.len()],
+        }
+    }
+
+    /// Return the aggregated latencies of `command`.
+    pub fn timing(&self, command: CommandId) -> CommandTiming {
+        self.timings[command as usize]
+    }
+
+    /// Iterate over the aggregated latencies of all commands which were
+    /// measured at least once.
+    pub fn timings(&self) -> impl Iterator<Item = (CommandId, CommandTiming)> + '_ {
+        CommandId::ALL
+            .into_iter()
+            .map(|command| (command, self.timing(command)))
+            .filter(|(_, timing)| timing.count > 0)
+    }
+
+    /// Reset all aggregated latencies.
+    pub fn reset(&mut self) {
+        self.timings = [CommandTiming::default(); CommandId::ALL.len()];
+    }
+
+    /// Return a mutable reference to the wrapped I²C bus.
+    pub fn inner_mut(&mut self) -> &mut I2C {
+        &mut self.i2c
+    }
+
+    /// Destroy the wrapper, return the wrapped I²C bus.
+    pub fn into_inner(self) -> I2C {
+        self.i2c
+    }
+
+    /// Update the latencies after a bus transfer, which wrote the given
+    /// bytes at the given start time (if any) and read a response (if
+    /// `read`).
+    fn on_transfer(&mut self, written: Option<(u32, &[u8])>, read: bool, ok: bool) {
+        let command = written.and_then(|(start, data)| match data {
+            [c0, c1, ..] => Some((CommandId::from_code(u16::from_be_bytes([*c0, *c1]))?, start)),
+            _ => None,
+        });
+        if let Some((command, start)) = command {
+            self.pending = if ok { Some((command, start)) } else { None };
+        }
+        if !(read && ok) {
+            return;
+        }
+        if let Some((command, sent)) = self.pending.take() {
+            let elapsed = self.clock.now().wrapping_sub(sent);
+            self.timings[command as usize].add(elapsed);
+        }
+    }
+}
+
+/// Return the data of the first write operation, if any.
+fn first_write<'a>(operations: &'a [Operation<'_>]) -> Option<&'a [u8]> {
+    operations.iter().find_map(|operation| match operation {
+        Operation::Write(data) => Some(&data[..]),
+        Operation::Read(_) => None,
+    })
+}
+
+/// Return whether the operations contain a read.
+fn has_read(operations: &[Operation<'_>]) -> bool {
+    operations
+        .iter()
+        .any(|operation| matches!(operation, Operation::Read(_)))
+}
+
+impl<I2C: ErrorType, C> ErrorType for ProfiledI2c<I2C, C> {
+    type Error = I2C::Error;
+}
+
+impl<I2C: I2c, C: Clock<Instant = u32>> I2c for ProfiledI2c<I2C, C> {
+    fn read(&mut self, address: SevenBitAddress, read: &mut [u8]) -> Result<(), Self::Error> {
+        let result = self.i2c.read(address, read);
+        self.on_transfer(None, true, result.is_ok());
+        result
+    }
+
+    fn write(&mut self, address: SevenBitAddress, write: &[u8]) -> Result<(), Self::Error> {
+        let start = self.clock.now();
+        let result = self.i2c.write(address, write);
+        self.on_transfer(Some((start, write)), false, result.is_ok());
+        result
+    }
+
+    fn write_read(
+        &mut self,
+        address: SevenBitAddress,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let start = self.clock.now();
+        let result = self.i2c.write_read(address, write, read);
+        self.on_transfer(Some((start, write)), true, result.is_ok());
+        result
+    }
+
+    fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let start = self.clock.now();
+        let result = self.i2c.transaction(address, operations);
+        let written = first_write(operations).map(|data| (start, data));
+        self.on_transfer(written, has_read(operations), result.is_ok());
+        result
+    }
+}
+
+#[cfg(feature = "embedded-hal-async")]
+impl<I2C, C> embedded_hal_async::i2c::I2c for ProfiledI2c<I2C, C>
+where
+    I2C: embedded_hal_async::i2c::I2c,
+    C: Clock<Instant = u32>,
+{
+    async fn read(&mut self, address: SevenBitAddress, read: &mut [u8]) -> Result<(), Self::Error> {
+        let result = self.i2c.read(address, read).await;
+        self.on_transfer(None, true, result.is_ok());
+        result
+    }
+
+    async fn write(&mut self, address: SevenBitAddress, write: &[u8]) -> Result<(), Self::Error> {
+        let start = self.clock.now();
+        let result = self.i2c.write(address, write).await;
+        self.on_transfer(Some((start, write)), false, result.is_ok());
+        result
+    }
+
+    async fn write_read(
+        &mut self,
+        address: SevenBitAddress,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let start = self.clock.now();
+        let result = self.i2c.write_read(address, write, read).await;
+        self.on_transfer(Some((start, write)), true, result.is_ok());
+        result
+    }
+
+    async fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let start = self.clock.now();
+        let result = self.i2c.transaction(address, operations).await;
+        let written = first_write(operations).map(|data| (start, data));
+        self.on_transfer(written, has_read(operations), result.is_ok());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        i2c::{Mock as I2cMock, Transaction},
+    };
+
+    use super::*;
+    use crate::Sgp30;
+
+    /// A clock returning the given timestamps.
+    struct FakeClock(std::vec::IntoIter<u32>);
+
+    impl Clock for FakeClock {
+        type Instant = u32;
+
+        fn now(&mut self) -> u32 {
+            self.0.next().unwrap()
+        }
+    }
+
+    #[test]
+    fn timings() {
+        let response = vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81];
+        let expectations = [
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, response.clone()),
+            // Polled until ready
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, response.clone())
+                .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+            Transaction::read(0x58, response),
+            Transaction::write_read(
+                0x58,
+                CommandId::GetFeatureSet.as_bytes()[..].into(),
+                vec![0x00, 0x42, 0xDE],
+            ),
+        ];
+        // The second measurement wraps around
+        let clock = FakeClock(vec![100, 12_100, u32::MAX - 999, 13_000, 20, 40].into_iter());
+        let mut sgp = Sgp30::assume_initialized(
+            ProfiledI2c::new(I2cMock::new(&expectations), clock),
+            0x58,
+            NoopDelay,
+        );
+        sgp.set_poll_interval_us(Some(1_000));
+        sgp.measure().unwrap();
+        sgp.measure().unwrap();
+        sgp.set_combined_reads(true);
+        sgp.get_feature_set().unwrap();
+
+        let mut profiled = sgp.destroy();
+        let timing = profiled.timing(CommandId::MeasureAirQuality);
+        assert_eq!(timing.count, 2);
+        assert_eq!(timing.min, 12_000);
+        assert_eq!(timing.max, 14_000);
+        assert_eq!(timing.mean(), Some(13_000));
+        assert_eq!(profiled.timing(CommandId::GetFeatureSet).max, 20);
+        assert_eq!(profiled.timing(CommandId::GetBaseline).mean(), None);
+        assert_eq!(profiled.timings().count(), 2);
+        profiled.reset();
+        assert_eq!(profiled.timings().count(), 0);
+        profiled.into_inner().done();
+    }
+}
@@ -0,0 +1,240 @@
+//! Protocol Buffers messages for telemetry.
+//!
+//! The message types in this module are [`prost`] messages matching the
+//! definitions in `proto/sgp30.proto`, and can be converted from the
+//! measurements and the health reports of the driver:
+//!
+//! ```
+//! use prost::Message;
+//! use sgp30::{proto, Measurement};
+//!
+//! let measurement = Measurement::new(412, 27);
+//! let bytes = proto::Measurement::from(measurement).encode_to_vec();
+//!
+//! let decoded = proto::Measurement::decode(&bytes[..]).unwrap();
+//! assert_eq!(Measurement::try_from(decoded), Ok(measurement));
+//! ```
+//!
+//! The health reports ([`CalibrationReport`](crate::calibration::CalibrationReport)
+//! and [`AgingEstimate`](crate::aging::AgingEstimate)) can only be converted
+//! into messages, since they are computed on the device.
+//!
+//! This module requires the `prost` feature. Encoding and decoding require a
+//! global allocator, and [`prost`] does not support targets without atomic
+//! compare-and-swap instructions (e.g. `thumbv6m-none-eabi`).
+
+use core::num::TryFromIntError;
+
+/// A measurement result from the sensor, see [`crate::Measurement`].
+#[derive(Clone, Copy, PartialEq, Eq, prost::Message)]
+pub struct Measurement {
+    /// CO₂ equivalent (parts per million, ppm)
+    #[prost(uint32, tag = "1")]
+    pub co2eq_ppm: u32,
+    /// Total Volatile Organic Compounds (parts per billion, ppb)
+    #[prost(uint32, tag = "2")]
+    pub tvoc_ppb: u32,
+}
+
+impl From<crate::Measurement> for Measurement {
+    fn from(measurement: crate::Measurement) -> Self {
+        Self {
+            co2eq_ppm: measurement.co2eq_ppm.into(),
+            tvoc_ppb: measurement.tvoc_ppb.into(),
+        }
+    }
+}
+
+impl TryFrom<Measurement> for crate::Measurement {
+    type Error = TryFromIntError;
+
+    /// Convert a message into a measurement. Fails if a value exceeds the
+    /// range of the sensor.
+    fn try_from(measurement: Measurement) -> Result<Self, Self::Error> {
+        Ok(Self::new(
+            measurement.co2eq_ppm.try_into()?,
+            measurement.tvoc_ppb.try_into()?,
+        ))
+    }
+}
+
+/// The baseline values, see [`crate::Baseline`].
+#[derive(Clone, Copy, PartialEq, Eq, prost::Message)]
+pub struct Baseline {
+    /// CO₂eq baseline
+    #[prost(uint32, tag = "1")]
+    pub co2eq: u32,
+    /// TVOC baseline
+    #[prost(uint32, tag = "2")]
+    pub tvoc: u32,
+}
+
+impl From<crate::Baseline> for Baseline {
+    fn from(baseline: crate::Baseline) -> Self {
+        Self {
+            co2eq: baseline.co2eq.into(),
+            tvoc: baseline.tvoc.into(),
+        }
+    }
+}
+
+impl TryFrom<Baseline> for crate::Baseline {
+    type Error = TryFromIntError;
+
+    /// Convert a message into a baseline. Fails if a value exceeds 16 bits.
+    fn try_from(baseline: Baseline) -> Result<Self, Self::Error> {
+        Ok(Self::new(
+            baseline.co2eq.try_into()?,
+            baseline.tvoc.try_into()?,
+        ))
+    }
+}
+
+/// Summary statistics of a single raw signal, see
+/// [`SignalStats`](crate::calibration::SignalStats).
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct SignalStats {
+    /// The arithmetic mean of the signal.
+    #[prost(float, tag = "1")]
+    pub mean: f32,
+    /// The smallest value of the signal.
+    #[prost(uint32, tag = "2")]
+    pub min: u32,
+    /// The largest value of the signal.
+    #[prost(uint32, tag = "3")]
+    pub max: u32,
+    /// The (population) variance of the signal.
+    #[prost(float, tag = "4")]
+    pub variance: f32,
+    /// The drift of the signal in ticks per sample.
+    #[prost(float, tag = "5")]
+    pub drift: f32,
+}
+
+impl From<crate::calibration::SignalStats> for SignalStats {
+    fn from(stats: crate::calibration::SignalStats) -> Self {
+        Self {
+            mean: stats.mean,
+            min: stats.min.into(),
+            max: stats.max.into(),
+            variance: stats.variance,
+            drift: stats.drift,
+        }
+    }
+}
+
+/// The summary of the raw signals, see
+/// [`CalibrationReport`](crate::calibration::CalibrationReport).
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct CalibrationReport {
+    /// The number of samples the statistics are based on.
+    #[prost(uint32, tag = "1")]
+    pub samples: u32,
+    /// Statistics of the H2 signal.
+    #[prost(message, optional, tag = "2")]
+    pub h2: Option<SignalStats>,
+    /// Statistics of the Ethanol signal.
+    #[prost(message, optional, tag = "3")]
+    pub ethanol: Option<SignalStats>,
+}
+
+impl From<crate::calibration::CalibrationReport> for CalibrationReport {
+    fn from(report: crate::calibration::CalibrationReport) -> Self {
+        Self {
+            samples: report.samples,
+            h2: Some(report.h2.into()),
+            ethanol: Some(report.ethanol.into()),
+        }
+    }
+}
+
+/// The estimated drift of the baseline values, see
+/// [`AgingEstimate`](crate::aging::AgingEstimate).
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct AgingEstimate {
+    /// The number of records the estimate is based on.
+    #[prost(uint64, tag = "1")]
+    pub records: u64,
+    /// The time between the oldest and the newest record in hours.
+    #[prost(uint32, tag = "2")]
+    pub span_hours: u32,
+    /// Drift of the CO₂eq baseline in ticks per day.
+    #[prost(float, tag = "3")]
+    pub co2eq_drift_per_day: f32,
+    /// Drift of the TVOC baseline in ticks per day.
+    #[prost(float, tag = "4")]
+    pub tvoc_drift_per_day: f32,
+    /// Mean of the CO₂eq baseline.
+    #[prost(float, tag = "5")]
+    pub co2eq_mean: f32,
+    /// Mean of the TVOC baseline.
+    #[prost(float, tag = "6")]
+    pub tvoc_mean: f32,
+}
+
+impl From<crate::aging::AgingEstimate> for AgingEstimate {
+    fn from(estimate: crate::aging::AgingEstimate) -> Self {
+        Self {
+            // usize is at most 64 bits wide on all supported targets
+            records: estimate.records as u64,
+            span_hours: estimate.span_hours,
+            co2eq_drift_per_day: estimate.co2eq_drift_per_day,
+            tvoc_drift_per_day: estimate.tvoc_drift_per_day,
+            co2eq_mean: estimate.co2eq_mean,
+            tvoc_mean: estimate.tvoc_mean,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+
+    #[test]
+    fn measurement_round_trip() {
+        let measurement = crate::Measurement::new(412, 27);
+        let bytes = Measurement::from(measurement).encode_to_vec();
+        // Field 1 and 2 as varints
+        assert_eq!(bytes, [0x08, 0x9C, 0x03, 0x10, 0x1B]);
+        let decoded = Measurement::decode(&bytes[..]).unwrap();
+        assert_eq!(crate::Measurement::try_from(decoded), Ok(measurement));
+
+        let out_of_range = Measurement {
+            co2eq_ppm: 0x10000,
+            tvoc_ppb: 0,
+        };
+        assert!(crate::Measurement::try_from(out_of_range).is_err());
+    }
+
+    #[test]
+    fn baseline_round_trip() {
+        let baseline = crate::Baseline::new(0x8A3C, 0x91F0);
+        let bytes = Baseline::from(baseline).encode_to_vec();
+        let decoded = Baseline::decode(&bytes[..]).unwrap();
+        assert_eq!(crate::Baseline::try_from(decoded), Ok(baseline));
+    }
+
+    #[test]
+    fn calibration_report() {
+        let stats = crate::calibration::SignalStats {
+            mean: 13_500.5,
+            min: 13_400,
+            max: 13_600,
+            variance: 12.5,
+            drift: -0.25,
+        };
+        let report = crate::calibration::CalibrationReport {
+            samples: 60,
+            h2: stats.clone(),
+            ethanol: stats,
+        };
+        let message = CalibrationReport::from(report);
+        let decoded = CalibrationReport::decode(&message.encode_to_vec()[..]).unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(decoded.samples, 60);
+        assert_eq!(decoded.h2.unwrap().max, 13_600);
+        assert_eq!(decoded.ethanol.unwrap().drift, -0.25);
+    }
+}
@@ -0,0 +1,321 @@
+//! Decoupling of the measurements from their processing through a queue.
+//!
+//! The measurements have to be taken at a strict 1 s cadence, while their
+//! processing (e.g. sending them over a radio) may take longer from time to
+//! time. The [`SampleProducer`] takes the measurements and pushes them as
+//! numbered [`Sample`]s into a lock-free [`heapless::spsc`] queue, from which
+//! the application consumes them at its own pace, e.g. with the producer
+//! running in a timer interrupt and the consumer in the main loop:
+//!
+//! ```
+//! use embedded_hal_mock::eh1::{
+//!     delay::NoopDelay,
+//!     i2c::{Mock as I2cMock, Transaction},
+//! };
+//! use sgp30::{
+//!     queue::{SampleProducer, SampleQueue},
+//!     sampler::Sampler,
+//!     Sgp30,
+//! };
+//!
+//! # let mock = I2cMock::new(&[
+//! #     Transaction::write(0x58, vec![0x20, 0x03]),
+//! #     Transaction::write(0x58, vec![0x20, 0x08]),
+//! #     Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+//! # ]);
+//! let mut queue = SampleQueue::<16>::new();
+//! let (producer, mut consumer) = queue.split();
+//!
+//! let sgp = Sgp30::new(mock, 0x58, NoopDelay);
+//! let mut producer = SampleProducer::new(Sampler::new(sgp), producer);
+//!
+//! // In the sensor task or interrupt
+//! producer.step().unwrap();
+//!
+//! // In the application
+//! while let Some(sample) = consumer.dequeue() {
+//!     assert_eq!(sample.seq, 0);
+//!     assert_eq!(sample.measurement.co2eq_ppm, 400);
+//! }
+//! # producer.into_parts().0.into_inner().destroy().done();
+//! ```
+//!
+//! If the queue is full, new samples are dropped and counted, see
+//! [`SampleProducer::dropped()`]. The consumer can detect the resulting gaps
+//! through the sequence numbers of the samples.
+//!
+//! In the opposite direction, interrupt handlers and other contexts can
+//! request sensor operations through a [`RequestQueue`] without accessing
+//! the I²C bus themselves. The [`RequestExecutor`] executes the requests
+//! from the main loop, in between two measurements:
+//!
+//! ```
+//! use embedded_hal_mock::eh1::{
+//!     delay::NoopDelay,
+//!     i2c::{Mock as I2cMock, Transaction},
+//! };
+//! use sgp30::{
+//!     queue::{Request, RequestExecutor, RequestQueue, Response},
+//!     Sgp30,
+//! };
+//!
+//! # let mock = I2cMock::new(&[
+//! #     Transaction::write(0x58, vec![0x20, 0x15]),
+//! #     Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+//! # ]);
+//! let mut queue = RequestQueue::<4>::new();
+//! let (mut producer, consumer) = queue.split();
+//! let mut executor = RequestExecutor::new(consumer);
+//!
+//! // In an interrupt handler, e.g. of a button
+//! producer.enqueue(Request::SaveBaseline).unwrap();
+//!
+//! // In the main loop
+//! let mut sgp = Sgp30::assume_initialized(mock, 0x58, NoopDelay);
+//! while let Some((request, result)) = executor.execute_next(&mut sgp) {
+//!     if let Ok(Response::Baseline(baseline)) = result {
+//!         // Store the baseline
+//!         assert_eq!(baseline.co2eq, 0x1234);
+//!     }
+//! }
+//! # sgp.destroy().done();
+//! ```
+//!
+//! This module requires the `heapless` feature.
+
+use embedded_hal::delay::DelayNs;
+use heapless::spsc::{Consumer, Producer, Queue};
+
+use crate::{
+    sampler::{Sample, Sequenced},
+    transport::Sgp30Transport,
+    Baseline, Error, Humidity, Measurement, Sgp30,
+};
+
+/// A queue of samples holding up to `N - 1` samples.
+pub type SampleQueue<const N: usize> = Queue<Sample, N>;
+
+/// Takes measurements and pushes them into a [`SampleQueue`], see the
+/// [module documentation](self).
+pub struct SampleProducer<'a, S, const N: usize> {
+    samples: Sequenced<S>,
+    producer: Producer<'a, Sample, N>,
+    dropped: u32,
+}
+
+impl<'a, S, E, const N: usize> SampleProducer<'a, S, N>
+where
+    S: Iterator<Item = Result<Measurement, E>>,
+{
+    /// Create a new producer taking the measurements from `measurements`
+    /// (e.g. a [`Sampler`](crate::sampler::Sampler)), pushing them into
+    /// the queue of `producer`.
+    pub fn new(measurements: S, producer: Producer<'a, Sample, N>) -> Self {
+        Self {
+            samples: Sequenced::new(measurements, 0),
+            producer,
+            dropped: 0,
+        }
+    }
+
+    /// Take the next measurement and push it into the queue.
+    ///
+    /// When using a [`Sampler`](crate::sampler::Sampler), this waits for the
+    /// next 1 s interval. Failed measurements are returned and not pushed
+    /// into the queue. Does nothing if the wrapped iterator ended.
+    pub fn step(&mut self) -> Result<(), E> {
+        let Some(sample) = self.samples.next().transpose()? else {
+            return Ok(());
+        };
+        if self.producer.enqueue(sample).is_err() {
+            self.dropped = self.dropped.saturating_add(1);
+        }
+        Ok(())
+    }
+
+    /// Return the number of samples dropped because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Destroy the producer, return the wrapped measurement iterator and
+    /// queue producer.
+    pub fn into_parts(self) -> (S, Producer<'a, Sample, N>) {
+        (self.samples.into_inner(), self.producer)
+    }
+}
+
+/// A sensor operation requested through a [`RequestQueue`].
+///
+/// The self-test is not available as a request, since it must not be run
+/// while the air quality measurement is active.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Request {
+    /// Set the humidity used for compensation, see
+    /// [`Sgp30::set_humidity()`].
+    SetHumidity(Option<Humidity>),
+    /// Restore a baseline, see [`Sgp30::set_baseline()`].
+    SetBaseline(Baseline),
+    /// Read the baseline so that it can be stored, see
+    /// [`Sgp30::get_baseline()`].
+    SaveBaseline,
+}
+
+/// The result of a successfully executed [`Request`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Response {
+    /// The request was executed and has no result.
+    Done,
+    /// The baseline read for [`Request::SaveBaseline`].
+    Baseline(Baseline),
+}
+
+/// The result of executing a [`Request`].
+pub type RequestResult<E> = Result<Response, Error<E>>;
+
+/// A queue of requests holding up to `N - 1` requests.
+pub type RequestQueue<const N: usize> = Queue<Request, N>;
+
+/// Executes the requests of a [`RequestQueue`], see the
+/// [module documentation](self).
+pub struct RequestExecutor<'a, const N: usize> {
+    consumer: Consumer<'a, Request, N>,
+}
+
+impl<'a, const N: usize> RequestExecutor<'a, N> {
+    /// Create a new executor taking the requests from `consumer`.
+    pub fn new(consumer: Consumer<'a, Request, N>) -> Self {
+        Self { consumer }
+    }
+
+    /// Execute the oldest pending request on `sgp`, return the request and
+    /// its result. Returns `None` if no request is pending.
+    pub fn execute_next<I2C, D>(
+        &mut self,
+        sgp: &mut Sgp30<I2C, D>,
+    ) -> Option<(Request, RequestResult<I2C::Error>)>
+    where
+        I2C: Sgp30Transport,
+        D: DelayNs,
+    {
+        let request = self.consumer.dequeue()?;
+        let result = match request {
+            Request::SetHumidity(humidity) => {
+                sgp.set_humidity(humidity.as_ref()).map(|()| Response::Done)
+            }
+            Request::SetBaseline(baseline) => sgp.set_baseline(&baseline).map(|()| Response::Done),
+            Request::SaveBaseline => sgp.get_baseline().map(Response::Baseline),
+        };
+        Some((request, result))
+    }
+
+    /// Return whether requests are pending.
+    pub fn is_pending(&self) -> bool {
+        self.consumer.ready()
+    }
+
+    /// Destroy the executor, return the queue consumer.
+    pub fn into_inner(self) -> Consumer<'a, Request, N> {
+        self.consumer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        i2c::{Mock as I2cMock, Transaction},
+    };
+
+    use super::*;
+    use crate::commands::CommandId;
+
+    #[test]
+    fn queue_full() {
+        let measurements = (0..).map(|i| Ok::<_, ()>(Measurement::new(400 + i, 0)));
+        let mut queue = SampleQueue::<3>::new();
+        let (producer, mut consumer) = queue.split();
+        let mut producer = SampleProducer::new(measurements, producer);
+        for _ in 0..3 {
+            producer.step().unwrap();
+        }
+        assert_eq!(producer.dropped(), 1);
+        assert_eq!(consumer.dequeue().unwrap().seq, 0);
+        producer.step().unwrap();
+        assert_eq!(consumer.dequeue().unwrap().seq, 1);
+        // The third sample was dropped
+        let sample = consumer.dequeue().unwrap();
+        assert_eq!(sample.seq, 3);
+        assert_eq!(sample.measurement.co2eq_ppm, 403);
+        assert_eq!(consumer.dequeue(), None);
+    }
+
+    #[test]
+    fn error() {
+        let measurements = [Err(()), Ok(Measurement::new(400, 0))].into_iter();
+        let mut queue = SampleQueue::<3>::new();
+        let (producer, mut consumer) = queue.split();
+        let mut producer = SampleProducer::new(measurements, producer);
+        assert_eq!(producer.step(), Err(()));
+        assert_eq!(consumer.dequeue(), None);
+        assert_eq!(producer.step(), Ok(()));
+        assert_eq!(consumer.dequeue().unwrap().seq, 0);
+        assert_eq!(producer.dropped(), 0);
+    }
+
+    #[test]
+    fn requests() {
+        #[rustfmt::skip]
+        let expectations = [
+            Transaction::write(0x58, vec![
+                /* command: */ 0x20, 0x61,
+                /* data + crc8: */ 0x00, 0x00, 0x81,
+            ]),
+            Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            Transaction::write(0x58, vec![
+                /* command: */ 0x20, 0x1E,
+                /* data + crc8: */ 0x56, 0x78, 0x7D, 0x12, 0x34, 0x37,
+            ]),
+        ];
+        let mut sgp = Sgp30::assume_initialized(I2cMock::new(&expectations), 0x58, NoopDelay);
+        let mut queue = RequestQueue::<4>::new();
+        let (mut producer, consumer) = queue.split();
+        let mut executor = RequestExecutor::new(consumer);
+        assert!(!executor.is_pending());
+        assert!(executor.execute_next(&mut sgp).is_none());
+
+        producer.enqueue(Request::SetHumidity(None)).unwrap();
+        producer.enqueue(Request::SaveBaseline).unwrap();
+        let baseline = Baseline::new(0x1234, 0x5678);
+        producer.enqueue(Request::SetBaseline(baseline)).unwrap();
+        // The queue holds up to 3 requests
+        assert_eq!(
+            producer.enqueue(Request::SaveBaseline),
+            Err(Request::SaveBaseline)
+        );
+        assert!(executor.is_pending());
+
+        let mut execute = |sgp: &mut Sgp30<_, _>| {
+            let (request, result) = executor.execute_next(sgp)?;
+            Some((request, result.unwrap()))
+        };
+        assert_eq!(
+            execute(&mut sgp),
+            Some((Request::SetHumidity(None), Response::Done))
+        );
+        assert_eq!(
+            execute(&mut sgp),
+            Some((
+                Request::SaveBaseline,
+                Response::Baseline(Baseline::new(4_660, 54_274))
+            ))
+        );
+        assert_eq!(
+            execute(&mut sgp),
+            Some((Request::SetBaseline(baseline), Response::Done))
+        );
+        assert_eq!(execute(&mut sgp), None);
+        sgp.destroy().done();
+    }
+}
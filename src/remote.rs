@@ -0,0 +1,452 @@
+//! Remote access to a sensor over a serial link.
+//!
+//! In sensor-hub architectures, the SGP30 is often attached to a small
+//! co-processor, which is connected to the main processor via UART or TCP.
+//! This module provides both ends of such a link, speaking a tiny framed
+//! protocol over any [`embedded_io`] `Read` + `Write` stream:
+//!
+//! - The [`Sgp30Agent`] runs on the co-processor and executes the bus
+//!   transfers requested by the host on its I²C bus.
+//! - The [`Sgp30Remote`] runs on the host. It implements
+//!   [`Sgp30Transport`], so the regular [`Sgp30`](crate::Sgp30) driver can
+//!   be used on top of it. All command handling, CRC validation and timing
+//!   happen on the host.
+//!
+//! On the host:
+//!
+//! ```no_run
+//! # fn host<U: embedded_io::Read + embedded_io::Write>(uart: U) {
+//! use linux_embedded_hal::Delay;
+//! use sgp30::{remote::Sgp30Remote, Sgp30};
+//!
+//! let mut sgp = Sgp30::new(Sgp30Remote::new(uart), 0x58, Delay);
+//! sgp.init().unwrap();
+//! # }
+//! ```
+//!
+//! On the co-processor:
+//!
+//! ```no_run
+//! # fn device<I: embedded_hal::i2c::I2c, U: embedded_io::Read + embedded_io::Write>(
+//! #     i2c: I,
+//! #     mut uart: U,
+//! # ) {
+//! use sgp30::remote::Sgp30Agent;
+//!
+//! let mut agent = Sgp30Agent::new(i2c);
+//! loop {
+//!     agent.serve(&mut uart).unwrap();
+//! }
+//! # }
+//! ```
+//!
+//! ## Protocol
+//!
+//! Every request consists of a 4 byte header, followed by the data to write:
+//!
+//! | Byte | Content                                                      |
+//! |------|--------------------------------------------------------------|
+//! | 0    | Operation: `0x01` write, `0x02` read, `0x03` write-read      |
+//! | 1    | 7 bit I²C address                                            |
+//! | 2    | Number of bytes to write (at most [`MAX_TRANSFER_LEN`])      |
+//! | 3    | Number of bytes to read (at most [`MAX_TRANSFER_LEN`])       |
+//! | 4..  | The bytes to write                                           |
+//!
+//! The response starts with a status byte. On success (`0x00`), the status
+//! is followed by the bytes read. Otherwise, the status is `0xFF` for an
+//! invalid request, or one of the codes `0x01`–`0x07` for a bus error (see
+//! [`embedded_hal::i2c::ErrorKind`]).
+//!
+//! This module requires the `remote` feature.
+
+use embedded_hal::i2c::{self, ErrorKind, I2c, NoAcknowledgeSource};
+use embedded_io::{Read, ReadExactError, Write};
+
+use crate::transport::Sgp30Transport;
+
+/// The maximum number of bytes written or read in a single request.
+pub const MAX_TRANSFER_LEN: usize = 16;
+
+const OP_WRITE: u8 = 0x01;
+const OP_READ: u8 = 0x02;
+const OP_WRITE_READ: u8 = 0x03;
+
+const STATUS_OK: u8 = 0x00;
+const STATUS_INVALID_REQUEST: u8 = 0xFF;
+
+/// Encode a bus error as a status byte.
+fn encode_kind(kind: ErrorKind) -> u8 {
+    match kind {
+        ErrorKind::Bus => 0x01,
+        ErrorKind::ArbitrationLoss => 0x02,
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => 0x03,
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data) => 0x04,
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown) => 0x05,
+        ErrorKind::Overrun => 0x06,
+        _ => 0x07,
+    }
+}
+
+/// Decode a bus error status byte.
+fn decode_kind(status: u8) -> ErrorKind {
+    match status {
+        0x01 => ErrorKind::Bus,
+        0x02 => ErrorKind::ArbitrationLoss,
+        0x03 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+        0x04 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+        0x05 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+        0x06 => ErrorKind::Overrun,
+        _ => ErrorKind::Other,
+    }
+}
+
+/// Errors of the host side of the link.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum RemoteError<E> {
+    /// Error of the underlying stream
+    #[cfg_attr(feature = "std", error("I/O error: {0:?}"))]
+    Io(E),
+    /// The stream ended in the middle of a response
+    #[cfg_attr(feature = "std", error("unexpected end of stream"))]
+    UnexpectedEof,
+    /// The request was rejected by the agent, or exceeds
+    /// [`MAX_TRANSFER_LEN`]
+    #[cfg_attr(feature = "std", error("invalid request"))]
+    InvalidRequest,
+    /// Bus error reported by the agent
+    #[cfg_attr(feature = "std", error("remote I²C bus error: {0:?}"))]
+    Bus(ErrorKind),
+}
+
+impl<E> From<ReadExactError<E>> for RemoteError<E> {
+    fn from(err: ReadExactError<E>) -> Self {
+        match err {
+            ReadExactError::UnexpectedEof => RemoteError::UnexpectedEof,
+            ReadExactError::Other(e) => RemoteError::Io(e),
+        }
+    }
+}
+
+impl<E: core::fmt::Debug> i2c::Error for RemoteError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            RemoteError::Bus(kind) => *kind,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// Host side of the link, see the [module documentation](self).
+#[derive(Debug)]
+pub struct Sgp30Remote<IO> {
+    io: IO,
+}
+
+impl<IO> Sgp30Remote<IO> {
+    /// Create a new remote transport communicating over `io`.
+    pub const fn new(io: IO) -> Self {
+        Self { io }
+    }
+
+    /// Destroy the remote transport, return the stream.
+    pub fn destroy(self) -> IO {
+        self.io
+    }
+}
+
+impl<IO: Read + Write> Sgp30Remote<IO> {
+    /// Send a request and receive the response into `buf`.
+    fn transfer(
+        &mut self,
+        op: u8,
+        address: u8,
+        data: &[u8],
+        buf: &mut [u8],
+    ) -> Result<(), RemoteError<IO::Error>> {
+        if data.len() > MAX_TRANSFER_LEN || buf.len() > MAX_TRANSFER_LEN {
+            return Err(RemoteError::InvalidRequest);
+        }
+        // Both lengths fit into a byte
+        let header = [op, address, data.len() as u8, buf.len() as u8];
+        self.io.write_all(&header).map_err(RemoteError::Io)?;
+        self.io.write_all(data).map_err(RemoteError::Io)?;
+        self.io.flush().map_err(RemoteError::Io)?;
+
+        let mut status = [0];
+        self.io.read_exact(&mut status)?;
+        match status[0] {
+            STATUS_OK => Ok(self.io.read_exact(buf)?),
+            STATUS_INVALID_REQUEST => Err(RemoteError::InvalidRequest),
+            code => Err(RemoteError::Bus(decode_kind(code))),
+        }
+    }
+}
+
+impl<IO: Read + Write> Sgp30Transport for Sgp30Remote<IO> {
+    type Error = RemoteError<IO::Error>;
+
+    fn write(&mut self, address: u8, data: &[u8]) -> Result<(), Self::Error> {
+        self.transfer(OP_WRITE, address, data, &mut [])
+    }
+
+    fn read(&mut self, address: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.transfer(OP_READ, address, &[], buf)
+    }
+
+    fn write_read(&mut self, address: u8, data: &[u8], buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.transfer(OP_WRITE_READ, address, data, buf)
+    }
+
+    fn is_address_nack(error: &Self::Error) -> bool {
+        matches!(
+            error,
+            RemoteError::Bus(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
+        )
+    }
+}
+
+/// Errors of the device side of the link.
+///
+/// Bus errors are reported to the host and don't cause an error here.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum AgentError<E> {
+    /// Error of the underlying stream
+    #[cfg_attr(feature = "std", error("I/O error: {0:?}"))]
+    Io(E),
+    /// The stream ended in the middle of a request
+    #[cfg_attr(feature = "std", error("unexpected end of stream"))]
+    UnexpectedEof,
+    /// An invalid request was received and rejected
+    #[cfg_attr(feature = "std", error("invalid request"))]
+    InvalidRequest,
+}
+
+impl<E> From<ReadExactError<E>> for AgentError<E> {
+    fn from(err: ReadExactError<E>) -> Self {
+        match err {
+            ReadExactError::UnexpectedEof => AgentError::UnexpectedEof,
+            ReadExactError::Other(e) => AgentError::Io(e),
+        }
+    }
+}
+
+/// Device side of the link, see the [module documentation](self).
+#[derive(Debug)]
+pub struct Sgp30Agent<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C> Sgp30Agent<I2C> {
+    /// Create a new agent executing requests on the `i2c` bus.
+    pub const fn new(i2c: I2C) -> Self {
+        Self { i2c }
+    }
+
+    /// Destroy the agent, return the I²C bus instance.
+    pub fn destroy(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C: I2c> Sgp30Agent<I2C> {
+    /// Receive a single request from `io`, execute it and send the response.
+    ///
+    /// Call this in a loop to serve the host. After an invalid request, the
+    /// stream might be out of sync and should be reset.
+    pub fn serve<IO: Read + Write>(&mut self, io: &mut IO) -> Result<(), AgentError<IO::Error>> {
+        let mut header = [0; 4];
+        io.read_exact(&mut header)?;
+        let [op, address, write_len, read_len] = header;
+        let (write_len, read_len) = (usize::from(write_len), usize::from(read_len));
+        if !matches!(op, OP_WRITE | OP_READ | OP_WRITE_READ)
+            || write_len > MAX_TRANSFER_LEN
+            || read_len > MAX_TRANSFER_LEN
+        {
+            io.write_all(&[STATUS_INVALID_REQUEST])
+                .map_err(AgentError::Io)?;
+            io.flush().map_err(AgentError::Io)?;
+            return Err(AgentError::InvalidRequest);
+        }
+
+        let mut data = [0; MAX_TRANSFER_LEN];
+        let data = &mut data[..write_len];
+        io.read_exact(data)?;
+        let mut buf = [0; MAX_TRANSFER_LEN];
+        let buf = &mut buf[..read_len];
+
+        let result = match op {
+            OP_WRITE => self.i2c.write(address, data),
+            OP_READ => self.i2c.read(address, buf),
+            _ => self.i2c.write_read(address, data, buf),
+        };
+        match result {
+            Ok(()) => {
+                io.write_all(&[STATUS_OK]).map_err(AgentError::Io)?;
+                io.write_all(buf).map_err(AgentError::Io)?;
+            }
+            Err(e) => {
+                io.write_all(&[encode_kind(i2c::Error::kind(&e))])
+                    .map_err(AgentError::Io)?;
+            }
+        }
+        io.flush().map_err(AgentError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::{
+        delay::NoopDelay,
+        i2c::{Mock as I2cMock, Transaction},
+    };
+    use super::*;
+    use crate::{commands::CommandId, Error, Sgp30};
+
+    /// An in-memory stream.
+    #[derive(Default)]
+    struct Pipe {
+        rx: VecDeque<u8>,
+        tx: Vec<u8>,
+    }
+
+    impl embedded_io::ErrorType for Pipe {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let n = buf.len().min(self.rx.len());
+            for (byte, rx) in buf.iter_mut().zip(self.rx.drain(..n)) {
+                *byte = rx;
+            }
+            Ok(n)
+        }
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.tx.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    /// A host stream served synchronously by an agent on every flush.
+    struct Loopback {
+        agent: Sgp30Agent<I2cMock>,
+        host: Pipe,
+    }
+
+    impl embedded_io::ErrorType for Loopback {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.host.read(buf)
+        }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.host.write(buf)
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            let mut device = Pipe {
+                rx: self.host.tx.drain(..).collect(),
+                tx: Vec::new(),
+            };
+            self.agent.serve(&mut device).unwrap();
+            assert!(device.rx.is_empty());
+            self.host.rx.extend(device.tx);
+            Ok(())
+        }
+    }
+
+    /// Test the driver on top of a remote transport
+    #[test]
+    fn loopback() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            Transaction::write_read(
+                0x58,
+                CommandId::GetFeatureSet.as_bytes()[..].into(),
+                vec![0x00, 0x22, 0x65],
+            ),
+        ];
+        let io = Loopback {
+            agent: Sgp30Agent::new(I2cMock::new(&expectations)),
+            host: Pipe::default(),
+        };
+        let mut sgp = Sgp30::new(Sgp30Remote::new(io), 0x58, NoopDelay);
+        sgp.init().unwrap();
+        assert_eq!(sgp.measure().unwrap().co2eq_ppm, 0x1234);
+        sgp.set_combined_reads(true);
+        assert_eq!(sgp.get_feature_set().unwrap().product_version, 0x22);
+        sgp.destroy().destroy().agent.destroy().done();
+    }
+
+    /// Test that bus errors are passed to the host
+    #[test]
+    fn bus_error() {
+        let expectations =
+            [
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into())
+                    .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+            ];
+        let io = Loopback {
+            agent: Sgp30Agent::new(I2cMock::new(&expectations)),
+            host: Pipe::default(),
+        };
+        let mut sgp = Sgp30::new(Sgp30Remote::new(io), 0x58, NoopDelay);
+        assert!(matches!(
+            sgp.init(),
+            Err(Error::DeviceNotResponding {
+                command: CommandId::InitAirQuality,
+                source: RemoteError::Bus(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+            })
+        ));
+        sgp.destroy().destroy().agent.destroy().done();
+    }
+
+    /// Test that invalid requests are rejected by the agent
+    #[test]
+    fn invalid_request() {
+        let mut agent = Sgp30Agent::new(I2cMock::new(&[]));
+        let mut io = Pipe {
+            rx: [0x04, 0x58, 0x00, 0x00].into(),
+            tx: Vec::new(),
+        };
+        assert_eq!(agent.serve(&mut io), Err(AgentError::InvalidRequest));
+        assert_eq!(io.tx, [STATUS_INVALID_REQUEST]);
+
+        let mut io = Pipe {
+            rx: [OP_READ, 0x58, 0x00].into(),
+            tx: Vec::new(),
+        };
+        assert_eq!(agent.serve(&mut io), Err(AgentError::UnexpectedEof));
+        agent.destroy().done();
+
+        let mut remote = Sgp30Remote::new(Pipe {
+            rx: [STATUS_INVALID_REQUEST].into(),
+            tx: Vec::new(),
+        });
+        assert_eq!(
+            remote.read(0x58, &mut [0; 3]),
+            Err(RemoteError::InvalidRequest)
+        );
+        assert_eq!(remote.destroy().tx, [OP_READ, 0x58, 0x00, 0x03]);
+    }
+}
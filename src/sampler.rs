@@ -0,0 +1,781 @@
+//! Periodic sampling of air quality measurements.
+//!
+//! After initializing the air quality measurement, the SGP30 must be queried
+//! in regular intervals of 1 s. The [`Sampler`] wraps a blocking [`Sgp30`]
+//! driver and yields measurements at that cadence as an [`Iterator`].
+//!
+//! ```no_run
+//! use linux_embedded_hal::{Delay, I2cdev};
+//! use sgp30::{sampler::Sampler, Sgp30};
+//!
+//! let dev = I2cdev::new("/dev/i2c-1").unwrap();
+//! let sgp = Sgp30::new(dev, 0x58, Delay);
+//! for measurement in Sampler::new(sgp).take(60) {
+//!     println!("{:?}", measurement.unwrap());
+//! }
+//! ```
+//!
+//! The cadence is based on the driver's delay: Before every measurement but
+//! the first, the sampler waits for 1 s minus the maximum duration of the
+//! measurement command. Any additional time spent between two calls to
+//! [`next()`](Iterator::next) is added on top of that.
+//!
+//! If a [`Clock`] is provided through [`Sampler::with_clock()`], the sampler
+//! yields [`TimestampedMeasurement`]s instead. [`Sampler::with_seq()`] numbers
+//! the measurements as [`Sample`]s, which allows consumers on the other end
+//! of a lossy link to detect lost or duplicated measurements.
+//!
+//! To filter, correct or aggregate the measurements, chain processors from
+//! the [`pipeline`](crate::pipeline) module onto the sampler.
+//!
+//! With the `embedded-hal-async` feature, the [`StaggeredSampler`] drives
+//! several async drivers with staggered offsets inside every 1 s interval,
+//! and [`Sgp30Async::run()`](crate::Sgp30Async::run) runs the whole sampling
+//! loop of a single driver, configured through a [`RunConfig`].
+//!
+//! With the `embassy` feature, the samplers can publish every measurement
+//! into an [`embassy_sync::watch::Watch`], from which any number of tasks
+//! (e.g. a display, a radio or a control loop) can read the latest value
+//! without taking part in the measurement loop:
+//!
+//! ```
+//! # #[cfg(feature = "embassy")]
+//! # {
+//! use embassy_sync::{blocking_mutex::raw::NoopRawMutex, watch::Watch};
+//! use embedded_hal_mock::eh1::{
+//!     delay::NoopDelay,
+//!     i2c::{Mock as I2cMock, Transaction},
+//! };
+//! use sgp30::{sampler::Sampler, Measurement, Sgp30};
+//!
+//! # let mock = I2cMock::new(&[
+//! #     Transaction::write(0x58, vec![0x20, 0x03]),
+//! #     Transaction::write(0x58, vec![0x20, 0x08]),
+//! #     Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+//! # ]);
+//! let latest = Watch::<NoopRawMutex, Measurement, 2>::new();
+//! let mut display = latest.receiver().unwrap();
+//!
+//! let sgp = Sgp30::new(mock, 0x58, NoopDelay);
+//! let mut sampler = Sampler::new(sgp).publish_to(&latest);
+//! sampler.next().unwrap().unwrap();
+//! assert_eq!(display.try_changed(), Some(Measurement::new(400, 0)));
+//! # sampler.into_inner().into_inner().destroy().done();
+//! # }
+//! ```
+
+#[cfg(feature = "embassy")]
+use embassy_sync::{
+    blocking_mutex::raw::RawMutex,
+    watch::{DynSender, Watch},
+};
+use embedded_hal::delay::DelayNs;
+
+use crate::{commands, transport::Sgp30Transport, Error, Measurement, Sgp30};
+
+pub use crate::timings::SAMPLE_INTERVAL_US;
+
+/// Waits for the next sample interval in a blocking measurement loop.
+///
+/// The first call to [`wait()`](Self::wait) returns immediately, every
+/// following call waits for the rest of the interval, assuming that the
+/// measurement commands in between took `busy_us`.
+#[derive(Debug)]
+pub(crate) struct Cadence {
+    busy_us: u32,
+    started: bool,
+}
+
+impl Cadence {
+    pub(crate) const fn new(busy_us: u32) -> Self {
+        Self {
+            busy_us,
+            started: false,
+        }
+    }
+
+    pub(crate) fn wait(&mut self, delay: &mut impl DelayNs) {
+        if self.started {
+            delay.delay_us(SAMPLE_INTERVAL_US.saturating_sub(self.busy_us));
+        }
+        self.started = true;
+    }
+}
+
+/// A source of timestamps.
+///
+/// This is tick-count based and does not make any assumptions about the
+/// resolution or epoch of the timestamps, so it can be implemented on top of
+/// any hardware timer or RTOS tick counter.
+pub trait Clock {
+    /// The timestamp type, e.g. a `u32` or `u64` tick count.
+    type Instant: Copy;
+
+    /// Return the current time.
+    fn now(&mut self) -> Self::Instant;
+}
+
+/// A [`Clock`] returning the wall-clock time of the host system.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SystemTimeClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemTimeClock {
+    type Instant = std::time::SystemTime;
+
+    fn now(&mut self) -> Self::Instant {
+        std::time::SystemTime::now()
+    }
+}
+
+/// A measurement together with the time at which it was taken.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TimestampedMeasurement<T> {
+    /// The time at which the measurement was completed.
+    pub at: T,
+    /// The measurement.
+    pub measurement: Measurement,
+}
+
+impl<T> TimestampedMeasurement<T> {
+    /// Convert the timestamp into another representation.
+    ///
+    /// This can for example be used to convert the `SystemTime` timestamps of
+    /// the `SystemTimeClock` to `chrono::DateTime<Utc>` or
+    /// `time::OffsetDateTime`, both of which implement `From<SystemTime>`.
+    pub fn convert<U: From<T>>(self) -> TimestampedMeasurement<U> {
+        TimestampedMeasurement {
+            at: U::from(self.at),
+            measurement: self.measurement,
+        }
+    }
+}
+
+/// An iterator yielding air quality measurements at 1 s intervals.
+///
+/// The air quality measurement is initialized (if it wasn't already) when
+/// the first measurement is requested. If the initialization fails, it is
+/// retried one sample interval later, with the next measurement. The
+/// iterator never ends.
+#[derive(Debug)]
+pub struct Sampler<I2C, D> {
+    sgp: Sgp30<I2C, D>,
+    cadence: Cadence,
+}
+
+impl<I2C, D> Sampler<I2C, D>
+where
+    I2C: Sgp30Transport,
+    D: DelayNs,
+{
+    /// Create a new sampler wrapping the `sgp` driver.
+    pub fn new(sgp: Sgp30<I2C, D>) -> Self {
+        Self {
+            sgp,
+            cadence: Cadence::new(commands::MEASURE_AIR_QUALITY.max_duration_us),
+        }
+    }
+
+    /// Return a timestamping sampler using the provided `clock`.
+    pub fn with_clock<C: Clock>(self, clock: C) -> Timestamped<Self, C> {
+        Timestamped::new(self, clock)
+    }
+
+    /// Return a sampler publishing every measurement into `watch`.
+    #[cfg(feature = "embassy")]
+    pub fn publish_to<M, const N: usize>(
+        self,
+        watch: &Watch<M, Measurement, N>,
+    ) -> Published<'_, Self>
+    where
+        M: RawMutex,
+    {
+        Published::new(self, watch.dyn_sender())
+    }
+
+    /// Return a sampler numbering the measurements, starting at 0.
+    pub fn with_seq(self) -> Sequenced<Self> {
+        Sequenced::new(self, 0)
+    }
+
+    /// Return a mutable reference to the wrapped driver.
+    ///
+    /// Note that any time spent using the driver in between two samples
+    /// delays the next sample.
+    pub fn driver_mut(&mut self) -> &mut Sgp30<I2C, D> {
+        &mut self.sgp
+    }
+
+    /// Return the most recent successful measurement, without communicating
+    /// with the sensor.
+    ///
+    /// See [`Sgp30::last_measurement()`].
+    pub fn last_measurement(&self) -> Option<Measurement> {
+        self.sgp.last_measurement()
+    }
+
+    /// Destroy the sampler, return the wrapped driver.
+    pub fn into_inner(self) -> Sgp30<I2C, D> {
+        self.sgp
+    }
+
+    /// Wait for the next sample interval and take a measurement.
+    pub fn sample(&mut self) -> Result<Measurement, Error<I2C::Error>> {
+        // Wait for the next interval even if the initialization fails, so
+        // that a missing sensor isn't polled in a busy loop
+        self.cadence.wait(&mut self.sgp.delay);
+        self.sgp.init()?;
+        self.sgp.measure()
+    }
+}
+
+impl<I2C, D> Iterator for Sampler<I2C, D>
+where
+    I2C: Sgp30Transport,
+    D: DelayNs,
+{
+    type Item = Result<Measurement, Error<I2C::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.sample())
+    }
+}
+
+/// An iterator adapter attaching timestamps to measurements.
+///
+/// See [`Sampler::with_clock()`].
+#[derive(Debug)]
+pub struct Timestamped<S, C> {
+    inner: S,
+    clock: C,
+}
+
+impl<S, C> Timestamped<S, C> {
+    /// Wrap the measurement iterator `inner`, timestamping every measurement
+    /// using `clock`.
+    pub fn new(inner: S, clock: C) -> Self {
+        Self { inner, clock }
+    }
+
+    /// Return a mutable reference to the wrapped iterator.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Destroy the adapter, return the wrapped iterator and clock.
+    pub fn into_parts(self) -> (S, C) {
+        (self.inner, self.clock)
+    }
+}
+
+impl<S, C, E> Iterator for Timestamped<S, C>
+where
+    S: Iterator<Item = Result<Measurement, E>>,
+    C: Clock,
+{
+    type Item = Result<TimestampedMeasurement<C::Instant>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.inner.next()?;
+        let at = self.clock.now();
+        Some(result.map(|measurement| TimestampedMeasurement { at, measurement }))
+    }
+}
+
+/// A measurement together with its sequence number.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Sample {
+    /// The sequence number, incremented by one for every measurement and
+    /// wrapping around after `u32::MAX`.
+    pub seq: u32,
+    /// The measurement.
+    pub measurement: Measurement,
+}
+
+/// An iterator adapter attaching sequence numbers to measurements.
+///
+/// Failed measurements don't use up a sequence number, so a gap in the
+/// sequence numbers seen by a consumer always means that a measurement was
+/// lost on the way. See [`Sampler::with_seq()`].
+#[derive(Debug)]
+pub struct Sequenced<S> {
+    inner: S,
+    next_seq: u32,
+}
+
+impl<S> Sequenced<S> {
+    /// Wrap the measurement iterator `inner`, numbering the measurements
+    /// starting at `first_seq`.
+    ///
+    /// Pass the last sequence number plus one when restarting the
+    /// measurements, e.g. after a reset, to keep the sequence numbers
+    /// unique.
+    pub fn new(inner: S, first_seq: u32) -> Self {
+        Self {
+            inner,
+            next_seq: first_seq,
+        }
+    }
+
+    /// Return the sequence number of the next measurement.
+    pub fn next_seq(&self) -> u32 {
+        self.next_seq
+    }
+
+    /// Return a mutable reference to the wrapped iterator.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Destroy the adapter, return the wrapped iterator.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, E> Iterator for Sequenced<S>
+where
+    S: Iterator<Item = Result<Measurement, E>>,
+{
+    type Item = Result<Sample, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let measurement = match self.inner.next()? {
+            Ok(measurement) => measurement,
+            Err(e) => return Some(Err(e)),
+        };
+        let seq = self.next_seq;
+        self.next_seq = seq.wrapping_add(1);
+        Some(Ok(Sample { seq, measurement }))
+    }
+}
+
+/// An iterator adapter publishing measurements into a [`Watch`].
+///
+/// See [`Sampler::publish_to()`].
+#[cfg(feature = "embassy")]
+pub struct Published<'a, S> {
+    inner: S,
+    sender: DynSender<'a, Measurement>,
+}
+
+#[cfg(feature = "embassy")]
+impl<'a, S> Published<'a, S> {
+    /// Wrap the measurement iterator `inner`, sending every measurement to
+    /// `sender`.
+    pub fn new(inner: S, sender: DynSender<'a, Measurement>) -> Self {
+        Self { inner, sender }
+    }
+
+    /// Return a mutable reference to the wrapped iterator.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Destroy the adapter, return the wrapped iterator.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+#[cfg(feature = "embassy")]
+impl<S, E> Iterator for Published<'_, S>
+where
+    S: Iterator<Item = Result<Measurement, E>>,
+{
+    type Item = Result<Measurement, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.inner.next()?;
+        if let Ok(measurement) = &result {
+            self.sender.send(*measurement);
+        }
+        Some(result)
+    }
+}
+
+/// Parameters of the sampling loop of
+/// [`Sgp30Async::run_with()`](crate::Sgp30Async::run_with).
+#[cfg(feature = "embedded-hal-async")]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct RunConfig {
+    /// The number of measurements after initialization which are discarded
+    /// if they have the fixed values of
+    /// [`Measurement::WARM_UP`](crate::Measurement::WARM_UP).
+    pub warm_up_samples: u32,
+    /// How often a failed measurement is retried in the following sample
+    /// intervals before it is reported as an error.
+    pub max_retries: u8,
+    /// The interval between two baseline reads in seconds, 0 to never read
+    /// the baseline.
+    pub baseline_interval_s: u32,
+}
+
+#[cfg(feature = "embedded-hal-async")]
+impl RunConfig {
+    /// Default configuration: Discard the first 15 measurements, retry
+    /// failed measurements twice and read the baseline every hour.
+    pub const DEFAULT: Self = Self {
+        warm_up_samples: crate::timings::INIT_PHASE_S,
+        max_retries: 2,
+        baseline_interval_s: 60 * 60,
+    };
+}
+
+#[cfg(feature = "embedded-hal-async")]
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// An event of the sampling loop of
+/// [`Sgp30Async::run_with()`](crate::Sgp30Async::run_with).
+#[cfg(feature = "embedded-hal-async")]
+#[derive(Debug)]
+pub enum RunEvent<E> {
+    /// A new measurement.
+    Measurement(Measurement),
+    /// The periodically read baseline.
+    Baseline(crate::Baseline),
+    /// A measurement, the initialization or a baseline read failed, including
+    /// all retries.
+    Error(Error<E>),
+}
+
+/// Samples several async drivers at 1 s intervals, with their measurements
+/// staggered evenly across every interval.
+///
+/// With `n` sensors, the interval is divided into `n` slots of `1 s / n`,
+/// and every sensor is measured at the start of its own slot. This way the
+/// measurements of sensors on a shared bus never collide, while every sensor
+/// keeps its own 1 s cadence.
+///
+/// The cadence is based on the `delay` passed to [`new()`](Self::new), in the
+/// same way as for the [`Sampler`]. Sensors which aren't initialized yet are
+/// initialized right before their first measurement.
+#[cfg(feature = "embedded-hal-async")]
+#[derive(Debug)]
+pub struct StaggeredSampler<'a, I2C, D, T> {
+    sensors: &'a mut [crate::Sgp30Async<I2C, D>],
+    delay: T,
+    slot_us: u32,
+    next: usize,
+    started: bool,
+}
+
+#[cfg(feature = "embedded-hal-async")]
+impl<'a, I2C, D, T> StaggeredSampler<'a, I2C, D, T>
+where
+    I2C: embedded_hal_async::i2c::I2c,
+    D: embedded_hal_async::delay::DelayNs,
+    T: embedded_hal_async::delay::DelayNs,
+{
+    /// Create a new sampler for the `sensors`, using `delay` to wait for the
+    /// next slot.
+    ///
+    /// Returns `None` if `sensors` is empty, or if there are so many sensors
+    /// that their slots are shorter than a measurement.
+    pub fn new(sensors: &'a mut [crate::Sgp30Async<I2C, D>], delay: T) -> Option<Self> {
+        let count = u32::try_from(sensors.len()).ok().filter(|&n| n > 0)?;
+        let slot_us = SAMPLE_INTERVAL_US / count;
+        if slot_us < commands::MEASURE_AIR_QUALITY.max_duration_us {
+            return None;
+        }
+        Some(Self {
+            sensors,
+            delay,
+            slot_us,
+            next: 0,
+            started: false,
+        })
+    }
+
+    /// Return the length of the slot of every sensor in microseconds.
+    pub fn slot_us(&self) -> u32 {
+        self.slot_us
+    }
+
+    /// Wait for the next slot and take a measurement with its sensor.
+    ///
+    /// Returns the index of the sensor in the slice passed to
+    /// [`new()`](Self::new) together with the measurement result.
+    pub async fn next(&mut self) -> (usize, Result<Measurement, Error<I2C::Error>>) {
+        if self.started {
+            self.delay
+                .delay_us(self.slot_us() - commands::MEASURE_AIR_QUALITY.max_duration_us)
+                .await;
+        }
+        self.started = true;
+
+        let index = self.next;
+        self.next = (index + 1) % self.sensors.len();
+        let sensor = &mut self.sensors[index];
+        if !sensor.is_initialized() {
+            if let Err(e) = sensor.init().await {
+                return (index, Err(e));
+            }
+        }
+        (index, sensor.measure().await)
+    }
+
+    /// Like [`next()`](Self::next), but additionally publish a successful
+    /// measurement to the sender with the same index as the sensor.
+    ///
+    /// Sensors without a corresponding sender are measured, but their
+    /// measurements are not published.
+    #[cfg(feature = "embassy")]
+    pub async fn next_published(
+        &mut self,
+        senders: &[DynSender<'_, Measurement>],
+    ) -> (usize, Result<Measurement, Error<I2C::Error>>) {
+        let (index, result) = self.next().await;
+        if let (Ok(measurement), Some(sender)) = (&result, senders.get(index)) {
+            sender.send(*measurement);
+        }
+        (index, result)
+    }
+
+    /// Destroy the sampler, return the delay.
+    pub fn into_delay(self) -> T {
+        self.delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock as hal;
+
+    use self::hal::eh1::{
+        delay::NoopDelay,
+        i2c::{Mock as I2cMock, Transaction},
+    };
+    use super::*;
+    use crate::commands::CommandId;
+
+    /// A clock counting the number of calls.
+    struct CountingClock(u32);
+
+    impl Clock for CountingClock {
+        type Instant = u32;
+
+        fn now(&mut self) -> u32 {
+            self.0 += 1;
+            self.0
+        }
+    }
+
+    fn expectations() -> [Transaction; 5] {
+        [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+        ]
+    }
+
+    #[test]
+    fn sampler() {
+        let mock = I2cMock::new(&expectations());
+        let sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        let mut sampler = Sampler::new(sgp);
+        let first = sampler.next().unwrap().unwrap();
+        assert_eq!(first.co2eq_ppm, 400);
+        assert_eq!(first.tvoc_ppb, 0);
+        let second = sampler.next().unwrap().unwrap();
+        assert_eq!(second.co2eq_ppm, 4_660);
+        assert_eq!(second.tvoc_ppb, 54_274);
+        assert_eq!(sampler.last_measurement(), Some(second));
+        sampler.into_inner().destroy().done();
+    }
+
+    #[test]
+    fn sampler_init_failed() {
+        use embedded_hal::i2c::ErrorKind;
+
+        use self::hal::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+
+        let init = || Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into());
+        let mock = I2cMock::new(&[
+            init().with_error(ErrorKind::Other),
+            init(),
+            Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+        ]);
+        let mut delay = CheckedDelay::new(&[
+            DelayTransaction::delay_us(988_000),
+            DelayTransaction::delay_us(commands::INIT_AIR_QUALITY.max_duration_us),
+            DelayTransaction::delay_us(commands::MEASURE_AIR_QUALITY.max_duration_us),
+        ]);
+        let mut sampler = Sampler::new(Sgp30::new(mock, 0x58, &mut delay));
+        assert!(matches!(sampler.next(), Some(Err(Error::I2cWrite { .. }))));
+        assert_eq!(sampler.next().unwrap().unwrap(), Measurement::WARM_UP);
+        sampler.into_inner().destroy().done();
+        delay.done();
+    }
+
+    #[test]
+    fn timestamped() {
+        let mock = I2cMock::new(&expectations());
+        let sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        let mut sampler = Sampler::new(sgp).with_clock(CountingClock(0));
+        let first = sampler.next().unwrap().unwrap();
+        assert_eq!(first.at, 1);
+        assert_eq!(first.measurement.co2eq_ppm, 400);
+        let second = sampler.next().unwrap().unwrap();
+        assert_eq!(second.at, 2);
+        assert_eq!(second.measurement.co2eq_ppm, 4_660);
+        let (sampler, _clock) = sampler.into_parts();
+        sampler.into_inner().destroy().done();
+    }
+
+    #[test]
+    fn sequenced() {
+        let results: [Result<Measurement, ()>; 4] = [
+            Ok(Measurement::new(400, 0)),
+            Err(()),
+            Ok(Measurement::new(410, 1)),
+            Ok(Measurement::new(420, 2)),
+        ];
+        let mut samples = Sequenced::new(results.into_iter(), u32::MAX);
+        assert_eq!(
+            samples.next(),
+            Some(Ok(Sample {
+                seq: u32::MAX,
+                measurement: Measurement::new(400, 0)
+            }))
+        );
+        assert_eq!(samples.next(), Some(Err(())));
+        assert_eq!(samples.next_seq(), 0);
+        assert_eq!(samples.next().unwrap().unwrap().seq, 0);
+        assert_eq!(samples.next().unwrap().unwrap().seq, 1);
+        assert_eq!(samples.next(), None);
+
+        let mock = I2cMock::new(&expectations());
+        let sgp = Sgp30::new(mock, 0x58, NoopDelay);
+        let mut sampler = Sampler::new(sgp).with_seq();
+        assert_eq!(sampler.next().unwrap().unwrap().seq, 0);
+        let second = sampler.next().unwrap().unwrap();
+        assert_eq!(second.seq, 1);
+        assert_eq!(second.measurement.co2eq_ppm, 4_660);
+        sampler.into_inner().into_inner().destroy().done();
+    }
+
+    #[test]
+    fn convert() {
+        let sample = TimestampedMeasurement {
+            at: 42u32,
+            measurement: Measurement {
+                co2eq_ppm: 400,
+                tvoc_ppb: 0,
+            },
+        };
+        let converted: TimestampedMeasurement<u64> = sample.clone().convert();
+        assert_eq!(converted.at, 42u64);
+        assert_eq!(converted.measurement, sample.measurement);
+    }
+
+    #[cfg(feature = "embedded-hal-async")]
+    #[test]
+    fn staggered() {
+        use self::hal::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+        use crate::Sgp30Async;
+
+        futures_executor::block_on(async {
+            let first = I2cMock::new(&[
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            ]);
+            let second = I2cMock::new(&[
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+            ]);
+            let mut sensors = [
+                Sgp30Async::new(first, 0x58, NoopDelay),
+                Sgp30Async::new(second, 0x58, NoopDelay),
+            ];
+            let delay = CheckedDelay::new(&[
+                DelayTransaction::async_delay_us(488_000),
+                DelayTransaction::async_delay_us(488_000),
+            ]);
+            let mut none: [Sgp30Async<I2cMock, NoopDelay>; 0] = [];
+            assert!(StaggeredSampler::new(&mut none, NoopDelay).is_none());
+            let mut sampler = StaggeredSampler::new(&mut sensors, delay).unwrap();
+            assert_eq!(sampler.slot_us(), 500_000);
+
+            let (index, result) = sampler.next().await;
+            assert_eq!(index, 0);
+            assert_eq!(result.unwrap().co2eq_ppm, 400);
+            let (index, result) = sampler.next().await;
+            assert_eq!(index, 1);
+            assert_eq!(result.unwrap().co2eq_ppm, 400);
+            let (index, result) = sampler.next().await;
+            assert_eq!(index, 0);
+            assert_eq!(result.unwrap().co2eq_ppm, 4_660);
+
+            sampler.into_delay().done();
+            for sensor in sensors {
+                sensor.destroy().done();
+            }
+        })
+    }
+
+    #[cfg(feature = "embassy")]
+    #[test]
+    fn staggered_published() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+
+        use crate::Sgp30Async;
+
+        futures_executor::block_on(async {
+            let first = I2cMock::new(&[
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+            ]);
+            let second = I2cMock::new(&[
+                Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+                Transaction::write(0x58, CommandId::MeasureAirQuality.as_bytes()[..].into()),
+                Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+            ]);
+            let mut sensors = [
+                Sgp30Async::new(first, 0x58, NoopDelay),
+                Sgp30Async::new(second, 0x58, NoopDelay),
+            ];
+            let watch = Watch::<NoopRawMutex, Measurement, 1>::new();
+            let mut receiver = watch.receiver().unwrap();
+            // Only the first sensor is published
+            let senders = [watch.dyn_sender()];
+            let mut sampler = StaggeredSampler::new(&mut sensors, NoopDelay).unwrap();
+
+            let (index, _) = sampler.next_published(&senders).await;
+            assert_eq!(index, 0);
+            assert_eq!(
+                receiver.try_changed(),
+                Some(Measurement::new(4_660, 54_274))
+            );
+            let (index, _) = sampler.next_published(&senders).await;
+            assert_eq!(index, 1);
+            assert_eq!(receiver.try_changed(), None);
+
+            for sensor in sensors {
+                sensor.destroy().done();
+            }
+        })
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn system_time_clock() {
+        let mut clock = SystemTimeClock;
+        let first = clock.now();
+        let second = clock.now();
+        assert!(second >= first);
+    }
+}
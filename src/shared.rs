@@ -0,0 +1,140 @@
+//! A driver shared between tasks.
+//!
+//! The driver methods take `&mut self`, so a single task has to own the
+//! driver. When another task needs access as well, e.g. a command handler
+//! reading the baseline while the measurement loop keeps measuring, the
+//! [`SharedSgp30`] provides interior mutability: It can be accessed through
+//! a shared reference from any task or thread, and hands out the driver for
+//! the duration of a closure:
+//!
+//! ```
+//! use embedded_hal_mock::eh1::{
+//!     delay::NoopDelay,
+//!     i2c::{Mock as I2cMock, Transaction},
+//! };
+//! use sgp30::{shared::SharedSgp30, Sgp30};
+//!
+//! # let mock = I2cMock::new(&[
+//! #     Transaction::write(0x58, vec![0x20, 0x03]),
+//! #     Transaction::write(0x58, vec![0x20, 0x08]),
+//! #     Transaction::read(0x58, vec![0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]),
+//! # ]);
+//! let sgp = SharedSgp30::new(Sgp30::new(mock, 0x58, NoopDelay));
+//!
+//! // In the measurement loop
+//! sgp.with(|sgp| sgp.init()).unwrap();
+//! let measurement = sgp.with(|sgp| sgp.measure()).unwrap();
+//!
+//! // In the command handler
+//! assert_eq!(sgp.with(|sgp| sgp.last_measurement()), Some(measurement));
+//! # sgp.into_inner().destroy().done();
+//! ```
+//!
+//! With the `std` feature, the driver is protected by a
+//! [`std::sync::Mutex`], so other threads block while a command is
+//! executed. Otherwise, it is protected by a [`critical_section::Mutex`],
+//! which blocks interrupts for the duration of every command, including the
+//! command's execution time (e.g. 12 ms for a measurement). To keep a
+//! driver in a `static` which is only initialized at runtime, see
+//! [`StaticSgp30`](crate::global::StaticSgp30).
+//!
+//! This module requires the `critical-section` or the `std` feature.
+
+#[cfg(not(feature = "std"))]
+use core::cell::RefCell;
+
+use crate::Sgp30;
+
+/// A driver which can be accessed through a shared reference, see the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct SharedSgp30<I2C, D> {
+    #[cfg(feature = "std")]
+    inner: std::sync::Mutex<Sgp30<I2C, D>>,
+    #[cfg(not(feature = "std"))]
+    inner: critical_section::Mutex<RefCell<Sgp30<I2C, D>>>,
+}
+
+impl<I2C, D> SharedSgp30<I2C, D> {
+    /// Wrap the `sgp` driver.
+    pub const fn new(sgp: Sgp30<I2C, D>) -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            inner: std::sync::Mutex::new(sgp),
+            #[cfg(not(feature = "std"))]
+            inner: critical_section::Mutex::new(RefCell::new(sgp)),
+        }
+    }
+
+    /// Call `f` with exclusive access to the driver, return its result.
+    ///
+    /// If a previous call panicked while using the driver, the driver is
+    /// handed out anyway, i.e. the poisoning of the mutex is ignored.
+    ///
+    /// # Panics
+    ///
+    /// Without the `std` feature, if called from within `f`. With the `std`
+    /// feature, calling it from within `f` deadlocks.
+    pub fn with<R>(&self, f: impl FnOnce(&mut Sgp30<I2C, D>) -> R) -> R {
+        #[cfg(feature = "std")]
+        {
+            let mut sgp = self
+                .inner
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            f(&mut sgp)
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            critical_section::with(|cs| f(&mut self.inner.borrow_ref_mut(cs)))
+        }
+    }
+
+    /// Destroy the wrapper, return the driver.
+    pub fn into_inner(self) -> Sgp30<I2C, D> {
+        #[cfg(feature = "std")]
+        {
+            self.inner
+                .into_inner()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            self.inner.into_inner().into_inner()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::{
+        delay::NoopDelay,
+        i2c::{Mock as I2cMock, Transaction},
+    };
+
+    use super::*;
+    use crate::{commands::CommandId, Baseline};
+
+    #[test]
+    fn shared_between_threads() {
+        let expectations = [
+            Transaction::write(0x58, CommandId::InitAirQuality.as_bytes()[..].into()),
+            Transaction::write(0x58, CommandId::GetBaseline.as_bytes()[..].into()),
+            Transaction::read(0x58, vec![0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]),
+        ];
+        let sgp = SharedSgp30::new(Sgp30::new(I2cMock::new(&expectations), 0x58, NoopDelay));
+        std::thread::scope(|s| {
+            s.spawn(|| sgp.with(|sgp| sgp.init()).unwrap())
+                .join()
+                .unwrap();
+        });
+        assert!(sgp.with(|sgp| sgp.is_initialized()));
+        let baseline = std::thread::scope(|s| {
+            s.spawn(|| sgp.with(|sgp| sgp.get_baseline()).unwrap())
+                .join()
+                .unwrap()
+        });
+        assert_eq!(baseline, Baseline::new(4_660, 54_274));
+        sgp.into_inner().destroy().done();
+    }
+}
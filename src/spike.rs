@@ -0,0 +1,230 @@
+//! Detection of short-lived TVOC spikes.
+//!
+//! Cooking, cleaning agents or solvents cause TVOC spikes which rise far
+//! above the slowly moving background level and decay within minutes. The
+//! [`SpikeDetector`] tracks the background level and reports the start and
+//! the end of such events, e.g. for switching a kitchen hood or an air
+//! purifier to a boost mode:
+//!
+//! ```
+//! use sgp30::{
+//!     spike::{SpikeConfig, SpikeDetector, SpikeEvent},
+//!     Measurement,
+//! };
+//!
+//! let mut detector = SpikeDetector::new(SpikeConfig::DEFAULT);
+//! assert_eq!(detector.update(&Measurement::new(400, 50)), None);
+//! assert_eq!(
+//!     detector.update(&Measurement::new(400, 400)),
+//!     Some(SpikeEvent::EventStarted {
+//!         background_ppb: 50,
+//!         tvoc_ppb: 400
+//!     })
+//! );
+//! assert_eq!(detector.update(&Measurement::new(400, 900)), None);
+//! assert_eq!(
+//!     detector.update(&Measurement::new(400, 60)),
+//!     Some(SpikeEvent::EventEnded {
+//!         peak_ppb: 900,
+//!         duration_samples: 2
+//!     })
+//! );
+//! ```
+
+use crate::Measurement;
+
+/// The start or end of a TVOC event.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum SpikeEvent {
+    /// The TVOC value rose above the background by more than
+    /// [`start_above_ppb`](SpikeConfig::start_above_ppb).
+    EventStarted {
+        /// The background level when the event started
+        background_ppb: u16,
+        /// The TVOC value which started the event
+        tvoc_ppb: u16,
+    },
+    /// The TVOC value fell back to less than
+    /// [`end_above_ppb`](SpikeConfig::end_above_ppb) above the background.
+    EventEnded {
+        /// The highest TVOC value during the event
+        peak_ppb: u16,
+        /// The number of measurements above the end threshold
+        duration_samples: u32,
+    },
+}
+
+/// Thresholds and parameters of the [`SpikeDetector`].
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SpikeConfig {
+    /// An event starts when the TVOC value exceeds the background by more
+    /// than this value.
+    pub start_above_ppb: u16,
+    /// An event ends when the TVOC value falls to this value above the
+    /// background or below. Should be smaller than
+    /// [`start_above_ppb`](Self::start_above_ppb) to avoid flapping.
+    pub end_above_ppb: u16,
+    /// The weight of a new measurement when tracking the background level,
+    /// between 0 (exclusive) and 1. The background is not updated during an
+    /// event.
+    pub background_smoothing: f32,
+}
+
+impl SpikeConfig {
+    /// Default configuration for measurements taken at 1 Hz, tracking the
+    /// background over roughly 10 minutes.
+    pub const DEFAULT: Self = Self {
+        start_above_ppb: 250,
+        end_above_ppb: 50,
+        background_smoothing: 1.0 / 600.0,
+    };
+}
+
+impl Default for SpikeConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// An ongoing event.
+#[derive(Debug, Clone)]
+struct Event {
+    peak_ppb: u16,
+    duration_samples: u32,
+}
+
+/// Detects TVOC spikes in a series of measurements.
+#[derive(Debug, Clone)]
+pub struct SpikeDetector {
+    config: SpikeConfig,
+    /// The background level, `None` before the first measurement.
+    background_ppb: Option<f32>,
+    event: Option<Event>,
+}
+
+impl SpikeDetector {
+    /// Create a new detector.
+    ///
+    /// The first measurement is taken as the initial background level, so
+    /// the measurements of the 15 s initialization phase should be skipped.
+    pub const fn new(config: SpikeConfig) -> Self {
+        Self {
+            config,
+            background_ppb: None,
+            event: None,
+        }
+    }
+
+    /// Add a measurement, return the event that started or ended with it,
+    /// if any.
+    pub fn update(&mut self, measurement: &Measurement) -> Option<SpikeEvent> {
+        let tvoc_ppb = measurement.tvoc_ppb;
+        let background_ppb = match self.background_ppb {
+            Some(background_ppb) => background_ppb,
+            None => {
+                self.background_ppb = Some(f32::from(tvoc_ppb));
+                return None;
+            }
+        };
+        let above = f32::from(tvoc_ppb) - background_ppb;
+
+        if let Some(event) = &mut self.event {
+            if above > f32::from(self.config.end_above_ppb) {
+                event.peak_ppb = event.peak_ppb.max(tvoc_ppb);
+                event.duration_samples += 1;
+                return None;
+            }
+            let ended = SpikeEvent::EventEnded {
+                peak_ppb: event.peak_ppb,
+                duration_samples: event.duration_samples,
+            };
+            self.event = None;
+            return Some(ended);
+        }
+
+        if above > f32::from(self.config.start_above_ppb) {
+            self.event = Some(Event {
+                peak_ppb: tvoc_ppb,
+                duration_samples: 1,
+            });
+            return Some(SpikeEvent::EventStarted {
+                // The background is rounded to the nearest ppb
+                background_ppb: (background_ppb + 0.5) as u16,
+                tvoc_ppb,
+            });
+        }
+
+        self.background_ppb = Some(background_ppb + self.config.background_smoothing * above);
+        None
+    }
+
+    /// Return whether an event is ongoing.
+    pub fn in_event(&self) -> bool {
+        self.event.is_some()
+    }
+
+    /// Return the background level, or `None` before the first measurement.
+    pub fn background_ppb(&self) -> Option<f32> {
+        self.background_ppb
+    }
+
+    /// Forget the background level and any ongoing event, e.g. after the
+    /// sensor was restarted.
+    pub fn reset(&mut self) {
+        self.background_ppb = None;
+        self.event = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tvoc(ppb: u16) -> Measurement {
+        Measurement::new(400, ppb)
+    }
+
+    #[test]
+    fn background_drift() {
+        let mut detector = SpikeDetector::new(SpikeConfig::DEFAULT);
+        // A slow rise is tracked by the background
+        for i in 0..3000 {
+            assert_eq!(detector.update(&tvoc(i / 5)), None);
+        }
+        assert!(!detector.in_event());
+        assert!(detector.background_ppb().unwrap() > 350.0);
+    }
+
+    #[test]
+    fn spike() {
+        let mut detector = SpikeDetector::new(SpikeConfig::DEFAULT);
+        for _ in 0..10 {
+            assert_eq!(detector.update(&tvoc(100)), None);
+        }
+        assert_eq!(detector.update(&tvoc(350)), None);
+        assert_eq!(
+            detector.update(&tvoc(351)),
+            Some(SpikeEvent::EventStarted {
+                background_ppb: 100,
+                tvoc_ppb: 351
+            })
+        );
+        assert!(detector.in_event());
+        for ppb in [2000, 1000, 500, 151] {
+            assert_eq!(detector.update(&tvoc(ppb)), None);
+        }
+        // The background was not updated during the event
+        assert_eq!(
+            detector.update(&tvoc(150)),
+            Some(SpikeEvent::EventEnded {
+                peak_ppb: 2000,
+                duration_samples: 5
+            })
+        );
+        assert!(!detector.in_event());
+
+        detector.reset();
+        assert_eq!(detector.background_ppb(), None);
+        assert_eq!(detector.update(&tvoc(5000)), None);
+    }
+}
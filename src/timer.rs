@@ -0,0 +1,233 @@
+//! Hardware timers as delay source.
+//!
+//! The driver waits for the commands to complete using a [`DelayNs`]
+//! implementation. Some HALs only expose hardware timers through the
+//! [`fugit_timer::Timer`] countdown trait, without a good blocking delay.
+//! The [`TimerDelay`] adapter turns such a timer into a delay:
+//!
+//! ```
+//! use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction};
+//! use fugit_timer::{Timer, TimerDurationU32, TimerInstantU32};
+//! use sgp30::{timer::TimerDelay, Sgp30};
+//!
+//! /// A 1 MHz timer, e.g. provided by the HAL.
+//! struct HardwareTimer {
+//!     remaining: u32,
+//! }
+//!
+//! impl Timer<1_000_000> for HardwareTimer {
+//!     type Error = ();
+//!
+//!     fn now(&mut self) -> TimerInstantU32<1_000_000> {
+//!         TimerInstantU32::from_ticks(0)
+//!     }
+//!
+//!     fn start(&mut self, duration: TimerDurationU32<1_000_000>) -> Result<(), ()> {
+//!         self.remaining = duration.ticks();
+//!         Ok(())
+//!     }
+//!
+//!     fn cancel(&mut self) -> Result<(), ()> {
+//!         Ok(())
+//!     }
+//!
+//!     fn wait(&mut self) -> nb::Result<(), ()> {
+//!         if self.remaining == 0 {
+//!             return Ok(());
+//!         }
+//!         self.remaining -= 1;
+//!         Err(nb::Error::WouldBlock)
+//!     }
+//! }
+//!
+//! # let mock = I2cMock::new(&[Transaction::write(0x58, vec![0x20, 0x03])]);
+//! let delay = TimerDelay::new(HardwareTimer { remaining: 0 });
+//! let mut sgp = Sgp30::new(mock, 0x58, delay);
+//! sgp.init().unwrap();
+//! # sgp.destroy().done();
+//! ```
+//!
+//! While waiting for the timer to expire, an optional idle function is
+//! called, e.g. `cortex_m::asm::wfi` to sleep until the next interrupt
+//! instead of busy-waiting, see [`TimerDelay::with_idle()`].
+//!
+//! This module requires the `fugit` feature.
+
+use embedded_hal::delay::DelayNs;
+use fugit_timer::{Timer, TimerDurationU32};
+
+/// A delay based on a [`fugit_timer::Timer`] running at `HZ`, see the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct TimerDelay<T: Timer<HZ>, const HZ: u32> {
+    timer: T,
+    idle: Option<fn()>,
+    error: Option<T::Error>,
+}
+
+impl<T: Timer<HZ>, const HZ: u32> TimerDelay<T, HZ> {
+    /// Create a new delay using `timer`, polling it until it expires.
+    pub fn new(timer: T) -> Self {
+        Self {
+            timer,
+            idle: None,
+            error: None,
+        }
+    }
+
+    /// Create a new delay using `timer`, calling `idle` while waiting for
+    /// it to expire.
+    ///
+    /// If `idle` sleeps until the next interrupt (e.g. `cortex_m::asm::wfi`),
+    /// the interrupt of the timer must be enabled.
+    pub fn with_idle(timer: T, idle: fn()) -> Self {
+        Self {
+            timer,
+            idle: Some(idle),
+            error: None,
+        }
+    }
+
+    /// Return and clear the most recent error of the timer.
+    ///
+    /// Delays cannot fail, so if starting or waiting for the timer fails,
+    /// the delay returns immediately and the error is stored.
+    pub fn take_error(&mut self) -> Option<T::Error> {
+        self.error.take()
+    }
+
+    /// Destroy the delay, return the timer.
+    pub fn into_inner(self) -> T {
+        self.timer
+    }
+
+    /// Wait for `ticks` of the timer.
+    fn delay_ticks(&mut self, mut ticks: u64) {
+        while ticks > 0 {
+            let chunk = u32::try_from(ticks).unwrap_or(u32::MAX);
+            if let Err(e) = self.wait_for(chunk) {
+                self.error = Some(e);
+                return;
+            }
+            ticks -= u64::from(chunk);
+        }
+    }
+
+    /// Start the timer and wait until it expired.
+    fn wait_for(&mut self, ticks: u32) -> Result<(), T::Error> {
+        self.timer.start(TimerDurationU32::from_ticks(ticks))?;
+        loop {
+            match self.timer.wait() {
+                Ok(()) => return Ok(()),
+                Err(nb::Error::WouldBlock) => {
+                    if let Some(idle) = self.idle {
+                        idle();
+                    }
+                }
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Return the number of ticks at `HZ` covering at least `duration` in
+/// units of `1 / per_second` seconds.
+fn ticks_at_least<const HZ: u32>(duration: u32, per_second: u64) -> u64 {
+    (u64::from(duration) * u64::from(HZ)).div_ceil(per_second)
+}
+
+impl<T: Timer<HZ>, const HZ: u32> DelayNs for TimerDelay<T, HZ> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_ticks(ticks_at_least::<HZ>(ns, 1_000_000_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.delay_ticks(ticks_at_least::<HZ>(us, 1_000_000));
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_ticks(ticks_at_least::<HZ>(ms, 1_000));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fugit_timer::TimerInstantU32;
+
+    use super::*;
+
+    /// A 32 kHz timer recording the started durations.
+    #[derive(Default)]
+    struct FakeTimer {
+        started: Vec<u32>,
+        polls: u32,
+        fail: bool,
+    }
+
+    impl Timer<32_768> for FakeTimer {
+        type Error = ();
+
+        fn now(&mut self) -> TimerInstantU32<32_768> {
+            TimerInstantU32::from_ticks(0)
+        }
+
+        fn start(&mut self, duration: TimerDurationU32<32_768>) -> Result<(), ()> {
+            if self.fail {
+                return Err(());
+            }
+            self.started.push(duration.ticks());
+            Ok(())
+        }
+
+        fn cancel(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn wait(&mut self) -> nb::Result<(), ()> {
+            self.polls += 1;
+            if self.polls % 3 == 0 {
+                Ok(())
+            } else {
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+
+    #[test]
+    fn delay() {
+        let mut delay = TimerDelay::new(FakeTimer::default());
+        // Rounded up to the next tick
+        delay.delay_us(12_000);
+        delay.delay_ns(1);
+        delay.delay_ms(1_000);
+        delay.delay_us(0);
+        let timer = delay.into_inner();
+        assert_eq!(timer.started, [394, 1, 32_768]);
+        assert_eq!(timer.polls, 9);
+    }
+
+    #[test]
+    fn idle() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static IDLE_CALLS: AtomicU32 = AtomicU32::new(0);
+
+        let mut delay = TimerDelay::with_idle(FakeTimer::default(), || {
+            IDLE_CALLS.fetch_add(1, Ordering::Relaxed);
+        });
+        delay.delay_ms(1);
+        assert_eq!(IDLE_CALLS.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn error() {
+        let timer = FakeTimer {
+            fail: true,
+            ..FakeTimer::default()
+        };
+        let mut delay = TimerDelay::new(timer);
+        delay.delay_ms(10);
+        assert_eq!(delay.take_error(), Some(()));
+        assert_eq!(delay.take_error(), None);
+    }
+}
@@ -0,0 +1,39 @@
+//! Timing constraints of the SGP30.
+//!
+//! This module collects the timings from the datasheet which are used by
+//! the driver and its helpers, so that external schedulers (e.g. RTOS task
+//! budgets or bus arbiters) can plan the sensor's bus occupancy and the
+//! measurement cadence from the same values:
+//!
+//! ```
+//! use sgp30::{commands, timings};
+//!
+//! // Time per sample interval which the bus is busy for a measurement
+//! let busy_us = commands::MEASURE_AIR_QUALITY.max_duration_us;
+//! assert_eq!(busy_us, 12_000);
+//! // Time left per sample interval for other work
+//! assert_eq!(timings::SAMPLE_INTERVAL_US - busy_us, 988_000);
+//! ```
+//!
+//! The typical and maximum durations of the individual commands are part
+//! of the command table in the [`commands`](crate::commands) module, see
+//! [`CommandInfo`](crate::commands::CommandInfo). They can also be looked up
+//! by [`CommandId`](crate::commands::CommandId), e.g. for the command of an
+//! error.
+
+/// The interval between two air quality measurements in microseconds.
+pub const SAMPLE_INTERVAL_US: u32 = 1_000_000;
+
+/// The duration of the initialization phase in seconds (i.e. measurements
+/// at [`SAMPLE_INTERVAL_US`]), during which the sensor returns the fixed
+/// values of [`Measurement::WARM_UP`](crate::Measurement::WARM_UP).
+pub const INIT_PHASE_S: u32 = 15;
+
+/// The time in seconds the baseline compensation needs to settle without a
+/// restored baseline (datasheet, section 3.8). Baselines read before should
+/// not be stored.
+pub const BASELINE_LEARNING_S: u32 = 12 * 60 * 60;
+
+/// The maximum age in hours of a restored baseline (datasheet, section 3.8).
+/// Older baselines should be discarded.
+pub const BASELINE_MAX_AGE_HOURS: u32 = 7 * 24;
@@ -0,0 +1,95 @@
+//! The bus transport used by the blocking driver.
+//!
+//! The [`Sgp30`](crate::Sgp30) driver talks to the sensor through the
+//! [`Sgp30Transport`] trait, which is implemented for every
+//! [`embedded_hal::i2c::I2c`] bus. The command encoding, CRC handling and
+//! timing stay in the driver, so a transport only has to move raw bytes.
+//! Implementing the trait allows running the driver over other links, such
+//! as an I²C-over-USB bridge or a test harness:
+//!
+//! ```
+//! use linux_embedded_hal::Delay;
+//! use sgp30::{transport::Sgp30Transport, Sgp30};
+//!
+//! /// A fake sensor returning a fixed feature set.
+//! struct Fake;
+//!
+//! impl Sgp30Transport for Fake {
+//!     type Error = ();
+//!
+//!     fn write(&mut self, _address: u8, _data: &[u8]) -> Result<(), ()> {
+//!         Ok(())
+//!     }
+//!
+//!     fn read(&mut self, _address: u8, buf: &mut [u8]) -> Result<(), ()> {
+//!         buf.copy_from_slice(&[0x00, 0x22, 0x65]);
+//!         Ok(())
+//!     }
+//!
+//!     fn write_read(&mut self, address: u8, data: &[u8], buf: &mut [u8]) -> Result<(), ()> {
+//!         self.write(address, data)?;
+//!         self.read(address, buf)
+//!     }
+//! }
+//!
+//! let mut sgp = Sgp30::new(Fake, 0x58, Delay);
+//! assert_eq!(sgp.get_feature_set().unwrap().product_version, 0x22);
+//! ```
+
+use embedded_hal::i2c::{self, ErrorKind, I2c, NoAcknowledgeSource};
+
+/// A transport moving raw bytes between the driver and the sensor.
+///
+/// All data passed to and returned from the transport already includes the
+/// CRC bytes of the sensor protocol.
+pub trait Sgp30Transport {
+    /// The transport error type.
+    type Error;
+
+    /// Write `data` (a command, optionally followed by data words) to the
+    /// sensor at `address`.
+    fn write(&mut self, address: u8, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Read the response of the sensor at `address` into `buf`.
+    fn read(&mut self, address: u8, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Write `data` and read the response into `buf` in a single combined
+    /// transaction, without a delay in between.
+    fn write_read(&mut self, address: u8, data: &[u8], buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Return whether `error` means that the sensor did not acknowledge its
+    /// address, i.e. that it is not responding at all.
+    ///
+    /// Such errors are reported as
+    /// [`Error::DeviceNotResponding`](crate::Error::DeviceNotResponding).
+    /// The default implementation returns `false`.
+    fn is_address_nack(error: &Self::Error) -> bool {
+        let _ = error;
+        false
+    }
+}
+
+impl<T: I2c> Sgp30Transport for T {
+    type Error = T::Error;
+
+    fn write(&mut self, address: u8, data: &[u8]) -> Result<(), Self::Error> {
+        I2c::write(self, address, data)
+    }
+
+    fn read(&mut self, address: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        I2c::read(self, address, buf)
+    }
+
+    fn write_read(&mut self, address: u8, data: &[u8], buf: &mut [u8]) -> Result<(), Self::Error> {
+        I2c::write_read(self, address, data, buf)
+    }
+
+    fn is_address_nack(error: &Self::Error) -> bool {
+        is_address_nack(error)
+    }
+}
+
+/// Return whether the I²C bus `error` is a NACK of the address byte.
+pub(crate) fn is_address_nack<E: i2c::Error>(error: &E) -> bool {
+    error.kind() == ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+}
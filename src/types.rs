@@ -1,8 +1,17 @@
+use core::{array::TryFromSliceError, fmt, str::FromStr};
+
+#[cfg(feature = "humidity-float")]
 #[allow(unused_imports)] // Required for no_std
-use num_traits::float::FloatCore;
+use num_traits::Float;
 
 /// A measurement result from the sensor.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes, compare(PartialEq)),
+    archive_attr(derive(Debug))
+)]
 pub struct Measurement {
     /// CO₂ equivalent (parts per million, ppm)
     pub co2eq_ppm: u16,
@@ -11,9 +20,45 @@ pub struct Measurement {
 }
 
 impl Measurement {
-    pub(crate) fn from_bytes(buf: &[u8; 6]) -> Self {
-        let co2eq_ppm = (u16::from(buf[0]) << 8) | u16::from(buf[1]);
-        let tvoc_ppb = (u16::from(buf[3]) << 8) | u16::from(buf[4]);
+    /// The fixed values returned by the sensor during the initialization
+    /// phase of 15 s after [`init()`](crate::Sgp30::init): 400 ppm CO₂eq
+    /// and 0 ppb TVOC.
+    pub const WARM_UP: Measurement = Measurement::new(400, 0);
+
+    /// Create a new measurement.
+    pub const fn new(co2eq_ppm: u16, tvoc_ppb: u16) -> Self {
+        Self {
+            co2eq_ppm,
+            tvoc_ppb,
+        }
+    }
+
+    /// Parse a measurement from a raw 6 byte sensor response.
+    ///
+    /// The response consists of two big-endian words, each followed by a
+    /// CRC byte (CO₂eq first). The CRC bytes are ignored, so they must be
+    /// validated separately (e.g. using [`sensirion_i2c::crc8::validate()`])
+    /// when parsing data that was not already checked by the driver.
+    pub const fn from_bytes(buf: &[u8; 6]) -> Self {
+        let co2eq_ppm = u16::from_be_bytes([buf[0], buf[1]]);
+        let tvoc_ppb = u16::from_be_bytes([buf[3], buf[4]]);
+        Self {
+            co2eq_ppm,
+            tvoc_ppb,
+        }
+    }
+}
+
+impl From<Measurement> for (u16, u16) {
+    /// Convert a `Measurement` to a `(co2eq_ppm, tvoc_ppb)` tuple.
+    fn from(val: Measurement) -> Self {
+        (val.co2eq_ppm, val.tvoc_ppb)
+    }
+}
+
+impl From<(u16, u16)> for Measurement {
+    /// Create a `Measurement` from a `(co2eq_ppm, tvoc_ppb)` tuple.
+    fn from((co2eq_ppm, tvoc_ppb): (u16, u16)) -> Self {
         Self {
             co2eq_ppm,
             tvoc_ppb,
@@ -22,7 +67,7 @@ impl Measurement {
 }
 
 /// A raw signals result from the sensor.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub struct RawSignals {
     /// H2 signal
     pub h2: u16,
@@ -31,15 +76,46 @@ pub struct RawSignals {
 }
 
 impl RawSignals {
-    pub(crate) fn from_bytes(buf: &[u8; 6]) -> Self {
-        let h2 = (u16::from(buf[0]) << 8) | u16::from(buf[1]);
-        let ethanol = (u16::from(buf[3]) << 8) | u16::from(buf[4]);
+    /// Create new raw signals.
+    pub const fn new(h2: u16, ethanol: u16) -> Self {
+        Self { h2, ethanol }
+    }
+
+    /// Parse raw signals from a raw 6 byte sensor response.
+    ///
+    /// The response consists of two big-endian words, each followed by a
+    /// CRC byte (H2 first). The CRC bytes are ignored, so they must be
+    /// validated separately (e.g. using [`sensirion_i2c::crc8::validate()`])
+    /// when parsing data that was not already checked by the driver.
+    pub const fn from_bytes(buf: &[u8; 6]) -> Self {
+        let h2 = u16::from_be_bytes([buf[0], buf[1]]);
+        let ethanol = u16::from_be_bytes([buf[3], buf[4]]);
+        Self { h2, ethanol }
+    }
+}
+
+impl From<RawSignals> for [u16; 2] {
+    /// Convert `RawSignals` to an `[h2, ethanol]` array.
+    fn from(val: RawSignals) -> Self {
+        [val.h2, val.ethanol]
+    }
+}
+
+impl From<[u16; 2]> for RawSignals {
+    /// Create `RawSignals` from an `[h2, ethanol]` array.
+    fn from([h2, ethanol]: [u16; 2]) -> Self {
         Self { h2, ethanol }
     }
 }
 
 /// The baseline values.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes, compare(PartialEq)),
+    archive_attr(derive(Debug))
+)]
 pub struct Baseline {
     /// CO₂eq baseline
     pub co2eq: u16,
@@ -48,22 +124,146 @@ pub struct Baseline {
 }
 
 impl Baseline {
-    pub(crate) fn from_bytes(buf: &[u8; 6]) -> Self {
+    /// Create a new baseline.
+    pub const fn new(co2eq: u16, tvoc: u16) -> Self {
+        Self { co2eq, tvoc }
+    }
+
+    /// Parse a baseline from a raw 6 byte sensor response.
+    ///
+    /// The response consists of two big-endian words, each followed by a
+    /// CRC byte (CO₂eq first, as returned by the get baseline command). The
+    /// CRC bytes are ignored, so they must be validated separately (e.g.
+    /// using [`sensirion_i2c::crc8::validate()`]) when parsing data that was
+    /// not already checked by the driver.
+    pub const fn from_bytes(buf: &[u8; 6]) -> Self {
         let measurement = Measurement::from_bytes(buf);
         Baseline {
             co2eq: measurement.co2eq_ppm,
             tvoc: measurement.tvoc_ppb,
         }
     }
+
+    /// Convert this to the binary representation expected by the SGP30
+    /// sensor when setting the baseline.
+    ///
+    /// Note that the order of the two values is inverted when writing
+    /// compared to when reading: The TVOC baseline comes first, followed by
+    /// the CO₂eq baseline. Both values are encoded as big-endian words.
+    pub fn as_bytes(&self) -> [u8; 4] {
+        let tvoc = self.tvoc.to_be_bytes();
+        let co2eq = self.co2eq.to_be_bytes();
+        [tvoc[0], tvoc[1], co2eq[0], co2eq[1]]
+    }
+
+    /// Format the baseline as upper-case hex, with the CO₂eq baseline first
+    /// (e.g. `"8A3C:91F0"`).
+    ///
+    /// The result can be parsed again using [`str::parse()`].
+    pub fn to_hex(&self) -> BaselineHex {
+        const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+        let mut buf = [b':'; BaselineHex::LEN];
+        for (i, value) in [self.co2eq, self.tvoc].into_iter().enumerate() {
+            for (j, byte) in buf[i * 5..i * 5 + 4].iter_mut().enumerate() {
+                *byte = DIGITS[usize::from(value >> (12 - 4 * j)) & 0xF];
+            }
+        }
+        BaselineHex(buf)
+    }
+}
+
+impl FromStr for Baseline {
+    type Err = ParseBaselineError;
+
+    /// Parse a baseline in the format of [`Baseline::to_hex()`]. Both
+    /// upper-case and lower-case hex digits are accepted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn parse_word(s: &str) -> Result<u16, ParseBaselineError> {
+            if s.len() != 4 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ParseBaselineError);
+            }
+            u16::from_str_radix(s, 16).map_err(|_| ParseBaselineError)
+        }
+        let (co2eq, tvoc) = s.split_once(':').ok_or(ParseBaselineError)?;
+        Ok(Baseline {
+            co2eq: parse_word(co2eq)?,
+            tvoc: parse_word(tvoc)?,
+        })
+    }
+}
+
+impl From<Baseline> for [u8; 4] {
+    /// Convert a `Baseline` to two big-endian words, CO₂eq first, without
+    /// CRC bytes.
+    ///
+    /// This is a plain storage encoding, e.g. for a non-volatile memory
+    /// which already protects its contents. It differs from the order sent
+    /// to the sensor, see [`Baseline::as_bytes()`].
+    fn from(val: Baseline) -> Self {
+        let co2eq = val.co2eq.to_be_bytes();
+        let tvoc = val.tvoc.to_be_bytes();
+        [co2eq[0], co2eq[1], tvoc[0], tvoc[1]]
+    }
 }
 
+impl TryFrom<&[u8]> for Baseline {
+    type Error = TryFromSliceError;
+
+    /// Create a `Baseline` from two big-endian words, CO₂eq first, without
+    /// CRC bytes, as encoded by the conversion into `[u8; 4]`. Fails if the
+    /// slice is not exactly 4 bytes long.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let [co2eq_msb, co2eq_lsb, tvoc_msb, tvoc_lsb]: [u8; 4] = bytes.try_into()?;
+        Ok(Baseline {
+            co2eq: u16::from_be_bytes([co2eq_msb, co2eq_lsb]),
+            tvoc: u16::from_be_bytes([tvoc_msb, tvoc_lsb]),
+        })
+    }
+}
+
+/// The hex representation of a [`Baseline`], see [`Baseline::to_hex()`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct BaselineHex([u8; BaselineHex::LEN]);
+
+impl BaselineHex {
+    /// The length of the hex representation (e.g. `"8A3C:91F0"`).
+    pub const LEN: usize = 9;
+
+    /// Return the hex representation as a string slice.
+    pub fn as_str(&self) -> &str {
+        // Only ASCII characters are ever written to the buffer
+        core::str::from_utf8(&self.0).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for BaselineHex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AsRef<str> for BaselineHex {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// The error returned when parsing a [`Baseline`] from a string fails.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+#[cfg_attr(
+    feature = "std",
+    error("invalid baseline, expected two hex words (e.g. \"8A3C:91F0\")")
+)]
+pub struct ParseBaselineError;
+
 /// Absolute humidity in g/m³.
 ///
 /// Internally this is represented as a 8.8bit fixed-point number.
 ///
 /// To construct a `Humidity` instance, either use the lossless `new()`
 /// constructor, or the lossy `from_f32()` method.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
 pub struct Humidity {
     integer: u8,    // 0-255
     fractional: u8, // 0/256-255/256
@@ -71,15 +271,22 @@ pub struct Humidity {
 
 /// Errors that can occur when constructing a `Humidity` value.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
 pub enum HumidityError {
     /// A zero value is not allowed in a `Humidity` struct since that will turn
     /// off the temperature compensation.
+    #[cfg_attr(feature = "std", error("humidity must not be zero"))]
     ZeroValue,
     /// A value is outside the representable range.
+    #[cfg_attr(feature = "std", error("humidity out of range"))]
     OutOfRange,
 }
 
 impl Humidity {
+    /// The humidity used for compensation by the sensor after a reset, or
+    /// after compensation was turned off: 11.57 g/m³ (`0x0B92`).
+    pub const DEFAULT: Humidity = Humidity::new_const(0x0B, 0x92);
+
     /// Create a new `Humidity` instance.
     ///
     /// The humidity should be passed in as a 8.8bit fixed-point number.
@@ -89,7 +296,7 @@ impl Humidity {
     /// - The pair `(0x00, 0x01)` represents `1/256 g/m³` (0.00390625)
     /// - The pair `(0xFF, 0xFF)` represents `255 g/m³ + 255/256 g/m³` (255.99609375)
     /// - The pair `(0x10, 0x80)` represents `16 g/m³ + 128/256 g/m³` (16.5)
-    pub fn new(integer: u8, fractional: u8) -> Result<Self, HumidityError> {
+    pub const fn new(integer: u8, fractional: u8) -> Result<Self, HumidityError> {
         if integer == 0 && fractional == 0 {
             return Err(HumidityError::ZeroValue);
         }
@@ -99,9 +306,33 @@ impl Humidity {
         })
     }
 
+    /// Create a new `Humidity` instance in a const context.
+    ///
+    /// This is identical to [`new()`](Humidity::new), but panics instead of
+    /// returning an error. When used to initialize a `const` or `static`, an
+    /// invalid value is therefore rejected at compile time:
+    ///
+    /// ```
+    /// use sgp30::Humidity;
+    ///
+    /// const COMPENSATION: Humidity = Humidity::new_const(0x10, 0x80);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// - If both `integer` and `fractional` are zero.
+    #[allow(clippy::panic)] // Evaluated at compile time in const contexts
+    pub const fn new_const(integer: u8, fractional: u8) -> Self {
+        match Humidity::new(integer, fractional) {
+            Ok(humidity) => humidity,
+            Err(_) => panic!("Humidity must not be zero"),
+        }
+    }
+
     /// Create a new `Humidity` instance from a f32.
     ///
     /// When converting, the fractional part will always be rounded down.
+    #[cfg(feature = "humidity-float")]
     pub fn from_f32(val: f32) -> Result<Self, HumidityError> {
         if val.is_nan() {
             return Err(HumidityError::OutOfRange);
@@ -125,13 +356,93 @@ impl Humidity {
         Humidity::new(integer, fractional)
     }
 
+    /// Create a new `Humidity` instance from the air temperature and the dew
+    /// point, both in °C.
+    ///
+    /// The vapor pressure is derived from the dew point using the Magnus
+    /// formula, with the constants given by Sensirion for temperatures above
+    /// 0 °C. Returns an `OutOfRange` error if either value is not
+    /// finite, or if the dew point is above the air temperature.
+    ///
+    /// ```
+    /// use sgp30::Humidity;
+    ///
+    /// let humidity = Humidity::from_dew_point(25.0, 15.0).unwrap();
+    /// assert_eq!(f32::from(humidity).round(), 12.0);
+    /// ```
+    #[cfg(feature = "humidity-float")]
+    pub fn from_dew_point(temp_c: f32, dew_point_c: f32) -> Result<Self, HumidityError> {
+        if !temp_c.is_finite() || !dew_point_c.is_finite() || dew_point_c > temp_c {
+            return Err(HumidityError::OutOfRange);
+        }
+        let vapor_pressure_hpa = saturation_vapor_pressure_hpa(dew_point_c);
+        Humidity::from_f32(absolute_humidity(vapor_pressure_hpa, temp_c))
+    }
+
+    /// Create a new `Humidity` instance from the air temperature in °C, the
+    /// relative humidity in % and the barometric pressure in hPa.
+    ///
+    /// The saturation vapor pressure is computed using the Magnus formula
+    /// and corrected by the enhancement factor of moist air according to
+    /// Buck (1981), which depends on the pressure. This matters at altitude
+    /// or when a pressure reading is available anyway (e.g. from a BME280).
+    /// Returns an `OutOfRange` error if any value is not finite, if the
+    /// relative humidity is outside of 0–100 % or if the pressure is not
+    /// positive.
+    ///
+    /// ```
+    /// use sgp30::Humidity;
+    ///
+    /// // 50 % relative humidity at 25 °C, about 1500 m above sea level
+    /// let humidity = Humidity::from_relative_humidity_at_pressure(25.0, 50.0, 850.0).unwrap();
+    /// assert_eq!(f32::from(humidity).round(), 12.0);
+    /// ```
+    #[cfg(feature = "humidity-float")]
+    pub fn from_relative_humidity_at_pressure(
+        temp_c: f32,
+        relative_humidity: f32,
+        pressure_hpa: f32,
+    ) -> Result<Self, HumidityError> {
+        let valid = temp_c.is_finite()
+            && (0.0..=100.0).contains(&relative_humidity)
+            && pressure_hpa.is_finite()
+            && pressure_hpa > 0.0;
+        if !valid {
+            return Err(HumidityError::OutOfRange);
+        }
+        let enhancement_factor = 1.0007 + 3.46e-6 * pressure_hpa;
+        let vapor_pressure_hpa =
+            relative_humidity / 100.0 * enhancement_factor * saturation_vapor_pressure_hpa(temp_c);
+        Humidity::from_f32(absolute_humidity(vapor_pressure_hpa, temp_c))
+    }
+
+    /// Return whether this is the [default](Humidity::DEFAULT) humidity used
+    /// by the sensor.
+    pub const fn is_default(&self) -> bool {
+        self.integer == Humidity::DEFAULT.integer && self.fractional == Humidity::DEFAULT.fractional
+    }
+
     /// Convert this to the binary fixed-point representation expected by the
     /// SGP30 sensor.
-    pub fn as_bytes(&self) -> [u8; 2] {
+    pub const fn as_bytes(&self) -> [u8; 2] {
         [self.integer, self.fractional]
     }
 }
 
+/// Return the saturation vapor pressure over water in hPa at `temp_c` °C,
+/// according to the Magnus formula.
+#[cfg(feature = "humidity-float")]
+fn saturation_vapor_pressure_hpa(temp_c: f32) -> f32 {
+    6.112 * (17.62 * temp_c / (243.12 + temp_c)).exp()
+}
+
+/// Return the absolute humidity in g/m³ of air at `temp_c` °C with the given
+/// partial vapor pressure in hPa.
+#[cfg(feature = "humidity-float")]
+fn absolute_humidity(vapor_pressure_hpa: f32, temp_c: f32) -> f32 {
+    216.7 * vapor_pressure_hpa / (273.15 + temp_c)
+}
+
 impl From<Humidity> for f32 {
     /// Convert a `Humidity` instance to a f32.
     fn from(val: Humidity) -> Self {
@@ -139,8 +450,41 @@ impl From<Humidity> for f32 {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Humidity {
+    /// Serialize as g/m³ float in human-readable formats, and as the raw
+    /// 8.8 fixed-point `u16` otherwise.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_f32(f32::from(*self))
+        } else {
+            serializer.serialize_u16(u16::from_be_bytes(self.as_bytes()))
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Humidity {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let result = if deserializer.is_human_readable() {
+            // Values which are not a multiple of 1/256 g/m³ are rounded
+            // down, like in `from_f32()`
+            Humidity::from_f32(f32::deserialize(deserializer)?)
+        } else {
+            let [integer, fractional] = u16::deserialize(deserializer)?.to_be_bytes();
+            Humidity::new(integer, fractional)
+        };
+        result.map_err(|e| match e {
+            HumidityError::ZeroValue => D::Error::custom("humidity must not be zero"),
+            HumidityError::OutOfRange => D::Error::custom("humidity out of range"),
+        })
+    }
+}
+
 /// The product types compatible with this driver.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum ProductType {
     /// SGP30
     Sgp30,
@@ -150,39 +494,253 @@ pub enum ProductType {
 
 impl ProductType {
     /// Parse the product type.
-    pub fn parse(val: u8) -> Self {
+    pub const fn parse(val: u8) -> Self {
         match val {
             0 => ProductType::Sgp30,
             _ => ProductType::Unknown(val),
         }
     }
+
+    /// Return the 4 bit product type value.
+    const fn value(self) -> u8 {
+        match self {
+            ProductType::Sgp30 => 0,
+            ProductType::Unknown(val) => val,
+        }
+    }
 }
 
 /// The feature set returned by the sensor.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub struct FeatureSet {
     /// The product type (see [`ProductType`](enum.ProductType.html))
     pub product_type: ProductType,
+    /// The reserved bits 11 to 8 of the feature set word
+    pub reserved: u8,
     /// The product version
     pub product_version: u8,
 }
 
 impl FeatureSet {
     /// Parse the two bytes returned by the device.
-    pub fn parse(msb: u8, lsb: u8) -> Self {
+    pub const fn parse(msb: u8, lsb: u8) -> Self {
         FeatureSet {
             product_type: ProductType::parse(msb >> 4),
+            reserved: msb & 0x0F,
             product_version: lsb,
         }
     }
+
+    /// Parse the 16 bit feature set word returned by the device.
+    pub const fn from_raw(raw: u16) -> Self {
+        let [msb, lsb] = raw.to_be_bytes();
+        Self::parse(msb, lsb)
+    }
+
+    /// Return the 16 bit feature set word, as returned by the device.
+    ///
+    /// The word is laid out as follows:
+    ///
+    /// | Bits  | Content         |
+    /// |-------|-----------------|
+    /// | 15:12 | Product type    |
+    /// | 11:8  | Reserved        |
+    /// | 7:0   | Product version |
+    pub const fn raw(&self) -> u16 {
+        u16::from_be_bytes([
+            (self.product_type.value() << 4) | (self.reserved & 0x0F),
+            self.product_version,
+        ])
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::excessive_precision)]
 mod tests {
     use std::f32;
 
     use super::*;
 
+    #[test]
+    fn const_new() {
+        const MEASUREMENT: Measurement = Measurement::new(400, 13);
+        const SIGNALS: RawSignals = RawSignals::new(13_000, 18_000);
+        const BASELINE: Baseline = Baseline::new(0x1234, 0x5678);
+        assert_eq!(MEASUREMENT.co2eq_ppm, 400);
+        assert_eq!(MEASUREMENT.tvoc_ppb, 13);
+        assert_eq!(SIGNALS.h2, 13_000);
+        assert_eq!(SIGNALS.ethanol, 18_000);
+        assert_eq!(BASELINE.co2eq, 0x1234);
+        assert_eq!(BASELINE.tvoc, 0x5678);
+    }
+
+    #[test]
+    fn from_bytes() {
+        // A captured response to the measure command, 400 ppm / 0 ppb
+        const RESPONSE: [u8; 6] = [0x01, 0x90, 0x4C, 0x00, 0x00, 0x81];
+        assert!(sensirion_i2c::crc8::validate(&RESPONSE).is_ok());
+        assert_eq!(Measurement::from_bytes(&RESPONSE), Measurement::new(400, 0));
+        assert_eq!(RawSignals::from_bytes(&RESPONSE), RawSignals::new(400, 0));
+        assert_eq!(Baseline::from_bytes(&RESPONSE), Baseline::new(400, 0));
+    }
+
+    #[test]
+    fn humidity_ord() {
+        let low = Humidity::new(0x10, 0xFF).unwrap();
+        let high = Humidity::new(0x11, 0x00).unwrap();
+        assert!(low < high);
+        assert_eq!(low.max(high), high);
+    }
+
+    #[test]
+    fn measurement_tuple() {
+        let measurement = Measurement {
+            co2eq_ppm: 400,
+            tvoc_ppb: 13,
+        };
+        let tuple: (u16, u16) = measurement.into();
+        assert_eq!(tuple, (400, 13));
+        assert_eq!(Measurement::from(tuple), measurement);
+    }
+
+    #[test]
+    fn raw_signals_array() {
+        let signals = RawSignals {
+            h2: 13_000,
+            ethanol: 18_000,
+        };
+        let array: [u16; 2] = signals.into();
+        assert_eq!(array, [13_000, 18_000]);
+        assert_eq!(RawSignals::from(array), signals);
+    }
+
+    #[test]
+    fn baseline_as_bytes() {
+        let baseline = Baseline {
+            co2eq: 0x1234,
+            tvoc: 0x5678,
+        };
+        assert_eq!(baseline.as_bytes(), [0x56, 0x78, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn baseline_byte_array() {
+        let baseline = Baseline::new(0x1234, 0x5678);
+        let bytes: [u8; 4] = baseline.into();
+        assert_eq!(bytes, [0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(Baseline::try_from(&bytes[..]).unwrap(), baseline);
+        assert!(Baseline::try_from(&bytes[..3]).is_err());
+        assert!(Baseline::try_from(&[0; 5][..]).is_err());
+    }
+
+    #[test]
+    fn baseline_to_hex() {
+        let baseline = Baseline::new(0x8A3C, 0x91F0);
+        assert_eq!(baseline.to_hex().as_str(), "8A3C:91F0");
+        assert_eq!(Baseline::new(0, 0x00ff).to_hex().as_str(), "0000:00FF");
+        assert_eq!(format!("{}", baseline.to_hex()), "8A3C:91F0");
+    }
+
+    #[test]
+    fn baseline_from_str() {
+        assert_eq!("8A3C:91F0".parse(), Ok(Baseline::new(0x8A3C, 0x91F0)));
+        assert_eq!("8a3c:91f0".parse(), Ok(Baseline::new(0x8A3C, 0x91F0)));
+        let baseline = Baseline::new(0x0001, 0xFFFF);
+        assert_eq!(baseline.to_hex().as_str().parse(), Ok(baseline));
+        for invalid in [
+            "",
+            "8A3C",
+            "8A3C:",
+            "8A3C91F0",
+            "8A3:91F0",
+            "+A3C:91F0",
+            "8A3C:91F0:",
+            "8A3G:91F0",
+        ] {
+            assert_eq!(
+                invalid.parse::<Baseline>(),
+                Err(ParseBaselineError),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn feature_set_raw() {
+        let feature_set = FeatureSet::from_raw(0x1342);
+        assert_eq!(feature_set.product_type, ProductType::Unknown(1));
+        assert_eq!(feature_set.reserved, 0x3);
+        assert_eq!(feature_set.product_version, 0x42);
+        assert_eq!(feature_set.raw(), 0x1342);
+        assert_eq!(FeatureSet::parse(0x00, 0x22).raw(), 0x0022);
+    }
+
+    /// Test the human-readable serde representation of `Humidity`
+    #[cfg(feature = "serde")]
+    #[test]
+    fn humidity_serde_json() {
+        let humidity = Humidity::new(0x10, 0x80).unwrap();
+        assert_eq!(serde_json::to_string(&humidity).unwrap(), "16.5");
+        for (integer, fractional) in [(0x00, 0x01), (0x0B, 0x92), (0xFF, 0xFF)] {
+            let humidity = Humidity::new(integer, fractional).unwrap();
+            let json = serde_json::to_string(&humidity).unwrap();
+            assert_eq!(serde_json::from_str::<Humidity>(&json).unwrap(), humidity);
+        }
+        assert_eq!(
+            serde_json::from_str::<Humidity>("23.42").unwrap(),
+            Humidity::from_f32(23.42).unwrap()
+        );
+        assert!(serde_json::from_str::<Humidity>("0").is_err());
+        assert!(serde_json::from_str::<Humidity>("256").is_err());
+    }
+
+    /// Test the compact serde representation of `Humidity`
+    #[cfg(feature = "serde")]
+    #[test]
+    fn humidity_serde_postcard() {
+        let humidity = Humidity::new(0x10, 0x80).unwrap();
+        // Varint encoding of 0x1080
+        assert_eq!(postcard::to_allocvec(&humidity).unwrap(), [0x80, 0x21]);
+        for (integer, fractional) in [(0x00, 0x01), (0x0B, 0x92), (0xFF, 0xFF)] {
+            let humidity = Humidity::new(integer, fractional).unwrap();
+            let bytes = postcard::to_allocvec(&humidity).unwrap();
+            assert_eq!(postcard::from_bytes::<Humidity>(&bytes).unwrap(), humidity);
+        }
+        assert!(postcard::from_bytes::<Humidity>(&[0x00]).is_err());
+    }
+
+    /// Test the zero-copy representation of `Measurement` and `Baseline`
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn rkyv_round_trip() {
+        use rkyv::{
+            ser::{serializers::BufferSerializer, Serializer},
+            AlignedBytes, Deserialize, Infallible,
+        };
+
+        let measurement = Measurement::new(412, 27);
+        let mut serializer = BufferSerializer::new(AlignedBytes([0u8; 16]));
+        serializer.serialize_value(&measurement).unwrap();
+        let len = serializer.pos();
+        let bytes = serializer.into_inner();
+        let archived = rkyv::check_archived_root::<Measurement>(&bytes[..len]).unwrap();
+        assert_eq!(archived, &measurement);
+        assert_eq!(archived.co2eq_ppm, 412);
+        let deserialized: Measurement = archived.deserialize(&mut Infallible).unwrap();
+        assert_eq!(deserialized, measurement);
+
+        let baseline = Baseline::new(0x8A3C, 0x91F0);
+        let mut serializer = BufferSerializer::new(AlignedBytes([0u8; 16]));
+        serializer.serialize_value(&baseline).unwrap();
+        let len = serializer.pos();
+        let bytes = serializer.into_inner();
+        let archived = rkyv::check_archived_root::<Baseline>(&bytes[..len]).unwrap();
+        assert_eq!(archived, &baseline);
+        let deserialized: Baseline = archived.deserialize(&mut Infallible).unwrap();
+        assert_eq!(deserialized, baseline);
+    }
+
     #[test]
     fn humidity_as_bytes() {
         assert_eq!(Humidity::new(0x00, 0x01).unwrap().as_bytes(), [0x00, 0x01]);
@@ -191,6 +749,19 @@ mod tests {
     }
 
     #[test]
+    fn humidity_new_const() {
+        const HUMIDITY: Humidity = Humidity::new_const(0x10, 0x80);
+        assert_eq!(HUMIDITY, Humidity::new(0x10, 0x80).unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn humidity_new_const_zero() {
+        Humidity::new_const(0, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "humidity-float")]
     fn humidity_from_f32_ok() {
         assert_eq!(
             Humidity::from_f32(0.00390625f32),
@@ -211,6 +782,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "humidity-float")]
     fn humidity_from_f32_err() {
         assert_eq!(Humidity::from_f32(-3.0f32), Err(HumidityError::OutOfRange));
         assert_eq!(Humidity::from_f32(0.0f32), Err(HumidityError::ZeroValue));
@@ -218,6 +790,85 @@ mod tests {
         assert_eq!(Humidity::from_f32(f32::NAN), Err(HumidityError::OutOfRange));
     }
 
+    #[test]
+    #[cfg(feature = "humidity-float")]
+    fn humidity_from_dew_point() {
+        let humidity: f32 = Humidity::from_dew_point(25.0, 15.0).unwrap().into();
+        assert!((humidity - 12.37).abs() < 0.01, "{}", humidity);
+        // Saturated air
+        let humidity: f32 = Humidity::from_dew_point(20.0, 20.0).unwrap().into();
+        assert!((humidity - 17.24).abs() < 0.01, "{}", humidity);
+        let humidity: f32 = Humidity::from_dew_point(0.0, -10.0).unwrap().into();
+        assert!((humidity - 2.28).abs() < 0.01, "{}", humidity);
+    }
+
+    #[test]
+    #[cfg(feature = "humidity-float")]
+    fn humidity_from_dew_point_err() {
+        assert_eq!(
+            Humidity::from_dew_point(15.0, 25.0),
+            Err(HumidityError::OutOfRange)
+        );
+        assert_eq!(
+            Humidity::from_dew_point(f32::NAN, 10.0),
+            Err(HumidityError::OutOfRange)
+        );
+        assert_eq!(
+            Humidity::from_dew_point(20.0, f32::NEG_INFINITY),
+            Err(HumidityError::OutOfRange)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "humidity-float")]
+    fn humidity_from_relative_humidity_at_pressure() {
+        let sea_level: f32 = Humidity::from_relative_humidity_at_pressure(25.0, 50.0, 1013.25)
+            .unwrap()
+            .into();
+        assert!((sea_level - 11.53).abs() < 0.01, "{}", sea_level);
+        // The enhancement factor is smaller at lower pressure
+        let altitude: f32 = Humidity::from_relative_humidity_at_pressure(25.0, 50.0, 700.0)
+            .unwrap()
+            .into();
+        assert!((altitude - 11.52).abs() < 0.01, "{}", altitude);
+        assert!(altitude < sea_level);
+    }
+
+    #[test]
+    #[cfg(feature = "humidity-float")]
+    fn humidity_from_relative_humidity_at_pressure_err() {
+        for (temp_c, relative_humidity, pressure_hpa) in [
+            (25.0, 101.0, 1013.25),
+            (25.0, -1.0, 1013.25),
+            (25.0, f32::NAN, 1013.25),
+            (f32::INFINITY, 50.0, 1013.25),
+            (25.0, 50.0, 0.0),
+            (25.0, 50.0, f32::NAN),
+        ] {
+            assert_eq!(
+                Humidity::from_relative_humidity_at_pressure(
+                    temp_c,
+                    relative_humidity,
+                    pressure_hpa
+                ),
+                Err(HumidityError::OutOfRange)
+            );
+        }
+        assert_eq!(
+            Humidity::from_relative_humidity_at_pressure(25.0, 0.0, 1013.25),
+            Err(HumidityError::ZeroValue)
+        );
+    }
+
+    #[test]
+    fn humidity_default() {
+        assert_eq!(Humidity::DEFAULT.as_bytes(), [0x0B, 0x92]);
+        assert!((f32::from(Humidity::DEFAULT) - 11.57).abs() < 0.001);
+        assert!(Humidity::DEFAULT.is_default());
+        assert!(Humidity::new(0x0B, 0x92).unwrap().is_default());
+        assert!(!Humidity::new(0x0B, 0x91).unwrap().is_default());
+    }
+
     #[test]
     fn humidity_into_f32() {
         let float: f32 = Humidity::new(0x00, 0x01).unwrap().into();
@@ -0,0 +1,122 @@
+//! Conversions into [`uom`] quantities.
+
+#[allow(unused_imports)] // Required for no_std
+use num_traits::Float;
+use uom::si::{
+    f32::{MassConcentration, MolarConcentration, Pressure, Ratio, ThermodynamicTemperature},
+    mass_concentration::gram_per_cubic_meter,
+    molar_concentration::mole_per_cubic_meter,
+    pressure::pascal,
+    ratio::{part_per_billion, part_per_million, ratio},
+    thermodynamic_temperature::kelvin,
+};
+
+use crate::{types::HumidityError, Humidity, Measurement};
+
+/// The molar gas constant in J/(K · mol).
+const GAS_CONSTANT: f32 = 8.314_463;
+
+/// Return the amount-of-substance concentration of a gas with the mole
+/// fraction `fraction` in an ideal gas at `temperature` and `pressure`.
+fn molar_concentration(
+    fraction: Ratio,
+    temperature: ThermodynamicTemperature,
+    pressure: Pressure,
+) -> MolarConcentration {
+    // c = x · p / (R · T)
+    let concentration = fraction.get::<ratio>() * pressure.get::<pascal>()
+        / (GAS_CONSTANT * temperature.get::<kelvin>());
+    MolarConcentration::new::<mole_per_cubic_meter>(concentration)
+}
+
+impl Measurement {
+    /// Return the CO₂eq value as a mole fraction.
+    pub fn co2eq(&self) -> Ratio {
+        Ratio::new::<part_per_million>(f32::from(self.co2eq_ppm))
+    }
+
+    /// Return the TVOC value as a mole fraction.
+    pub fn tvoc(&self) -> Ratio {
+        Ratio::new::<part_per_billion>(f32::from(self.tvoc_ppb))
+    }
+
+    /// Return the CO₂eq value as an amount-of-substance concentration in
+    /// air at the given `temperature` and `pressure`, assuming an ideal
+    /// gas.
+    pub fn co2eq_concentration(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+    ) -> MolarConcentration {
+        molar_concentration(self.co2eq(), temperature, pressure)
+    }
+
+    /// Return the TVOC value as an amount-of-substance concentration in
+    /// air at the given `temperature` and `pressure`, assuming an ideal
+    /// gas.
+    pub fn tvoc_concentration(
+        &self,
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+    ) -> MolarConcentration {
+        molar_concentration(self.tvoc(), temperature, pressure)
+    }
+}
+
+impl From<Humidity> for MassConcentration {
+    fn from(humidity: Humidity) -> Self {
+        MassConcentration::new::<gram_per_cubic_meter>(humidity.into())
+    }
+}
+
+impl TryFrom<MassConcentration> for Humidity {
+    type Error = HumidityError;
+
+    /// Convert an absolute humidity.
+    ///
+    /// Unlike [`Humidity::from_f32()`], the value is rounded to the nearest
+    /// 1/256 g/m³, since the conversion from the SI base unit (kg/m³) is
+    /// inexact.
+    fn try_from(humidity: MassConcentration) -> Result<Self, Self::Error> {
+        let fixed_point = (humidity.get::<gram_per_cubic_meter>() * 256.0).round();
+        Humidity::from_f32(fixed_point / 256.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uom::si::{
+        mass_concentration::milligram_per_cubic_meter,
+        molar_concentration::millimole_per_cubic_meter, pressure::hectopascal, ratio::percent,
+        thermodynamic_temperature::degree_celsius,
+    };
+
+    use super::*;
+
+    #[test]
+    fn measurement() {
+        let measurement = Measurement::new(1_000, 500);
+        assert_eq!(measurement.co2eq().get::<percent>(), 0.1);
+        assert_eq!(measurement.tvoc().get::<part_per_million>(), 0.5);
+
+        // 1 mol of an ideal gas at 0 °C and 1013.25 hPa fills 22.414 l
+        let temperature = ThermodynamicTemperature::new::<degree_celsius>(0.0);
+        let pressure = Pressure::new::<hectopascal>(1013.25);
+        let co2eq = measurement.co2eq_concentration(temperature, pressure);
+        assert!((co2eq.get::<millimole_per_cubic_meter>() - 1000.0 / 22.414).abs() < 0.01);
+        let tvoc = measurement.tvoc_concentration(temperature, pressure);
+        assert!((tvoc.get::<millimole_per_cubic_meter>() - 0.5 / 22.414).abs() < 0.0001);
+    }
+
+    #[test]
+    fn humidity() {
+        let humidity = Humidity::new(0x10, 0x80).unwrap();
+        let concentration = MassConcentration::from(humidity);
+        assert_eq!(concentration.get::<milligram_per_cubic_meter>(), 16_500.0);
+        assert_eq!(Humidity::try_from(concentration), Ok(humidity));
+        assert_eq!(
+            Humidity::try_from(MassConcentration::new::<gram_per_cubic_meter>(300.0)),
+            Err(HumidityError::OutOfRange)
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Hardware-in-the-loop tests against a real SGP30 on a Linux I²C bus.
+//!
+//! The tests are ignored by default, run them on a device with a connected
+//! sensor (e.g. a Raspberry Pi) with:
+//!
+//! ```text
+//! cargo test --features hil --test hil -- --ignored --test-threads 1
+//! ```
+//!
+//! The I²C device defaults to `/dev/i2c-1` and can be changed with the
+//! `SGP30_I2C_DEV` environment variable. Every test restarts the air quality
+//! measurement, so the baseline of the sensor is lost. The self-test soft
+//! resets the sensor first (using the I²C general call, which also resets
+//! other devices on the bus supporting it), since it must not run during
+//! the air quality measurement.
+
+use std::{
+    env,
+    sync::{Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+use embedded_hal::i2c::I2c;
+use linux_embedded_hal::{Delay, I2cdev};
+#[cfg(feature = "humidity-float")]
+use sgp30::Humidity;
+use sgp30::{Baseline, Measurement, ProductType, Sgp30};
+
+/// The I²C address of the sensor.
+const ADDRESS: u8 = 0x58;
+
+/// Serializes the tests, since they all use the same sensor.
+static SENSOR: Mutex<()> = Mutex::new(());
+
+/// Open the sensor, holding the lock until the returned guard is dropped.
+fn sensor() -> (Sgp30<I2cdev, Delay>, MutexGuard<'static, ()>) {
+    let guard = SENSOR.lock().unwrap_or_else(|e| e.into_inner());
+    let path = env::var("SGP30_I2C_DEV").unwrap_or_else(|_| "/dev/i2c-1".into());
+    let dev = I2cdev::new(&path).unwrap_or_else(|e| panic!("cannot open {path}: {e}"));
+    (Sgp30::new(dev, ADDRESS, Delay), guard)
+}
+
+/// Sleep until 1 s after `start` of the previous measurement.
+fn wait_for_next_interval(start: Instant) {
+    thread::sleep(Duration::from_secs(1).saturating_sub(start.elapsed()));
+}
+
+/// Soft reset the sensor using the I²C general call, which stops the air
+/// quality measurement. Note that this resets all devices on the bus which
+/// support the general call.
+fn soft_reset(sgp: Sgp30<I2cdev, Delay>) -> Sgp30<I2cdev, Delay> {
+    let mut dev = sgp.destroy();
+    dev.write(0x00u8, &[0x06]).unwrap();
+    thread::sleep(Duration::from_millis(1));
+    Sgp30::new(dev, ADDRESS, Delay)
+}
+
+/// Test the serial number, feature set and self-test
+#[test]
+#[ignore = "requires an SGP30 sensor"]
+fn identification_and_selftest() {
+    let (sgp, _guard) = sensor();
+    // The self-test must not run while the air quality measurement, started
+    // by a previous test, is active
+    let mut sgp = soft_reset(sgp);
+    let serial = sgp.serial().unwrap();
+    assert_ne!(serial, [0; 6]);
+    assert_ne!(serial, [0xFF; 6]);
+    let feature_set = sgp.get_feature_set().unwrap();
+    assert_eq!(feature_set.product_type, ProductType::Sgp30);
+    assert!(sgp.selftest().unwrap());
+}
+
+/// Test the measurements during the initialization phase and afterwards
+#[test]
+#[ignore = "requires an SGP30 sensor"]
+fn init_and_measure() {
+    let (mut sgp, _guard) = sensor();
+    sgp.init().unwrap();
+    for _ in 0..5 {
+        let start = Instant::now();
+        assert_eq!(sgp.measure().unwrap(), Measurement::WARM_UP);
+        let signals = sgp.measure_raw_signals().unwrap();
+        assert!(signals.h2 > 0 && signals.ethanol > 0);
+        wait_for_next_interval(start);
+    }
+    // The initialization phase takes 15 s, but the sensor may keep
+    // reporting the same values in clean air
+    let start = Instant::now();
+    match sgp.wait_for_live_data(30) {
+        Ok(measurement) => assert_ne!(measurement, Measurement::WARM_UP),
+        Err(e) => assert_eq!(e.kind(), sgp30::ErrorKind::Timeout),
+    }
+    assert!(start.elapsed() >= Duration::from_secs(9));
+}
+
+/// Test writing and reading back the baseline
+#[test]
+#[ignore = "requires an SGP30 sensor"]
+fn baseline_round_trip() {
+    let (mut sgp, _guard) = sensor();
+    sgp.init().unwrap();
+    let baseline = Baseline::new(0x8A3C, 0x91F0);
+    sgp.set_baseline_verified(&baseline).unwrap();
+    assert_eq!(sgp.get_baseline().unwrap(), baseline);
+}
+
+/// Test setting and disabling the humidity compensation
+#[cfg(feature = "humidity-float")]
+#[test]
+#[ignore = "requires an SGP30 sensor"]
+fn humidity_compensation() {
+    let (mut sgp, _guard) = sensor();
+    sgp.init().unwrap();
+    let humidity = Humidity::from_f32(10.5).unwrap();
+    sgp.set_humidity(Some(&humidity)).unwrap();
+    assert!(sgp.humidity_compensation_active());
+    let start = Instant::now();
+    sgp.measure().unwrap();
+    wait_for_next_interval(start);
+    sgp.disable_humidity_compensation().unwrap();
+    assert!(!sgp.humidity_compensation_active());
+    sgp.measure().unwrap();
+}